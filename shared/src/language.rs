@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use grammar::grammar::GrammarConfiguration;
 use serde_json::Value;
 
@@ -40,23 +42,58 @@ pub struct Language {
     pub(crate) file_names: &'static [&'static str],
     pub(crate) lsp_language_id: Option<LanguageId>,
     pub(crate) lsp_command: Option<LspCommand>,
+    /// Language servers that run alongside the main one, e.g. linters
+    pub(crate) secondary_lsp_commands: &'static [LspCommand],
     pub(crate) tree_sitter_grammar_config: Option<GrammarConfig>,
     pub(crate) highlight_query: Option<&'static str>,
     pub(crate) formatter_command: Option<Command>,
+    /// The prefix of single-line comments, such as `//` or `#`
+    pub(crate) line_comment_prefix: Option<&'static str>,
+    /// Overrides `DEFAULT_AUTO_PAIRS`
+    pub(crate) auto_pairs: Option<&'static [(char, char)]>,
+    /// The kinds of syntax nodes that define a named item, such as functions and classes
+    pub(crate) definition_kinds: &'static [&'static str],
 }
 
+/// The opening and closing characters that are inserted together.
+pub const DEFAULT_AUTO_PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LspCommand {
     pub(crate) command: Command,
     pub(crate) initialization_options: Option<&'static str>,
+    /// Required if another server of the same language runs the same program
+    pub(crate) name: Option<&'static str>,
 }
 impl LspCommand {
     pub const fn default() -> LspCommand {
         LspCommand {
             command: Command::default(),
             initialization_options: None,
+            name: None,
         }
     }
+
+    /// Identifies the language server among the servers of the same language,
+    /// which is the program of the command unless a name is given.
+    pub fn name(&self) -> &'static str {
+        self.name.unwrap_or(self.command.0)
+    }
+
+    pub fn process_command(&self) -> ProcessCommand {
+        ProcessCommand::new(self.command.0, self.command.1)
+    }
+
+    pub fn initialization_options(&self) -> Option<Value> {
+        serde_json::from_str(self.initialization_options?).ok()
+    }
 }
 
 impl Language {
@@ -67,8 +104,12 @@ impl Language {
             lsp_language_id: None,
             highlight_query: None,
             lsp_command: None,
+            secondary_lsp_commands: &[],
             tree_sitter_grammar_config: None,
             formatter_command: None,
+            line_comment_prefix: None,
+            auto_pairs: None,
+            definition_kinds: &[],
         }
     }
 
@@ -97,7 +138,7 @@ impl Language {
     }
 
     pub fn initialization_options(&self) -> Option<Value> {
-        serde_json::from_str(self.lsp_command.clone()?.initialization_options?).ok()
+        self.lsp_command.as_ref()?.initialization_options()
     }
 
     pub fn tree_sitter_language(&self) -> Option<tree_sitter::Language> {
@@ -147,9 +188,16 @@ impl Language {
     }
 
     pub fn lsp_process_command(&self) -> Option<ProcessCommand> {
+        self.lsp_command.as_ref().map(LspCommand::process_command)
+    }
+
+    /// The main language server followed by the secondary language servers.
+    pub fn lsp_commands(&self) -> Vec<LspCommand> {
         self.lsp_command
-            .as_ref()
-            .map(|command| ProcessCommand::new(command.command.0, command.command.1))
+            .iter()
+            .chain(self.secondary_lsp_commands)
+            .cloned()
+            .collect()
     }
 
     pub fn tree_sitter_grammar_id(&self) -> Option<String> {
@@ -166,6 +214,25 @@ impl Language {
             .map(|command| ProcessCommand::new(command.0, command.1))
     }
 
+    pub fn line_comment_prefix(&self) -> Option<&'static str> {
+        self.line_comment_prefix
+    }
+
+    pub fn auto_pairs(&self) -> &'static [(char, char)] {
+        self.auto_pairs.unwrap_or(DEFAULT_AUTO_PAIRS)
+    }
+
+    pub fn definition_kinds(&self) -> &'static [&'static str] {
+        self.definition_kinds
+    }
+
+    pub fn with_definition_kinds(self, definition_kinds: &'static [&'static str]) -> Self {
+        Self {
+            definition_kinds,
+            ..self
+        }
+    }
+
     pub fn formatter(&self) -> Option<Formatter> {
         self.formatter_command().map(Formatter::from)
     }
@@ -192,6 +259,62 @@ pub(crate) fn from_filename(path: &CanonicalizedPath) -> Option<Language> {
         .map(|language| (*language).clone())
 }
 
+/// Extra file-extension to language mappings, which take precedence over
+/// the extensions defined in `LANGUAGES`.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageRegistry {
+    extensions: HashMap<String, Language>,
+}
+
+impl LanguageRegistry {
+    pub fn register(&mut self, extension: &str, language: Language) -> &mut Self {
+        self.extensions.insert(extension.to_string(), language);
+        self
+    }
+
+    /// Parses a comma-separated list of `extension=builtin_extension` pairs,
+    /// for example `mdc=md,jsonc=json`.
+    pub fn parse(mappings: &str) -> anyhow::Result<LanguageRegistry> {
+        let mut registry = LanguageRegistry::default();
+        for mapping in mappings.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+            let (extension, builtin_extension) = mapping.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid mapping {mapping:?}, expected `extension=builtin_extension`"
+                )
+            })?;
+            let language = from_extension(builtin_extension.trim()).ok_or_else(|| {
+                anyhow::anyhow!("Unsupported file extension: {:?}", builtin_extension.trim())
+            })?;
+            registry.register(extension.trim(), language);
+        }
+        Ok(registry)
+    }
+
+    pub fn find_by_extension(&self, extension: &str) -> Option<Language> {
+        self.extensions
+            .get(extension)
+            .cloned()
+            .or_else(|| from_extension(extension))
+    }
+
+    pub fn find_by_path(&self, path: &CanonicalizedPath) -> anyhow::Result<Language> {
+        if let Some(language) = path
+            .extension()
+            .and_then(|extension| self.find_by_extension(extension))
+            .or_else(|| from_filename(path))
+        {
+            return Ok(language);
+        }
+        match path.extension() {
+            Some(extension) => Err(anyhow::anyhow!("Unsupported file extension: {extension:?}")),
+            None => Err(anyhow::anyhow!(
+                "Unable to detect the language of {:?}, because it has no file extension",
+                path.display_absolute()
+            )),
+        }
+    }
+}
+
 use regex::Regex;
 
 /// Detect the language from the first line of the file content.
@@ -209,8 +332,11 @@ use regex::Regex;
 /// Spaces and other content on the line do not matter.
 pub fn from_content_directive(content: &str) -> Option<Language> {
     let first_line = content.lines().next()?;
+    if let Some(language) = language_from_shebang(first_line) {
+        return Some(language);
+    }
 
-    let re = Regex::new(r"(?:(?:^#!.*/)|(?:mode:)|(?:ft\s*=))\s*(\w+)").unwrap();
+    let re = Regex::new(r"(?:(?:mode:)|(?:ft\s*=))\s*(\w+)").unwrap();
     let language_id = re
         .captures(first_line)
         .and_then(|captures| captures.get(1).map(|mode| mode.as_str().to_string()));
@@ -227,6 +353,36 @@ pub fn from_content_directive(content: &str) -> Option<Language> {
     })
 }
 
+/// Detects the language of a shebang line, for example:
+/// `#!/bin/bash`, `#!/usr/bin/env python3` or `#!/usr/bin/env -S node --harmony`.
+pub fn language_from_shebang(first_line: &str) -> Option<Language> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let interpreter = words.next()?.rsplit('/').next()?;
+    let interpreter = if interpreter == "env" {
+        words.find(|word| !word.starts_with('-'))?
+    } else {
+        interpreter
+    };
+
+    // Strip version suffixes, such as `python3` or `lua5.1`
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let name = match name {
+        "sh" | "zsh" | "dash" | "ksh" => "bash",
+        "node" | "nodejs" => "javascript",
+        name => name,
+    };
+
+    LANGUAGES
+        .iter()
+        .find(|language| {
+            language
+                .lsp_language_id
+                .is_some_and(|lsp_id| lsp_id.0 == name)
+                || language.extensions.contains(&name)
+        })
+        .map(|language| (*language).clone())
+}
+
 #[cfg(test)]
 mod test_language {
     use super::*;
@@ -249,6 +405,51 @@ mod test_language {
         Ok(())
     }
 
+    #[test]
+    fn lsp_server_names_are_unique_within_each_language() {
+        for language in LANGUAGES {
+            let lsp_commands = language.lsp_commands();
+            let names = lsp_commands
+                .iter()
+                .map(LspCommand::name)
+                .collect::<std::collections::HashSet<_>>();
+            assert_eq!(names.len(), lsp_commands.len(), "{:?}", language.extensions);
+        }
+    }
+
+    #[test]
+    fn test_language_registry() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let path = |filename: &str| -> anyhow::Result<CanonicalizedPath> {
+            let path = tempdir.path().join(filename);
+            File::create(path.clone())?;
+            path.to_string_lossy().to_string().try_into()
+        };
+        let registry = LanguageRegistry::parse("mdc=md, jsonc=json")?;
+        assert_eq!(
+            registry
+                .find_by_path(&path("rules.mdc")?)?
+                .tree_sitter_grammar_id()
+                .unwrap(),
+            "markdown"
+        );
+
+        // Builtin extensions are still recognized
+        assert_eq!(
+            registry
+                .find_by_path(&path("hello.rs")?)?
+                .tree_sitter_grammar_id()
+                .unwrap(),
+            "rust"
+        );
+
+        let error = registry.find_by_path(&path("hello.unknown")?).unwrap_err();
+        assert!(error.to_string().contains("\"unknown\""));
+
+        assert!(LanguageRegistry::parse("mdc=nope").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_from_content_directive() -> anyhow::Result<()> {
         fn run_test_case(content: &str, expected_language_id: &'static str) -> anyhow::Result<()> {
@@ -266,7 +467,26 @@ mod test_language {
         run_test_case("-- tab_spaces: 5, mode: bash, use_tabs: false", "bash")?;
         run_test_case("-- tab_spaces: 5, mode:bash, use_tabs: false", "bash")?;
         run_test_case("-- vim: ft = bash", "bash")?;
+        run_test_case("#!/usr/bin/env python3\nprint(1)", "python")?;
 
         Ok(())
     }
+
+    #[test]
+    fn test_language_from_shebang() {
+        fn run_test_case(first_line: &str, expected_language_id: Option<&'static str>) {
+            let result = language_from_shebang(first_line)
+                .and_then(|language| language.tree_sitter_grammar_id());
+            assert_eq!(result.as_deref(), expected_language_id);
+        }
+
+        run_test_case("#!/bin/bash", Some("bash"));
+        run_test_case("#!/bin/sh", Some("bash"));
+        run_test_case("#!/usr/bin/env python3", Some("python"));
+        run_test_case("#!/usr/bin/env -S python3 -u", Some("python"));
+        run_test_case("#! /usr/bin/env ruby", Some("ruby"));
+        run_test_case("#!/usr/bin/env node", Some("javascript"));
+        run_test_case("#!/usr/bin/env unknown-interpreter", None);
+        run_test_case("// mode: python", None);
+    }
 }
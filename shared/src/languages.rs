@@ -63,6 +63,7 @@ const fn bash() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -82,6 +83,7 @@ const fn fish() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -101,6 +103,7 @@ const fn c() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
@@ -118,6 +121,7 @@ const fn unison() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("--"),
         ..Language::new()
     }
 }
@@ -131,6 +135,9 @@ const fn common_lisp() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some(";"),
+        // Single quotes are used for quoting
+        auto_pairs: Some(&[('(', ')'), ('"', '"')]),
         ..Language::new()
     }
 }
@@ -153,6 +160,7 @@ const fn cpp() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
@@ -206,6 +214,7 @@ const fn dockerfile() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -225,6 +234,7 @@ const fn elixir() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -238,6 +248,7 @@ const fn gitattributes() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -251,6 +262,7 @@ const fn gitcommit() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -264,6 +276,7 @@ const fn gitconfig() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -277,6 +290,7 @@ const fn gitignore() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -290,6 +304,7 @@ const fn gitrebase() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -309,6 +324,7 @@ const fn gleam() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
@@ -328,6 +344,12 @@ const fn golang() -> Language {
             subpath: None,
             commit: "master",
         }),
+        line_comment_prefix: Some("//"),
+        definition_kinds: &[
+            "function_declaration",
+            "method_declaration",
+            "type_declaration",
+        ],
         ..Language::new()
     }
 }
@@ -347,6 +369,7 @@ const fn graphql() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -360,6 +383,7 @@ const fn hare() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
@@ -419,10 +443,18 @@ const fn idris() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("--"),
         ..Language::new()
     }
 }
 
+const JAVASCRIPT_DEFINITION_KINDS: &[&str] = &[
+    "function_declaration",
+    "generator_function_declaration",
+    "class_declaration",
+    "method_definition",
+];
+
 const fn javascript() -> Language {
     Language {
         extensions: &["js", "mjs", "cjs"],
@@ -438,6 +470,8 @@ const fn javascript() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
+        definition_kinds: JAVASCRIPT_DEFINITION_KINDS,
         ..Language::new()
     }
 }
@@ -457,6 +491,8 @@ const fn javascript_react() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
+        definition_kinds: JAVASCRIPT_DEFINITION_KINDS,
         ..Language::new()
     }
 }
@@ -484,6 +520,7 @@ const fn just() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -503,6 +540,7 @@ const fn lua() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("--"),
         ..Language::new()
     }
 }
@@ -541,6 +579,7 @@ const fn nix() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -553,6 +592,11 @@ const fn python() -> Language {
             command: Command("pyright-langserver", &["--stdio"]),
             ..LspCommand::default()
         }),
+        secondary_lsp_commands: &[LspCommand {
+            command: Command("ruff", &["server"]),
+            initialization_options: None,
+            name: None,
+        }],
         lsp_language_id: Some(LanguageId::new("python")),
         tree_sitter_grammar_config: Some(GrammarConfig {
             id: "python",
@@ -560,6 +604,8 @@ const fn python() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
+        definition_kinds: &["function_definition", "class_definition"],
         ..Language::new()
     }
 }
@@ -582,6 +628,7 @@ const fn rescript() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
@@ -604,6 +651,7 @@ const fn ruby() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -620,6 +668,7 @@ const fn roc() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -639,6 +688,17 @@ const fn rust() -> Language {
             commit: "e86119bdb4968b9799f6a014ca2401c178d54b5f",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
+        // Single quotes are used for lifetimes and characters
+        auto_pairs: Some(&[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')]),
+        definition_kinds: &[
+            "function_item",
+            "impl_item",
+            "trait_item",
+            "struct_item",
+            "enum_item",
+            "mod_item",
+        ],
         ..Language::new()
     }
 }
@@ -653,6 +713,7 @@ const fn sql() -> Language {
             commit: "25f94f998de79bae9df28add9782f9ea6ea0e2b8",
             subpath: None,
         }),
+        line_comment_prefix: Some("--"),
         ..Language::new()
     }
 }
@@ -672,6 +733,7 @@ const fn swift() -> Language {
             commit: "with-generated-files",
             subpath: None,
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
@@ -685,6 +747,7 @@ const fn toml() -> Language {
             commit: "master",
             subpath: None,
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -698,10 +761,22 @@ const fn tree_sitter_query() -> Language {
             commit: "main",
             subpath: None,
         }),
+        line_comment_prefix: Some(";"),
         ..Language::new()
     }
 }
 
+const TYPESCRIPT_DEFINITION_KINDS: &[&str] = &[
+    "function_declaration",
+    "generator_function_declaration",
+    "class_declaration",
+    "abstract_class_declaration",
+    "method_definition",
+    "interface_declaration",
+    "enum_declaration",
+    "internal_module",
+];
+
 const fn typescript() -> Language {
     Language {
         extensions: &["ts", "mts", "cts"],
@@ -717,6 +792,8 @@ const fn typescript() -> Language {
             commit: "master",
             subpath: Some("typescript"),
         }),
+        line_comment_prefix: Some("//"),
+        definition_kinds: TYPESCRIPT_DEFINITION_KINDS,
         ..Language::new()
     }
 }
@@ -736,6 +813,8 @@ const fn typescript_react() -> Language {
             commit: "master",
             subpath: Some("tsx"),
         }),
+        line_comment_prefix: Some("//"),
+        definition_kinds: TYPESCRIPT_DEFINITION_KINDS,
         ..Language::new()
     }
 }
@@ -762,6 +841,7 @@ const fn yaml() -> Language {
             subpath: None,
             commit: "master",
         }),
+        line_comment_prefix: Some("#"),
         ..Language::new()
     }
 }
@@ -781,6 +861,7 @@ const fn zig() -> Language {
             subpath: None,
             commit: "master",
         }),
+        line_comment_prefix: Some("//"),
         ..Language::new()
     }
 }
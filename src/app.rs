@@ -22,12 +22,14 @@ use crate::{
     layout::Layout,
     list::{self, grep::RegexConfig, WalkBuilderConfig},
     lsp::{
+        call_hierarchy::CallDirection,
         completion::CompletionItem,
         goto_definition_response::GotoDefinitionResponse,
         manager::LspManager,
         process::{FromEditor, LspNotification, ResponseContext},
+        server_capability::ServerCapability,
         symbols::Symbols,
-        workspace_edit::WorkspaceEdit,
+        workspace_edit::{TextDocumentEdit, WorkspaceEdit},
     },
     position::Position,
     quickfix_list::{Location, QuickfixList, QuickfixListItem, QuickfixListType},
@@ -38,11 +40,13 @@ use crate::{
 };
 use event::event::Event;
 use itertools::{Either, Itertools};
+use lsp_types::FileChangeType;
 use name_variant::NamedVariant;
 use shared::{canonicalized_path::CanonicalizedPath, language::Language};
 use std::{
     any::TypeId,
     cell::RefCell,
+    collections::HashMap,
     path::{Path, PathBuf},
     rc::Rc,
     sync::{
@@ -65,6 +69,11 @@ pub(crate) struct App<T: Frontend> {
 
     lsp_manager: LspManager,
     enable_lsp: bool,
+    /// The cursor of the last document highlight request,
+    /// so that document highlights are only requested when the cursor moves.
+    document_highlight_cursor: Option<(CanonicalizedPath, Position)>,
+    /// The calls to request once the call hierarchy item at the cursor is prepared.
+    call_direction: Option<CallDirection>,
 
     working_directory: CanonicalizedPath,
     global_title: Option<String>,
@@ -94,6 +103,20 @@ pub(crate) enum StatusLineComponent {
     KeyboardLayout,
     ViewAlignment,
     Reveal,
+    /// The ongoing progresses of the language servers, such as indexing
+    LspProgress,
+    /// The status of every language server, such as whether it is initializing
+    LspServerStatus,
+    /// The requests of the current file that the language servers are yet to respond to
+    LspPendingRequests,
+    /// Shown when the latest reparse of the current file timed out,
+    /// so its highlights do not reflect the latest content
+    StaleSyntaxTree,
+    /// The duration of the latest reparse of the current file
+    #[cfg(feature = "metrics")]
+    ParseDuration,
+    /// The names of the definitions enclosing the cursor, such as `Foo › bar`
+    Breadcrumbs,
 }
 
 impl<T: Frontend> App<T> {
@@ -126,11 +149,13 @@ impl<T: Frontend> App<T> {
         status_line_components: Vec<StatusLineComponent>,
     ) -> anyhow::Result<App<T>> {
         let dimension = frontend.lock().unwrap().get_terminal_dimension()?;
-        let app = App {
+        let mut app = App {
             context: Context::new(working_directory.clone()),
             receiver,
             lsp_manager: LspManager::new(sender.clone(), working_directory.clone()),
             enable_lsp: true,
+            document_highlight_cursor: None,
+            call_direction: None,
             sender,
             layout: Layout::new(
                 dimension.decrement_height(GLOBAL_TITLE_BAR_HEIGHT),
@@ -144,6 +169,16 @@ impl<T: Frontend> App<T> {
             last_action_description: None,
             last_action_short_description: None,
         };
+        if let Some(timeout) = app.context.lsp_request_timeout() {
+            app.lsp_manager.set_request_timeout(timeout)?;
+        }
+        if let Some(debounce) = app.context.lsp_change_debounce() {
+            app.lsp_manager.set_change_debounce(debounce)?;
+        }
+        for (language, settings) in app.context.lsp_settings() {
+            app.lsp_manager
+                .update_configuration(language.clone(), settings.clone())?;
+        }
         Ok(app)
     }
     fn update_highlighted_spans(
@@ -216,6 +251,18 @@ impl<T: Frontend> App<T> {
         frontend.disable_raw_mode()?;
         frontend.disable_mouse_capture()?;
         // self.lsp_manager.shutdown();
+        if let Err(error) = self
+            .context
+            .save_searches(&crate::context::default_search_history_file())
+        {
+            log::error!("Failed to save search history: {error:?}");
+        }
+        if let Err(error) = self
+            .context
+            .save_registers(&crate::context::default_registers_file())
+        {
+            log::error!("Failed to save registers: {error:?}");
+        }
 
         std::process::exit(0);
     }
@@ -241,12 +288,37 @@ impl<T: Frontend> App<T> {
                     .unwrap_or_else(|e| {
                         self.show_global_info(Info::new("ERROR".to_string(), e.to_string()))
                     });
+                self.request_document_highlight().unwrap_or_else(|error| {
+                    log::error!("Failed to request document highlights: {error:?}")
+                });
             }
         }
 
         Ok(false)
     }
 
+    /// Document highlights are requested automatically as the cursor moves,
+    /// so they are silently skipped if the language server does not support them.
+    fn request_document_highlight(&mut self) -> anyhow::Result<()> {
+        if !self.enable_lsp {
+            return Ok(());
+        }
+        let Some(params) = self
+            .current_component()
+            .borrow()
+            .editor()
+            .get_request_params()
+        else {
+            return Ok(());
+        };
+        let cursor = Some((params.path.clone(), params.position));
+        if self.document_highlight_cursor == cursor {
+            return Ok(());
+        }
+        self.document_highlight_cursor = cursor;
+        self.lsp_manager.request_document_highlight(params)
+    }
+
     /// Return true if there's no more windows
     fn should_quit(&mut self) -> bool {
         self.layout.components().is_empty()
@@ -372,6 +444,40 @@ impl<T: Frontend> App<T> {
                                 }
                                 .to_string()
                             }),
+                        StatusLineComponent::LspProgress => self.lsp_manager.display_progresses(),
+                        StatusLineComponent::LspServerStatus => {
+                            self.lsp_manager.display_server_statuses()
+                        }
+                        StatusLineComponent::LspPendingRequests => self
+                            .current_component()
+                            .borrow()
+                            .path()
+                            .and_then(|path| self.lsp_manager.display_pending_requests(&path)),
+                        StatusLineComponent::StaleSyntaxTree => self
+                            .current_component()
+                            .borrow()
+                            .editor()
+                            .buffer()
+                            .is_tree_stale()
+                            .then(|| "Stale Highlights".to_string()),
+                        #[cfg(feature = "metrics")]
+                        StatusLineComponent::ParseDuration => {
+                            let component = self.current_component();
+                            let component = component.borrow();
+                            let buffer = component.editor().buffer();
+                            buffer
+                                .last_parse_duration()
+                                .zip(buffer.average_parse_duration())
+                                .map(|(last, average)| format!("Parse {last:?} (avg {average:?})"))
+                        }
+                        StatusLineComponent::Breadcrumbs => {
+                            let component = self.current_component();
+                            let component = component.borrow();
+                            let editor = component.editor();
+                            let breadcrumbs =
+                                editor.buffer().breadcrumbs(editor.get_cursor_char_index());
+                            (!breadcrumbs.is_empty()).then(|| breadcrumbs.join(" › "))
+                        }
                     })
                     .join(" │ ")
             });
@@ -423,7 +529,7 @@ impl<T: Frontend> App<T> {
         let mut frontend = self.frontend.lock().unwrap();
         frontend.hide_cursor()?;
         let cursor = screen.cursor();
-        frontend.render_screen(screen)?;
+        frontend.render_screen(screen, self.context.theme())?;
         if let Some(cursor) = cursor {
             frontend.show_cursor(&cursor)?;
         }
@@ -473,6 +579,12 @@ impl<T: Frontend> App<T> {
                 if_current_not_found,
             } => self.open_search_prompt(scope, if_current_not_found)?,
             Dispatch::OpenPipeToShellPrompt => self.open_pipe_to_shell_prompt()?,
+            Dispatch::OpenCopyToRegisterPrompt => {
+                self.open_register_prompt("Copy to register", DispatchPrompt::CopyToRegister)?
+            }
+            Dispatch::OpenPasteFromRegisterPrompt => {
+                self.open_register_prompt("Paste from register", DispatchPrompt::PasteFromRegister)?
+            }
             Dispatch::OpenFile { path, owner, focus } => {
                 self.open_file(&path, owner, true, focus)?;
             }
@@ -493,13 +605,8 @@ impl<T: Frontend> App<T> {
             }
             Dispatch::ResolveCompletionItem(completion_item) => {
                 if let Some(params) = self.get_request_params() {
-                    self.lsp_manager.send_message(
-                        params.path.clone(),
-                        FromEditor::CompletionItemResolve {
-                            completion_item,
-                            params,
-                        },
-                    )?
+                    self.lsp_manager
+                        .resolve_completion_item(params, completion_item)?
                 }
             }
             Dispatch::RequestReferences {
@@ -549,6 +656,13 @@ impl<T: Frontend> App<T> {
                     )?;
                 }
             }
+            Dispatch::RequestCalls { direction, scope } => {
+                if let Some(params) = self.get_request_params() {
+                    self.call_direction = Some(direction);
+                    self.lsp_manager
+                        .prepare_call_hierarchy(params.set_kind(Some(scope)))?;
+                }
+            }
             Dispatch::RequestImplementations(scope) => {
                 if let Some(params) = self.get_request_params() {
                     let params = params
@@ -573,13 +687,27 @@ impl<T: Frontend> App<T> {
             }
             Dispatch::RequestDocumentSymbols => {
                 if let Some(params) = self.get_request_params() {
-                    let params = params.set_description("Document Symbols");
-                    self.lsp_manager.send_message(
-                        params.path.clone(),
-                        FromEditor::TextDocumentDocumentSymbol(params),
-                    )?;
+                    if self
+                        .lsp_manager
+                        .supports(&params.path, ServerCapability::DocumentSymbol)
+                    {
+                        let params = params.set_description("Document Symbols");
+                        self.lsp_manager.send_message(
+                            params.path.clone(),
+                            FromEditor::TextDocumentDocumentSymbol(params),
+                        )?;
+                    } else {
+                        let symbols = Symbols::try_from_syntax_tree(
+                            self.current_component().borrow().editor().buffer(),
+                        )?;
+                        self.open_symbol_picker(symbols)?;
+                    }
                 }
             }
+            Dispatch::OpenWorkspaceSymbolPrompt => self.open_workspace_symbol_prompt()?,
+            Dispatch::RequestWorkspaceSymbols { query } => {
+                self.lsp_manager.request_workspace_symbols(query)?
+            }
             Dispatch::PrepareRename => {
                 if let Some(params) = self.get_request_params() {
                     self.lsp_manager.send_message(
@@ -596,17 +724,55 @@ impl<T: Frontend> App<T> {
                     )?;
                 }
             }
+            Dispatch::RequestFormatting => {
+                if let Some(params) = self.get_request_params() {
+                    self.lsp_manager.send_message(
+                        params.path.clone(),
+                        FromEditor::TextDocumentFormatting(params),
+                    )?;
+                }
+            }
+            Dispatch::RequestRangeFormatting => {
+                if let Some(params) = self.get_request_params() {
+                    let range = self
+                        .current_component()
+                        .borrow()
+                        .editor()
+                        .primary_selection_position_range()?;
+                    self.lsp_manager.send_message(
+                        params.path.clone(),
+                        FromEditor::TextDocumentRangeFormatting { params, range },
+                    )?;
+                }
+            }
             Dispatch::RequestCodeAction { diagnostics } => {
                 if let Some(params) = self.get_request_params() {
+                    let range = self
+                        .current_component()
+                        .borrow()
+                        .editor()
+                        .primary_selection_position_range()?;
                     self.lsp_manager.send_message(
                         params.path.clone(),
                         FromEditor::TextDocumentCodeAction {
                             params,
+                            range,
                             diagnostics,
                         },
                     )?;
                 }
             }
+            Dispatch::ResolveCodeAction(code_action) => {
+                if let Some(params) = self.get_request_params() {
+                    self.lsp_manager.send_message(
+                        params.path.clone(),
+                        FromEditor::CodeActionResolve {
+                            params,
+                            code_action,
+                        },
+                    )?;
+                }
+            }
             Dispatch::RequestSignatureHelp => {
                 if let Some(params) = self.get_request_params() {
                     self.lsp_manager.send_message(
@@ -621,6 +787,8 @@ impl<T: Frontend> App<T> {
                 language,
                 component_id,
                 batch_id,
+                version,
+                changes,
             } => {
                 if let Some(language) = language {
                     self.request_syntax_highlight(
@@ -635,10 +803,16 @@ impl<T: Frontend> App<T> {
                         path.clone(),
                         FromEditor::TextDocumentDidChange {
                             content,
-                            file_path: path,
-                            version: 2,
+                            file_path: path.clone(),
+                            version,
+                            changes,
                         },
                     )?;
+                    let component = self.current_component();
+                    let component = component.borrow();
+                    if component.editor().path().as_ref() == Some(&path) {
+                        self.request_document_annotations(component.editor())?;
+                    }
                 }
             }
             Dispatch::DocumentDidSave { path } => {
@@ -687,6 +861,7 @@ impl<T: Frontend> App<T> {
             } => self
                 .context
                 .set_clipboard_content(contents, use_system_clipboard)?,
+            Dispatch::SetRegister { name, content } => self.context.set_register(name, content),
             Dispatch::SetGlobalMode(mode) => self.set_global_mode(mode),
 
             #[cfg(test)]
@@ -763,9 +938,15 @@ impl<T: Frontend> App<T> {
                 let context = std::mem::take(&mut self.context);
                 self.context = context.set_theme(theme.clone());
             }
+            Dispatch::ToggleThemeVariant => self.context.toggle_theme_variant(),
             Dispatch::SetThemeFromDescriptor(theme_descriptor) => {
                 let context = std::mem::take(&mut self.context);
-                self.context = context.set_theme(theme_descriptor.to_theme());
+                // The color depth is configured by the environment, not by the theme
+                let theme = crate::themes::Theme {
+                    color_depth: context.theme().color_depth,
+                    ..theme_descriptor.to_theme()
+                };
+                self.context = context.set_theme(theme);
             }
             #[cfg(test)]
             Dispatch::HandleKeyEvents(key_events) => self.handle_key_events(key_events)?,
@@ -829,6 +1010,7 @@ impl<T: Frontend> App<T> {
 
     fn close_current_window(&mut self) -> anyhow::Result<()> {
         if let Some(removed_path) = self.layout.close_current_window(&self.context) {
+            self.lsp_manager.close_file(removed_path.clone())?;
             if let Some(path) = self.context.unmark_path(removed_path).cloned() {
                 self.open_file(&path, BufferOwner::User, true, true)?;
             }
@@ -894,6 +1076,21 @@ impl<T: Frontend> App<T> {
         )
     }
 
+    fn open_workspace_symbol_prompt(&mut self) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: "Workspace Symbol".to_string(),
+                on_enter: DispatchPrompt::RequestWorkspaceSymbols,
+                items: vec![],
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: false,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::WorkspaceSymbol,
+            None,
+        )
+    }
+
     fn open_search_prompt(
         &mut self,
         scope: Scope,
@@ -1102,8 +1299,21 @@ impl<T: Frontend> App<T> {
             return Ok(matching_editor);
         }
 
-        let mut buffer = Buffer::from_path(path, true)?;
+        let mut buffer = Buffer::from_path(path, true, Some(self.context.language_registry()))?;
         buffer.set_owner(owner);
+        if let Some(max_undo_depth) = self.context.max_undo_depth() {
+            buffer.set_max_undo_depth(max_undo_depth);
+        }
+        if buffer.has_mixed_line_endings() {
+            self.show_global_info(Info::new(
+                "Mixed line endings".to_string(),
+                format!(
+                    "{} contains mixed line endings, they will be saved as {:?}.",
+                    path.display_absolute(),
+                    buffer.line_ending()
+                ),
+            ));
+        }
 
         let language = buffer.language();
         let content = buffer.content();
@@ -1124,14 +1334,41 @@ impl<T: Frontend> App<T> {
         }
         if self.enable_lsp {
             self.lsp_manager.open_file(path.clone())?;
+            self.request_document_annotations(component.borrow().editor())?;
         }
         Ok(component)
     }
 
+    /// Semantic tokens, folding ranges and inlay hints are requested automatically,
+    /// so they are silently skipped if the language server does not support them.
+    fn request_document_annotations(&mut self, editor: &Editor) -> anyhow::Result<()> {
+        let Some(params) = editor.get_request_params() else {
+            return Ok(());
+        };
+        self.lsp_manager.send_message(
+            params.path.clone(),
+            FromEditor::TextDocumentSemanticTokens(params.clone()),
+        )?;
+        self.lsp_manager.send_message(
+            params.path.clone(),
+            FromEditor::TextDocumentFoldingRange(params.clone()),
+        )?;
+        self.lsp_manager.send_message(
+            params.path.clone(),
+            FromEditor::TextDocumentInlayHint {
+                range: editor.document_position_range()?,
+                params,
+            },
+        )
+    }
+
     pub(crate) fn handle_lsp_notification(
         &mut self,
         notification: LspNotification,
     ) -> anyhow::Result<()> {
+        let Some(notification) = self.lsp_manager.receive_response(notification) else {
+            return Ok(());
+        };
         match notification {
             LspNotification::Hover(hover) => self.show_editor_info(Info::new(
                 "Hover Info".to_string(),
@@ -1167,27 +1404,51 @@ impl<T: Frontend> App<T> {
                     locations.into_iter().map(QuickfixListItem::from).collect(),
                 ),
             ),
-            LspNotification::Completion(_context, completion) => {
+            LspNotification::Completion(context, completion) => {
+                let current_version = context.path.as_ref().and_then(|path| {
+                    self.layout
+                        .buffers()
+                        .into_iter()
+                        .find(|buffer| buffer.borrow().path().as_ref() == Some(path))
+                        .map(|buffer| buffer.borrow().version())
+                });
+                if current_version.is_some_and(|version| context.is_outdated(version)) {
+                    log::info!("Discarded the completion of an outdated document version");
+                    return Ok(());
+                }
                 self.handle_dispatch_suggestive_editor(DispatchSuggestiveEditor::Completion(
                     completion,
                 ))?;
 
                 Ok(())
             }
-            LspNotification::Initialized(language) => {
+            LspNotification::Initialized {
+                language,
+                server_name,
+                capabilities,
+            } => {
                 // Need to notify LSP that the file is opened
                 self.lsp_manager.initialized(
                     language,
+                    server_name,
+                    capabilities,
                     self.layout
                         .buffers()
                         .into_iter()
                         .filter_map(|buffer| buffer.borrow().path())
                         .collect_vec(),
-                );
+                )?;
+                let component = self.current_component();
+                let component = component.borrow();
+                self.request_document_annotations(component.editor())?;
                 Ok(())
             }
-            LspNotification::PublishDiagnostics(params) => {
+            LspNotification::PublishDiagnostics {
+                server_name,
+                params,
+            } => {
                 self.update_diagnostics(
+                    server_name,
                     params
                         .uri
                         .to_file_path()
@@ -1216,7 +1477,7 @@ impl<T: Frontend> App<T> {
                         .unwrap_or_default()
                         .map(|rope| rope.to_string())
                 };
-                self.open_rename_prompt(current_name)?;
+                self.open_rename_prompt(response.placeholder.or(current_name))?;
 
                 Ok(())
             }
@@ -1224,13 +1485,74 @@ impl<T: Frontend> App<T> {
                 self.show_global_info(Info::new("LSP Error".to_string(), error));
                 Ok(())
             }
+            LspNotification::ServerExited {
+                language,
+                process_id,
+            } => {
+                self.lsp_manager.server_exited(&language, process_id);
+                Ok(())
+            }
+            LspNotification::Log {
+                server_name,
+                level,
+                message,
+            } => {
+                self.show_global_info(Info::new(format!("LSP {level} ({server_name})"), message));
+                Ok(())
+            }
+            LspNotification::Progress {
+                server_name,
+                token,
+                progress,
+            } => {
+                self.lsp_manager
+                    .update_progress(server_name, token, progress);
+                Ok(())
+            }
+            LspNotification::ServerRestarted { server_name, .. } => {
+                self.show_global_info(Info::new(
+                    "LSP Server Restarted".to_string(),
+                    format!(
+                        "The language server `{server_name}` exited unexpectedly and was restarted"
+                    ),
+                ));
+                Ok(())
+            }
+            LspNotification::ServerUnavailable {
+                server_name,
+                reason,
+                ..
+            } => {
+                self.show_global_info(Info::new(
+                    "LSP Server Unavailable".to_string(),
+                    format!("The language server `{server_name}` cannot be started: {reason}"),
+                ));
+                Ok(())
+            }
             LspNotification::WorkspaceEdit(workspace_edit) => {
                 self.apply_workspace_edit(workspace_edit)
             }
+            LspNotification::DocumentFormatting(edit) => self.apply_formatting(edit),
             LspNotification::CodeAction(code_actions) => {
                 self.handle_dispatch(Dispatch::ReceiveCodeActions(code_actions))?;
                 Ok(())
             }
+            LspNotification::CodeActionResolved(code_action) => {
+                self.handle_dispatches(code_action.dispatches())
+            }
+            LspNotification::RequestTimedOut { method, path } => {
+                self.handle_missing_response(&method)?;
+                self.show_global_info(Info::new(
+                    "LSP Request Timed Out".to_string(),
+                    format!(
+                        "The language server did not respond to `{method}`{}",
+                        path.map(|path| format!(" of {}", path.display_absolute()))
+                            .unwrap_or_default()
+                    ),
+                ));
+                Ok(())
+            }
+            LspNotification::RequestFailed { method } => self.handle_missing_response(&method),
             LspNotification::SignatureHelp(signature_help) => {
                 self.handle_signature_help(signature_help)?;
                 Ok(())
@@ -1239,14 +1561,93 @@ impl<T: Frontend> App<T> {
                 self.open_symbol_picker(symbols)?;
                 Ok(())
             }
+            LspNotification::SemanticTokens { path, tokens } => {
+                self.layout
+                    .buffers()
+                    .into_iter()
+                    .filter(|buffer| buffer.borrow().path().as_ref() == Some(&path))
+                    .for_each(|buffer| buffer.borrow_mut().set_semantic_tokens(&tokens));
+                Ok(())
+            }
+            LspNotification::InlayHints { path, hints } => {
+                self.layout
+                    .buffers()
+                    .into_iter()
+                    .filter(|buffer| buffer.borrow().path().as_ref() == Some(&path))
+                    .for_each(|buffer| buffer.borrow_mut().set_inlay_hints(hints.clone()));
+                Ok(())
+            }
+            LspNotification::FoldingRanges { path, ranges } => {
+                self.layout
+                    .buffers()
+                    .into_iter()
+                    .filter(|buffer| buffer.borrow().path().as_ref() == Some(&path))
+                    .for_each(|buffer| buffer.borrow_mut().set_folding_ranges(ranges.clone()));
+                Ok(())
+            }
+            LspNotification::CallHierarchyItems(context, items) => {
+                let Some(direction) = self.call_direction.take() else {
+                    return Ok(());
+                };
+                // Usually there is only one item, which is the function at the cursor
+                let Some(item) = items.into_iter().next() else {
+                    return Err(anyhow::anyhow!("No function is found at the cursor"));
+                };
+                match direction {
+                    CallDirection::Incoming => self.lsp_manager.incoming_calls(item, context.scope),
+                    CallDirection::Outgoing => self.lsp_manager.outgoing_calls(item, context.scope),
+                }
+            }
+            LspNotification::CallHierarchyCalls(context, calls) => self.set_quickfix_list_type(
+                context,
+                QuickfixListType::Items(
+                    calls
+                        .into_iter()
+                        .flat_map(|call| call.into_quickfix_list_items())
+                        .collect(),
+                ),
+            ),
+            LspNotification::DocumentHighlights { path, highlights } => {
+                self.layout
+                    .buffers()
+                    .into_iter()
+                    .filter(|buffer| buffer.borrow().path().as_ref() == Some(&path))
+                    .for_each(|buffer| {
+                        buffer
+                            .borrow_mut()
+                            .set_document_highlights(highlights.clone())
+                    });
+                Ok(())
+            }
+            LspNotification::WorkspaceSymbols(symbols) => {
+                if let Some(symbols) = self.lsp_manager.receive_workspace_symbols(Some(symbols)) {
+                    self.open_symbol_picker(symbols)?;
+                }
+                Ok(())
+            }
             LspNotification::CompletionItemResolve(completion_item) => {
                 self.update_current_completion_item(completion_item.into())
             }
         }
     }
 
+    /// A language server failed to respond to `method`,
+    /// so the responses of the other servers are no longer waiting for it.
+    fn handle_missing_response(&mut self, method: &str) -> anyhow::Result<()> {
+        if method == "workspace/symbol" {
+            if let Some(symbols) = self.lsp_manager.receive_workspace_symbols(None) {
+                self.open_symbol_picker(symbols)?;
+            }
+        }
+        if let Some(notification) = self.lsp_manager.receive_missing_response(method) {
+            self.handle_lsp_notification(notification)?;
+        }
+        Ok(())
+    }
+
     fn update_diagnostics(
         &mut self,
+        server_name: &'static str,
         path: CanonicalizedPath,
         diagnostics: Vec<lsp_types::Diagnostic>,
     ) -> anyhow::Result<()> {
@@ -1256,7 +1657,7 @@ impl<T: Frontend> App<T> {
             .borrow_mut()
             .editor_mut()
             .buffer_mut()
-            .set_diagnostics(diagnostics);
+            .set_diagnostics(server_name, diagnostics);
         Ok(())
     }
 
@@ -1360,9 +1761,22 @@ impl<T: Frontend> App<T> {
         }
     }
 
+    /// No document is edited unless the edits of every document can be applied,
+    /// so that the workspace is not left half-edited.
     fn apply_workspace_edit(&mut self, workspace_edit: WorkspaceEdit) -> Result<(), anyhow::Error> {
-        // TODO: should we wrap this in a transaction so that if one of the edit/operation fails, the whole transaction fails?
-        // Such that it won't leave the workspace in an half-edited messed up state
+        // The edits are tried against copies of the buffers first,
+        // because the edits of the editors below cannot be rolled back
+        let mut buffers = workspace_edit
+            .edits
+            .iter()
+            .map(|edit| -> anyhow::Result<_> {
+                let component = self.open_file(&edit.path, BufferOwner::System, false, false)?;
+                let buffer = component.borrow().editor().buffer().clone();
+                Ok((edit.path.clone(), buffer))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        crate::lsp::workspace_edit::apply_workspace_edit(&mut buffers, workspace_edit.clone())?;
+
         for edit in workspace_edit.edits {
             let component = self.open_file(&edit.path, BufferOwner::System, false, false)?;
             let dispatches = component
@@ -1387,6 +1801,16 @@ impl<T: Frontend> App<T> {
         Ok(())
     }
 
+    /// Unlike workspace edits, formatting edits are not saved automatically.
+    fn apply_formatting(&mut self, edit: TextDocumentEdit) -> anyhow::Result<()> {
+        let component = self.open_file(&edit.path, BufferOwner::System, false, false)?;
+        let dispatches = component
+            .borrow_mut()
+            .editor_mut()
+            .apply_positional_edits(edit.edits, &self.context)?;
+        self.handle_dispatches(dispatches)
+    }
+
     fn show_keymap_legend(&mut self, keymap_legend_config: KeymapLegendConfig) {
         self.layout
             .show_keymap_legend(keymap_legend_config, &self.context)
@@ -1402,6 +1826,12 @@ impl<T: Frontend> App<T> {
         };
         let config = self.context.global_search_config().local_config();
         let affected_paths = list::grep::replace(walk_builder_config, config.clone())?;
+        self.lsp_manager.notify_files_changed(
+            affected_paths
+                .iter()
+                .map(|path| (path.clone(), FileChangeType::CHANGED))
+                .collect(),
+        )?;
         self.layout.reload_buffers(affected_paths)
     }
 
@@ -1483,7 +1913,10 @@ impl<T: Frontend> App<T> {
         } else {
             std::fs::remove_file(path)?;
         }
+        self.lsp_manager
+            .notify_files_changed(vec![(path.clone(), FileChangeType::DELETED)])?;
         self.layout.remove_suggestive_editor(path);
+        self.lsp_manager.close_file(path.clone())?;
         self.layout
             .refresh_file_explorer(&self.working_directory, &self.context)?;
         Ok(())
@@ -2303,6 +2736,25 @@ impl<T: Frontend> App<T> {
         )
     }
 
+    fn open_register_prompt(
+        &mut self,
+        title: &str,
+        on_enter: DispatchPrompt,
+    ) -> anyhow::Result<()> {
+        self.open_prompt(
+            PromptConfig {
+                title: title.to_string(),
+                items: Default::default(),
+                on_enter,
+                enter_selects_first_matching_item: false,
+                leaves_current_line_empty: true,
+                fire_dispatches_on_change: None,
+            },
+            PromptHistoryKey::Register,
+            None,
+        )
+    }
+
     fn use_last_non_contiguous_selection_mode(
         &mut self,
         if_current_not_found: IfCurrentNotFound,
@@ -2467,6 +2919,7 @@ impl Dispatches {
 pub(crate) enum Dispatch {
     SetTheme(crate::themes::Theme),
     SetThemeFromDescriptor(crate::themes::theme_descriptor::ThemeDescriptor),
+    ToggleThemeVariant,
     CloseCurrentWindow,
     OpenFilePicker(FilePickerKind),
     OpenSearchPrompt {
@@ -2490,14 +2943,22 @@ pub(crate) enum Dispatch {
     RequestDeclarations(Scope),
     RequestImplementations(Scope),
     RequestTypeDefinitions(Scope),
+    RequestCalls {
+        direction: CallDirection,
+        scope: Scope,
+    },
     RequestReferences {
         scope: Scope,
         include_declaration: bool,
     },
     PrepareRename,
+    RequestFormatting,
+    RequestRangeFormatting,
     RequestCodeAction {
         diagnostics: Vec<lsp_types::Diagnostic>,
     },
+    /// Resolve the edit of a code action that was chosen, then apply it
+    ResolveCodeAction(crate::lsp::code_action::CodeAction),
     RenameSymbol {
         new_name: String,
     },
@@ -2507,6 +2968,9 @@ pub(crate) enum Dispatch {
         path: Option<CanonicalizedPath>,
         content: String,
         language: Option<Language>,
+        version: i32,
+        /// See `Buffer::take_content_changes`
+        changes: Option<Vec<lsp_types::TextDocumentContentChangeEvent>>,
     },
     DocumentDidSave {
         path: CanonicalizedPath,
@@ -2522,6 +2986,10 @@ pub(crate) enum Dispatch {
     Custom(String),
     ToEditor(DispatchEditor),
     RequestDocumentSymbols,
+    OpenWorkspaceSymbolPrompt,
+    RequestWorkspaceSymbols {
+        query: String,
+    },
     GotoLocation(Location),
     OpenMoveToIndexPrompt,
     QuitAll,
@@ -2546,6 +3014,10 @@ pub(crate) enum Dispatch {
         copied_texts: CopiedTexts,
         use_system_clipboard: bool,
     },
+    SetRegister {
+        name: char,
+        content: String,
+    },
     SetGlobalMode(Option<GlobalMode>),
     #[cfg(test)]
     HandleKeyEvent(event::KeyEvent),
@@ -2616,6 +3088,8 @@ pub(crate) enum Dispatch {
     OpenThemePrompt,
     ResolveCompletionItem(lsp_types::CompletionItem),
     OpenPipeToShellPrompt,
+    OpenCopyToRegisterPrompt,
+    OpenPasteFromRegisterPrompt,
     SetLastNonContiguousSelectionMode(Either<SelectionMode, GlobalMode>),
     UseLastNonContiguousSelectionMode(IfCurrentNotFound),
     SetLastActionDescription {
@@ -2703,7 +3177,17 @@ impl RequestParams {
     }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Copy)]
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+    strum_macros::EnumIter,
+)]
 pub(crate) enum Scope {
     Local,
     Global,
@@ -2729,6 +3213,7 @@ pub(crate) enum DispatchPrompt {
     },
     MoveSelectionByIndex,
     RenameSymbol,
+    RequestWorkspaceSymbols,
     UpdateLocalSearchConfigSearch {
         scope: Scope,
         show_config_after_enter: bool,
@@ -2761,6 +3246,8 @@ pub(crate) enum DispatchPrompt {
     FilterSelectionMatchingSearch {
         maintain: bool,
     },
+    CopyToRegister,
+    PasteFromRegister,
 }
 impl DispatchPrompt {
     pub(crate) fn to_dispatches(&self, text: &str) -> anyhow::Result<Dispatches> {
@@ -2784,6 +3271,11 @@ impl DispatchPrompt {
             DispatchPrompt::RenameSymbol => Ok(Dispatches::new(vec![Dispatch::RenameSymbol {
                 new_name: text.to_string(),
             }])),
+            DispatchPrompt::RequestWorkspaceSymbols => {
+                Ok(Dispatches::new(vec![Dispatch::RequestWorkspaceSymbols {
+                    query: text.to_string(),
+                }]))
+            }
             DispatchPrompt::UpdateLocalSearchConfigSearch {
                 scope,
                 show_config_after_enter,
@@ -2870,6 +3362,19 @@ impl DispatchPrompt {
                     search: text.to_string(),
                 }),
             )),
+            DispatchPrompt::CopyToRegister => Ok(Dispatches::one(Dispatch::ToEditor(
+                DispatchEditor::CopyToRegister(parse_register_name(text)?),
+            ))),
+            DispatchPrompt::PasteFromRegister => Ok(Dispatches::one(Dispatch::ToEditor(
+                DispatchEditor::PasteFromRegister(parse_register_name(text)?),
+            ))),
         }
     }
 }
+
+fn parse_register_name(text: &str) -> anyhow::Result<char> {
+    text.trim()
+        .chars()
+        .exactly_one()
+        .map_err(|_| anyhow::anyhow!("A register is named by a single character, but got {text:?}"))
+}
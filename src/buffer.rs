@@ -1,5 +1,8 @@
 use crate::history::History;
 use crate::lsp::diagnostic::Diagnostic;
+use crate::lsp::document_highlight::{DocumentHighlight, DocumentHighlightKind};
+use crate::lsp::inlay_hint::InlayHint;
+use crate::lsp::semantic_tokens::SemanticToken;
 use crate::quickfix_list::QuickfixListItem;
 use crate::selection::Selection;
 use crate::selection_mode::naming_convention_agnostic::NamingConventionAgnostic;
@@ -7,24 +10,32 @@ use crate::syntax_highlight::SyntaxHighlightRequestBatchId;
 use crate::{
     char_index_range::CharIndexRange,
     components::suggestive_editor::Decoration,
-    context::{LocalSearchConfig, LocalSearchConfigMode},
+    context::{LocalSearchConfig, LocalSearchConfigMode, Search},
     edit::{Action, ActionGroup, Edit, EditTransaction},
+    grid::{IndexedHighlightGroup, StyleKey},
+    list::grep::RegexConfig,
     position::Position,
     selection::{CharIndex, SelectionSet},
     selection_mode::{AstGrep, ByteRange},
     syntax_highlight::{HighlightedSpan, HighlightedSpans},
+    transformation::CaseTransform,
     utils::find_previous,
 };
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
 use itertools::Itertools;
 use regex::Regex;
 use ropey::Rope;
 use shared::{
     canonicalized_path::CanonicalizedPath,
-    language::{self, Language},
+    language::{self, Language, LanguageRegistry},
 };
-use std::{collections::HashSet, ops::Range};
-use tree_sitter::{Node, Parser, Tree};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+use tree_sitter::{InputEdit, Node, Parser, Point, Query, QueryCursor, StreamingIterator, Tree};
 use tree_sitter_traversal2::{traverse, Order};
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Determines the buffer's owner. Ki distinguishes buffer ownership during switches.
 /// System-owned buffers (e.g., from LSP diagnostics or quicklist functions) are
@@ -36,16 +47,248 @@ pub enum BufferOwner {
     System,
 }
 
+/// The line ending convention of a file.
+/// Buffers always store `\n` internally,
+/// the original line ending is only restored when the buffer is saved.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Returns the line ending used by the majority of the lines of `text`,
+    /// and whether `text` contains a mix of line endings.
+    fn detect(text: &str) -> (LineEnding, bool) {
+        let crlf_count = text.matches("\r\n").count();
+        let lf_count = text.matches('\n').count() - crlf_count;
+        let line_ending = if crlf_count > lf_count {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        };
+        (line_ending, crlf_count > 0 && lf_count > 0)
+    }
+
+    fn normalize(text: &str) -> String {
+        text.replace("\r\n", "\n")
+    }
+
+    fn restore(&self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Decodes the content of a file into UTF-8, and returns the detected encoding,
+/// and whether the content starts with a BOM, which is excluded from the decoded content.
+///
+/// A BOM determines the encoding, otherwise the content is decoded as UTF-8 if valid,
+/// falling back to Windows-1252 (a superset of Latin-1) which can decode any bytes.
+fn decode(bytes: &[u8]) -> anyhow::Result<(String, &'static Encoding, bool)> {
+    let (encoding, bom_length) = match Encoding::for_bom(bytes) {
+        Some(found) => found,
+        None if std::str::from_utf8(bytes).is_ok() => (UTF_8, 0),
+        None => (WINDOWS_1252, 0),
+    };
+    let (content, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+    if had_errors {
+        return Err(anyhow::anyhow!(
+            "Unsupported encoding: the content is not valid {}",
+            encoding.name()
+        ));
+    }
+    Ok((content.into_owned(), encoding, bom_length > 0))
+}
+
+/// The `tree_sitter::Point` after the last byte of `rope`.
+fn end_point(rope: &Rope) -> Point {
+    let row = rope.len_lines() - 1;
+    Point::new(row, rope.len_bytes() - rope.line_to_byte(row))
+}
+
+/// The inverse of `decode`.
+fn encode(text: &str, encoding: &'static Encoding, had_bom: bool) -> anyhow::Result<Vec<u8>> {
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        // `Encoding::encode` outputs UTF-8 for UTF-16, so it is encoded manually, with a BOM
+        let to_bytes = if encoding == UTF_16LE {
+            u16::to_le_bytes
+        } else {
+            u16::to_be_bytes
+        };
+        return Ok(std::iter::once(0xFEFF)
+            .chain(text.encode_utf16())
+            .flat_map(to_bytes)
+            .collect());
+    }
+    let (bytes, _, had_unmappable_characters) = encoding.encode(text);
+    if had_unmappable_characters {
+        return Err(anyhow::anyhow!(
+            "Unable to save, because the content contains characters that cannot be encoded in {}",
+            encoding.name()
+        ));
+    }
+    let bom: &[u8] = if had_bom && encoding == UTF_8 {
+        b"\xEF\xBB\xBF"
+    } else {
+        b""
+    };
+    Ok([bom, &bytes[..]].concat())
+}
+
+/// Reparses of a file opened from disk are abandoned after this long,
+/// so that pathological content cannot freeze the editor.
+const PARSE_TIMEOUT_MICROS: u64 = 500_000;
+
+/// Owns the Tree-sitter parser of a buffer, so that the same parser is reused
+/// across reparses instead of being constructed (and configured) on every edit.
+struct BufferParser {
+    parser: Parser,
+    language: Option<tree_sitter::Language>,
+    /// 0 means there is no timeout.
+    timeout_micros: u64,
+}
+
+impl BufferParser {
+    /// Falls back to a parser without language if `language` is incompatible.
+    fn new(language: Option<tree_sitter::Language>) -> Self {
+        Self::try_new(language).unwrap_or_else(|error| {
+            log::error!("BufferParser::new: {error}");
+            Self {
+                parser: Parser::new(),
+                language: None,
+                timeout_micros: 0,
+            }
+        })
+    }
+
+    /// Fails if `language` was generated with an incompatible version of Tree-sitter.
+    fn try_new(language: Option<tree_sitter::Language>) -> anyhow::Result<Self> {
+        let mut parser = Parser::new();
+        if let Some(language) = language.as_ref() {
+            parser.set_language(language)?;
+        }
+        Ok(Self {
+            parser,
+            language,
+            timeout_micros: 0,
+        })
+    }
+
+    fn set_timeout_micros(&mut self, timeout_micros: u64) {
+        self.timeout_micros = timeout_micros;
+        self.parser.set_timeout_micros(timeout_micros);
+    }
+
+    /// Returns `None` if this parser has no language, or the parsing timed out.
+    fn parse(&mut self, text: &str) -> Option<Tree> {
+        self.language.as_ref()?;
+        let tree = self.parser.parse(text, None);
+        self.reset_if_timed_out(tree.is_none());
+        tree
+    }
+
+    /// Parses `rope` chunk by chunk, without converting it into a `String`.
+    /// `old_tree` must already be updated using `Tree::edit`.
+    fn parse_rope(&mut self, rope: &Rope, old_tree: Option<&Tree>) -> Option<Tree> {
+        self.language.as_ref()?;
+        let tree = self.parser.parse_with(
+            &mut |byte_index, _| {
+                rope.get_chunk_at_byte(byte_index)
+                    .map(|(chunk, chunk_byte_index, _, _)| {
+                        &chunk.as_bytes()[byte_index - chunk_byte_index..]
+                    })
+                    .unwrap_or_default()
+            },
+            old_tree,
+        );
+        self.reset_if_timed_out(tree.is_none());
+        tree
+    }
+
+    /// Otherwise the next parse would resume the timed out parse instead of starting over.
+    fn reset_if_timed_out(&mut self, timed_out: bool) {
+        if timed_out {
+            self.parser.reset();
+        }
+    }
+
+    fn has_language(&self) -> bool {
+        self.language.is_some()
+    }
+}
+
+impl Clone for BufferParser {
+    /// `tree_sitter::Parser` is not `Clone`,
+    /// so a new parser of the same language is constructed instead.
+    fn clone(&self) -> Self {
+        let mut parser = Self::new(self.language.clone());
+        parser.set_timeout_micros(self.timeout_micros);
+        parser
+    }
+}
+
+/// Durations of the incremental reparses of a buffer.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default)]
+struct ParseMetrics {
+    last: Option<std::time::Duration>,
+    total: std::time::Duration,
+    count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IndentStyle {
+    Spaces(usize),
+    Tabs,
+}
+
+impl IndentStyle {
+    /// The whitespace of one level of indentation
+    fn unit(&self) -> String {
+        match self {
+            IndentStyle::Spaces(width) => " ".repeat(*width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// Options of `Buffer::sort_lines`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SortOptions {
+    pub(crate) reverse: bool,
+    /// Lines are ordered by their leading integer,
+    /// lines without one are placed after them in lexicographic order.
+    pub(crate) numeric: bool,
+    pub(crate) case_insensitive: bool,
+    /// Only the first of the lines that are equal (according to the other options) is kept.
+    pub(crate) remove_duplicates: bool,
+}
+
 #[derive(Clone)]
 pub(crate) struct Buffer {
     rope: Rope,
     tree: Option<Tree>,
+    parser: BufferParser,
     treesitter_language: Option<tree_sitter::Language>,
     language: Option<Language>,
     path: Option<CanonicalizedPath>,
     highlighted_spans: HighlightedSpans,
+    /// Derived from the semantic tokens of the language server,
+    /// these are rendered on top of `highlighted_spans`.
+    semantic_highlighted_spans: HighlightedSpans,
+    /// Sorted by the char index that each hint is anchored to
+    inlay_hints: Vec<(CharIndex, InlayHint)>,
+    /// Provided by the language server, `None` if unavailable or outdated
+    folding_ranges: Option<Vec<Range<usize>>>,
+    /// The occurrences of the symbol under the cursor, sorted by their range
+    document_highlights: Vec<(CharIndexRange, DocumentHighlightKind)>,
     marks: Vec<CharIndexRange>,
-    diagnostics: Vec<Diagnostic>,
+    /// Keyed by the name of the language server that published them
+    diagnostics: HashMap<&'static str, Vec<Diagnostic>>,
     quickfix_list_items: Vec<QuickfixListItem>,
     decorations: Vec<Decoration>,
     selection_set_history: History<SelectionSet>,
@@ -54,8 +297,44 @@ pub(crate) struct Buffer {
     undo_stack: Vec<EditHistory>,
     redo_stack: Vec<EditHistory>,
     batch_id: SyntaxHighlightRequestBatchId,
+    line_ending: LineEnding,
+    mixed_line_endings: bool,
+    /// `None` means the undo history is unlimited.
+    max_undo_depth: Option<usize>,
+    /// Edits are rejected when this is set, see `Buffer::set_read_only`.
+    read_only: bool,
+    /// The encoding of the file, which the content is encoded back into when saving.
+    encoding: &'static Encoding,
+    /// Whether the file started with a BOM, which is not part of `rope`,
+    /// but is written back when saving.
+    had_bom: bool,
+    #[cfg(feature = "metrics")]
+    parse_metrics: ParseMetrics,
+    /// Set when the latest reparse timed out, in which case the previous tree is kept,
+    /// so the highlighting and syntax nodes might not reflect the latest content.
+    stale_tree: bool,
+    /// The changes since they were last taken, with UTF-16 ranges for incremental document sync.
+    /// `None` if the content was replaced wholesale, which requires a full sync.
+    content_changes: Option<Vec<lsp_types::TextDocumentContentChangeEvent>>,
+    /// The document version notified to language servers,
+    /// which is incremented whenever the changes are taken
+    version: i32,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SyntaxErrorKind {
+    /// Text that the parser is unable to recognize
+    Error,
+    /// A token that is expected, but absent, for example a missing semicolon
+    Missing,
+}
+
+/// The selections visited by `Buffer::expand_selection`,
+/// so that `Buffer::shrink_selection` can exactly reverse the expansions.
+#[derive(Clone, Debug, Default)]
+#[allow(dead_code)]
+pub(crate) struct SelectionExpansions(Vec<Selection>);
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct Line {
     pub(crate) origin_position: Position,
@@ -65,25 +344,51 @@ pub(crate) struct Line {
 }
 
 impl Buffer {
+    /// Unlike `Buffer::try_new`, this never fails,
+    /// the buffer is not parsed if `language` is unusable.
     pub(crate) fn new(language: Option<tree_sitter::Language>, text: &str) -> Self {
+        Self::with_parser(BufferParser::new(language), text)
+    }
+
+    /// An empty untitled buffer of `language`, which exists only in memory
+    /// until it is given a path via `Buffer::save_as`.
+    #[allow(dead_code)]
+    pub(crate) fn scratch(language: Language) -> Self {
+        let mut buffer = Self::new(language.tree_sitter_language(), "");
+        buffer.language = Some(language);
+        buffer
+    }
+
+    /// Returns an error if `language` is incompatible or if `text` cannot be parsed.
+    pub(crate) fn try_new(
+        language: Option<tree_sitter::Language>,
+        text: &str,
+    ) -> anyhow::Result<Self> {
+        let buffer = Self::with_parser(BufferParser::try_new(language)?, text);
+        if buffer.treesitter_language.is_some() && buffer.tree.is_none() {
+            return Err(anyhow::anyhow!("Tree-sitter failed to parse the buffer"));
+        }
+        Ok(buffer)
+    }
+
+    fn with_parser(mut parser: BufferParser, text: &str) -> Self {
+        let (line_ending, mixed_line_endings) = LineEnding::detect(text);
+        let text = &LineEnding::normalize(text);
         Self {
             rope: Rope::from_str(text),
-            treesitter_language: language.clone(),
+            treesitter_language: parser.language.clone(),
             language: None,
-            tree: {
-                let mut parser = Parser::new();
-                language.and_then(|language| {
-                    parser
-                        .set_language(&language)
-                        .ok()
-                        .and_then(|_| parser.parse(text, None))
-                })
-            },
+            tree: parser.parse(text),
+            parser,
             path: None,
             highlighted_spans: HighlightedSpans::default(),
+            semantic_highlighted_spans: HighlightedSpans::default(),
+            inlay_hints: Vec::new(),
+            folding_ranges: None,
+            document_highlights: Vec::new(),
             marks: Vec::new(),
             decorations: Vec::new(),
-            diagnostics: Vec::new(),
+            diagnostics: HashMap::new(),
             quickfix_list_items: Vec::new(),
             selection_set_history: History::new(),
             dirty: false,
@@ -91,6 +396,35 @@ impl Buffer {
             undo_stack: Default::default(),
             redo_stack: Default::default(),
             batch_id: Default::default(),
+            line_ending,
+            mixed_line_endings,
+            max_undo_depth: None,
+            read_only: false,
+            encoding: UTF_8,
+            had_bom: false,
+            #[cfg(feature = "metrics")]
+            parse_metrics: ParseMetrics::default(),
+            stale_tree: false,
+            content_changes: Some(Vec::new()),
+            version: 1,
+        }
+    }
+
+    /// Limits the number of undoable edits, the oldest edits are discarded first.
+    pub(crate) fn set_max_undo_depth(&mut self, depth: usize) {
+        self.max_undo_depth = Some(depth);
+        self.truncate_undo_stack();
+    }
+
+    fn push_undo_history(&mut self, history: EditHistory) {
+        self.undo_stack.push(history);
+        self.truncate_undo_stack();
+    }
+
+    fn truncate_undo_stack(&mut self) {
+        if let Some(max_undo_depth) = self.max_undo_depth {
+            let excess = self.undo_stack.len().saturating_sub(max_undo_depth);
+            self.undo_stack.drain(..excess);
         }
     }
 
@@ -117,13 +451,66 @@ impl Buffer {
 
     pub(crate) fn reload(&mut self) -> anyhow::Result<()> {
         if let Some(path) = self.path() {
-            let updated_content = path.read()?;
-            self.update_content(&updated_content, SelectionSet::default(), 0)?;
+            let (updated_content, encoding, had_bom) = decode(&std::fs::read(&path)?)?;
+            let edit_transaction =
+                self.get_edit_transaction(&LineEnding::normalize(&updated_content))?;
+            // Files that change on disk are reloaded even if they are read-only
+            self.apply_edit_transaction_unguarded(
+                &edit_transaction,
+                SelectionSet::default(),
+                true,
+                true,
+                0,
+            )?;
+            (self.encoding, self.had_bom) = (encoding, had_bom);
+            (self.line_ending, self.mixed_line_endings) = LineEnding::detect(&updated_content);
             self.dirty = false;
         }
         Ok(())
     }
 
+    /// Replaces the content with the content of the file on disk,
+    /// and returns `selection_set` remapped onto the new content.
+    ///
+    /// Returns an error if this buffer has unsaved changes, which would be lost otherwise.
+    pub(crate) fn reload_from_disk(
+        &mut self,
+        selection_set: SelectionSet,
+    ) -> anyhow::Result<SelectionSet> {
+        let Some(path) = self.path() else {
+            return Err(anyhow::anyhow!(
+                "Unable to reload a buffer that has no path"
+            ));
+        };
+        if self.dirty {
+            return Err(anyhow::anyhow!(
+                "{} has unsaved changes, save or discard them before reloading",
+                path.display_absolute()
+            ));
+        }
+        let (content, encoding, had_bom) = decode(&std::fs::read(&path)?)?;
+        let edit_transaction = self.get_edit_transaction(&LineEnding::normalize(&content))?;
+        let selection_set = selection_set.apply(selection_set.mode.clone(), |selection| {
+            let range = selection.range();
+            Ok(selection.clone().set_range(
+                (edit_transaction.map_char_index(range.start)
+                    ..edit_transaction.map_char_index(range.end))
+                    .into(),
+            ))
+        })?;
+        self.apply_edit_transaction_unguarded(
+            &edit_transaction,
+            selection_set.clone(),
+            true,
+            true,
+            0,
+        )?;
+        (self.encoding, self.had_bom) = (encoding, had_bom);
+        (self.line_ending, self.mixed_line_endings) = LineEnding::detect(&content);
+        self.dirty = false;
+        self.clamp_selection_set(selection_set)
+    }
+
     pub(crate) fn content(&self) -> String {
         self.rope.to_string()
     }
@@ -153,20 +540,38 @@ impl Buffer {
         self.path.clone()
     }
 
+    /// Untitled buffers have no path, so saving them requires `Buffer::save_as`.
+    #[allow(dead_code)]
+    pub(crate) fn is_untitled(&self) -> bool {
+        self.path.is_none()
+    }
+
     #[cfg(test)]
     pub(crate) fn set_path(&mut self, path: CanonicalizedPath) {
         self.path = Some(path);
     }
 
-    pub(crate) fn set_diagnostics(&mut self, diagnostics: Vec<lsp_types::Diagnostic>) {
-        self.diagnostics = diagnostics
+    /// Replaces the diagnostics previously published by the same language server.
+    pub(crate) fn set_diagnostics(
+        &mut self,
+        server_name: &'static str,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) {
+        let diagnostics = diagnostics
             .into_iter()
             .filter_map(|diagnostic| Diagnostic::try_from(self, diagnostic).ok())
-            .collect()
+            .collect();
+        self.diagnostics.insert(server_name, diagnostics);
     }
 
+    /// The diagnostics of all language servers, sorted by their range.
     pub(crate) fn diagnostics(&self) -> Vec<Diagnostic> {
-        self.diagnostics.clone()
+        self.diagnostics
+            .values()
+            .flatten()
+            .cloned()
+            .sorted_by_key(|diagnostic| (diagnostic.range.start, diagnostic.range.end))
+            .collect()
     }
 
     pub(crate) fn words(&self) -> Vec<String> {
@@ -225,48 +630,6 @@ impl Buffer {
             .collect_vec())
     }
 
-    fn get_rope_and_tree(
-        language: Option<tree_sitter::Language>,
-        text: &str,
-    ) -> (Rope, Option<Tree>) {
-        let mut parser = Parser::new();
-        let tree = language
-            .map(|language| parser.set_language(&language))
-            .and_then(|_| parser.parse(text, None));
-        // let start_char_index = edit.start;
-        // let old_end_char_index = edit.end();
-        // let new_end_char_index = edit.start + edit.new.len_chars();
-
-        // let start_byte = self.char_to_byte(start_char_index);
-        // let old_end_byte = self.char_to_byte(old_end_char_index);
-        // let start_position = self.char_to_point(start_char_index);
-        // let old_end_position = self.char_to_point(old_end_char_index);
-
-        // self.rope.remove(edit.start.0..edit.end().0);
-        // self.rope
-        //     .insert(edit.start.0, edit.new.to_string().as_str());
-
-        // let new_end_byte = self.char_to_byte(new_end_char_index);
-        // let new_end_position = self.char_to_point(new_end_char_index);
-
-        // let mut parser = tree_sitter::Parser::new();
-        // parser.set_language(self.tree.language()).unwrap();
-        // self.tree.edit(&InputEdit {
-        //     start_byte,
-        //     old_end_byte,
-        //     new_end_byte,
-        //     start_position,
-        //     old_end_position,
-        //     new_end_position,
-        // });
-
-        // self.tree = parser
-        //     .parse(&self.rope.to_string(), Some(&self.tree))
-        //     .unwrap();
-
-        (Rope::from_str(text), tree)
-    }
-
     pub(crate) fn given_range_is_node(&self, range: &CharIndexRange) -> bool {
         let Some(start) = self.char_to_byte(range.start).ok() else {
             return false;
@@ -299,8 +662,131 @@ impl Buffer {
         }
     }
 
+    pub(crate) fn set_semantic_tokens(&mut self, tokens: &[SemanticToken]) {
+        let highlight_names = crate::themes::highlight_names();
+        self.semantic_highlighted_spans = HighlightedSpans(
+            tokens
+                .iter()
+                .filter_map(|token| {
+                    let highlight_name = token.highlight_name()?;
+                    let index = highlight_names
+                        .iter()
+                        .position(|name| name == &highlight_name)?;
+                    // Semantic tokens are encoded as integers, so they are not converted
+                    // by `LspServerProcess`, and their columns are measured in UTF-16 code units
+                    let to_byte = |position: Position| {
+                        self.char_to_byte(self.lsp_position_to_char(position.into())?)
+                    };
+                    Some(HighlightedSpan {
+                        byte_range: to_byte(token.range.start).ok()?
+                            ..to_byte(token.range.end).ok()?,
+                        style_key: StyleKey::Syntax(IndexedHighlightGroup::new(index)),
+                    })
+                })
+                .sorted_by_key(|span| (span.byte_range.start, span.byte_range.end))
+                .collect(),
+        );
+    }
+
+    /// The resulting spans are sorted by range
+    pub(crate) fn semantic_highlighted_spans(&self) -> &Vec<HighlightedSpan> {
+        &self.semantic_highlighted_spans.0
+    }
+
+    /// Hints that cannot be resolved against the current content are discarded.
+    pub(crate) fn set_inlay_hints(&mut self, hints: Vec<InlayHint>) {
+        self.inlay_hints = hints
+            .into_iter()
+            .filter_map(|hint| Some((self.position_to_char(hint.position).ok()?, hint)))
+            .sorted_by_key(|(char_index, _)| *char_index)
+            .collect();
+    }
+
+    /// Returns the joined inlay hints anchored on the given line,
+    /// or `None` if there is none.
+    pub(crate) fn line_inlay_hints(&self, line_index: usize) -> Option<String> {
+        let line_range = self.line_to_char_range(line_index).ok()?;
+        let hints = self
+            .inlay_hints
+            .iter()
+            .filter(|(char_index, _)| {
+                line_range.start <= *char_index && *char_index < line_range.end
+            })
+            .map(|(_, hint)| hint.display())
+            .collect_vec();
+        (!hints.is_empty()).then(|| hints.join(" "))
+    }
+
+    /// Highlights whose range is invalid for the current content are dropped.
+    pub(crate) fn set_document_highlights(&mut self, highlights: Vec<DocumentHighlight>) {
+        self.document_highlights = highlights
+            .into_iter()
+            .filter_map(|highlight| {
+                Some((
+                    self.position_range_to_char_index_range(&highlight.range)
+                        .ok()?,
+                    highlight.kind,
+                ))
+            })
+            .sorted_by_key(|(range, _): &(CharIndexRange, _)| (range.start, range.end))
+            .collect();
+    }
+
+    pub(crate) fn document_highlights(&self) -> &[(CharIndexRange, DocumentHighlightKind)] {
+        &self.document_highlights
+    }
+
+    pub(crate) fn set_folding_ranges(&mut self, folding_ranges: Vec<Range<usize>>) {
+        self.folding_ranges = Some(folding_ranges);
+    }
+
+    /// Returns the foldable line ranges (end-exclusive), sorted by their start line.
+    ///
+    /// The ranges provided by the language server are preferred,
+    /// otherwise they are derived from the named syntax nodes spanning multiple lines.
+    pub(crate) fn folding_ranges(&self) -> Vec<Range<usize>> {
+        if let Some(folding_ranges) = &self.folding_ranges {
+            return folding_ranges.clone();
+        }
+        self.traverse(Order::Pre)
+            .into_iter()
+            .flatten()
+            .filter(|node| node.is_named() && node.parent().is_some())
+            .filter(|node| node.end_position().row > node.start_position().row)
+            .map(|node| node.start_position().row..node.end_position().row + 1)
+            .unique()
+            .sorted_by_key(|range| (range.start, std::cmp::Reverse(range.end)))
+            .collect()
+    }
+
     pub(crate) fn update(&mut self, text: &str) {
-        (self.rope, self.tree) = Self::get_rope_and_tree(self.treesitter_language.clone(), text);
+        let text = LineEnding::normalize(text);
+        let rope = Rope::from_str(&text);
+        match self.parser.parse(&text) {
+            Some(tree) => {
+                self.tree = Some(tree);
+                self.stale_tree = false;
+            }
+            None if self.parser.has_language() => {
+                log::warn!("Buffer::update: parsing timed out, keeping the previous tree");
+                // The previous tree is marked as entirely edited,
+                // so that the next reparse does not reuse any of its nodes
+                if let Some(tree) = self.tree.as_mut() {
+                    tree.edit(&InputEdit {
+                        start_byte: 0,
+                        old_end_byte: self.rope.len_bytes(),
+                        new_end_byte: rope.len_bytes(),
+                        start_position: Point::new(0, 0),
+                        old_end_position: end_point(&self.rope),
+                        new_end_position: end_point(&rope),
+                    });
+                }
+                self.stale_tree = true;
+            }
+            None => self.tree = None,
+        }
+        self.rope = rope;
+        self.content_changes = None;
         self.dirty = true;
         self.owner = BufferOwner::User;
     }
@@ -360,6 +846,318 @@ impl Buffer {
         self.rope.len_lines().saturating_sub(deduction)
     }
 
+    /// The number of chars of the given line, excluding its trailing newline.
+    /// Returns 0 if the line does not exist.
+    pub(crate) fn line_len_chars(&self, line: usize) -> usize {
+        self.rope
+            .get_line(line)
+            .map(|line| {
+                let len = line.len_chars();
+                match line.get_char(len.saturating_sub(1)) {
+                    Some('\n') => len - 1,
+                    _ => len,
+                }
+            })
+            .unwrap_or(0)
+    }
+
+    /// The index of the last char, which is `CharIndex(0)` if the buffer is empty.
+    pub(crate) fn last_char_index(&self) -> CharIndex {
+        CharIndex(self.rope.len_chars().saturating_sub(1))
+    }
+
+    /// Clamps the ranges (including the anchors of extended selections)
+    /// of `selection_set` into `0..=len_chars()`, which is necessary after
+    /// the content is modified without going through `selection_set`.
+    pub(crate) fn clamp_selection_set(
+        &self,
+        selection_set: SelectionSet,
+    ) -> anyhow::Result<SelectionSet> {
+        selection_set.clamp(CharIndex(self.len_chars()))
+    }
+
+    /// The start of the given 0-based `line`, clamped to the last line.
+    ///
+    /// Line numbers shown to the user are 1-based, so `:250` should be passed as `249`.
+    #[allow(dead_code)]
+    pub(crate) fn clamp_line(&self, line: usize) -> CharIndex {
+        let line = line.min(self.len_lines().saturating_sub(1));
+        CharIndex(self.rope.line_to_char(line))
+    }
+
+    /// The start of the line located at `percent` of this buffer, like Vim's `N%`,
+    /// where `0` is the first line and `100` (or above) is the last line.
+    #[allow(dead_code)]
+    pub(crate) fn char_at_percent(&self, percent: u8) -> CharIndex {
+        let percent = (percent as usize).min(100);
+        // Rounded up, so that the 1-based line is `ceil(percent * len_lines / 100)`
+        let line = (percent * self.len_lines()).div_ceil(100);
+        self.clamp_line(line.saturating_sub(1))
+    }
+
+    /// The start of the first word after `from`, or the end of the buffer if there is none.
+    ///
+    /// Words are found using Unicode word segmentation, so that this also works
+    /// within strings and comments. If `subword` is true, words are further split
+    /// at underscores and case changes, for example `fooBar` and `foo_bar`
+    /// both consist of the subwords `foo` and `bar`.
+    #[allow(dead_code)]
+    pub(crate) fn next_word_boundary(&self, from: CharIndex, subword: bool) -> CharIndex {
+        self.word_ranges(subword)
+            .into_iter()
+            .find(|range| range.start > from)
+            .map(|range| range.start)
+            .unwrap_or(CharIndex(self.len_chars()))
+    }
+
+    /// The start of the last word before `from`, or the start of the buffer if there is none.
+    #[allow(dead_code)]
+    pub(crate) fn previous_word_boundary(&self, from: CharIndex, subword: bool) -> CharIndex {
+        self.word_ranges(subword)
+            .into_iter()
+            .rev()
+            .find(|range| range.start < from)
+            .map(|range| range.start)
+            .unwrap_or(CharIndex(0))
+    }
+
+    /// The (exclusive) end of the first word that ends after `from`,
+    /// or the end of the buffer if there is none.
+    #[allow(dead_code)]
+    pub(crate) fn word_end_boundary(&self, from: CharIndex, subword: bool) -> CharIndex {
+        self.word_ranges(subword)
+            .into_iter()
+            .find(|range| range.end > from)
+            .map(|range| range.end)
+            .unwrap_or(CharIndex(self.len_chars()))
+    }
+
+    /// Sorted ranges of the Unicode words of this buffer,
+    /// which are segments that contain at least one alphanumeric char.
+    fn word_ranges(&self, subword: bool) -> Vec<CharIndexRange> {
+        let content = self.content();
+        content
+            .split_word_bound_indices()
+            .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
+            .flat_map(|(byte_start, word)| {
+                let byte_ranges = if subword {
+                    subword_byte_ranges(word)
+                } else {
+                    vec![0..word.len()]
+                };
+                byte_ranges
+                    .into_iter()
+                    .map(move |range| (byte_start + range.start)..(byte_start + range.end))
+            })
+            .map(|range| {
+                (CharIndex(self.rope.byte_to_char(range.start))
+                    ..CharIndex(self.rope.byte_to_char(range.end)))
+                    .into()
+            })
+            .collect_vec()
+    }
+
+    /// The start of the first blank line that ends the paragraph at or after `from`,
+    /// or the end of the buffer if there is none.
+    #[allow(dead_code)]
+    pub(crate) fn next_paragraph(&self, from: CharIndex) -> anyhow::Result<CharIndex> {
+        let line = self.char_to_line(from)?;
+        match (line + 1..self.len_lines())
+            .find(|&line| self.is_blank_line(line) && !self.is_blank_line(line - 1))
+        {
+            Some(line) => self.line_to_char(line),
+            None => Ok(CharIndex(self.len_chars())),
+        }
+    }
+
+    /// The start of the last blank line that precedes the paragraph before `from`,
+    /// or the start of the buffer if there is none.
+    #[allow(dead_code)]
+    pub(crate) fn previous_paragraph(&self, from: CharIndex) -> anyhow::Result<CharIndex> {
+        let line = self.char_to_line(from)?;
+        match (0..line)
+            .rev()
+            .find(|&line| self.is_blank_line(line) && !self.is_blank_line(line + 1))
+        {
+            Some(line) => self.line_to_char(line),
+            None => Ok(CharIndex(0)),
+        }
+    }
+
+    fn is_blank_line(&self, line: usize) -> bool {
+        self.rope
+            .get_line(line)
+            .map(|line| line.chars().all(char::is_whitespace))
+            .unwrap_or(true)
+    }
+
+    /// The start of the first Markdown heading after `from`.
+    ///
+    /// Returns `None` if there is none, or if this buffer is not parsed as Markdown.
+    #[allow(dead_code)]
+    pub(crate) fn next_heading(&self, from: CharIndex) -> anyhow::Result<Option<CharIndex>> {
+        Ok(self
+            .heading_starts()?
+            .into_iter()
+            .find(|&start| start > from))
+    }
+
+    /// The start of the last Markdown heading before `from`.
+    #[allow(dead_code)]
+    pub(crate) fn previous_heading(&self, from: CharIndex) -> anyhow::Result<Option<CharIndex>> {
+        Ok(self
+            .heading_starts()?
+            .into_iter()
+            .rev()
+            .find(|&start| start < from))
+    }
+
+    /// The sorted starts of the `atx_heading` and `setext_heading` nodes of `tree_sitter_md`.
+    fn heading_starts(&self) -> anyhow::Result<Vec<CharIndex>> {
+        self.traverse(Order::Pre)
+            .into_iter()
+            .flatten()
+            .filter(|node| matches!(node.kind(), "atx_heading" | "setext_heading"))
+            .map(|node| self.byte_to_char(node.start_byte()))
+            .collect()
+    }
+
+    /// The level, text and start of each Markdown heading, in document order.
+    ///
+    /// Useful for Markdown files, which usually have no language server providing document symbols.
+    pub(crate) fn markdown_outline(&self) -> Vec<(usize, String, CharIndex)> {
+        self.traverse(Order::Pre)
+            .into_iter()
+            .flatten()
+            .filter_map(|node| {
+                let level = match node.kind() {
+                    // The marker kinds range from `atx_h1_marker` to `atx_h6_marker`
+                    "atx_heading" => (0..node.child_count())
+                        .filter_map(|index| node.child(index))
+                        .find_map(|child| {
+                            child
+                                .kind()
+                                .strip_prefix("atx_h")?
+                                .strip_suffix("_marker")?
+                                .parse()
+                                .ok()
+                        })?,
+                    "setext_heading" => (0..node.child_count())
+                        .filter_map(|index| node.child(index))
+                        .find_map(|child| match child.kind() {
+                            "setext_h1_underline" => Some(1),
+                            "setext_h2_underline" => Some(2),
+                            _ => None,
+                        })?,
+                    _ => return None,
+                };
+                let text = node
+                    .child_by_field_name("heading_content")
+                    .and_then(|content| self.rope.get_byte_slice(content.byte_range()))
+                    .map(|content| content.to_string().trim().to_string())
+                    .unwrap_or_default();
+                let start = self.byte_to_char(node.start_byte()).ok()?;
+                Some((level, text, start))
+            })
+            .collect()
+    }
+
+    /// The destination of the Markdown link under `cursor`, which can be an inline link,
+    /// an autolink, a reference-style link or a link reference definition.
+    ///
+    /// Returns `None` if the cursor is not on a link, or the reference of the link is undefined.
+    #[allow(dead_code)]
+    pub(crate) fn markdown_link_at(&self, cursor: CharIndex) -> Option<String> {
+        let byte = self.char_to_byte(cursor).ok()?;
+        let block = std::iter::successors(self.node_at(cursor, false), |node| node.parent())
+            .find(|node| matches!(node.kind(), "inline" | "link_reference_definition"))?;
+        if block.kind() == "link_reference_definition" {
+            return self.markdown_link_destination(&self.rope.to_string(), block);
+        }
+
+        // The block grammar of `tree_sitter_md` leaves the content of `inline` nodes unparsed,
+        // so it is parsed separately with the inline grammar
+        let inline = self.rope.get_byte_slice(block.byte_range())?.to_string();
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_md::INLINE_LANGUAGE.into())
+            .ok()?;
+        let tree = parser.parse(&inline, None)?;
+        let offset = byte - block.start_byte();
+        let node = tree.root_node().descendant_for_byte_range(offset, offset)?;
+        let link = std::iter::successors(Some(node), |node| node.parent()).find(|node| {
+            matches!(
+                node.kind(),
+                "inline_link"
+                    | "uri_autolink"
+                    | "full_reference_link"
+                    | "collapsed_reference_link"
+                    | "shortcut_link"
+            )
+        })?;
+        let text_of = |node: Node| inline.get(node.byte_range()).map(str::to_string);
+        let child_of_kind = |kind: &str| {
+            (0..link.child_count())
+                .filter_map(|index| link.child(index))
+                .find(|child| child.kind() == kind)
+        };
+        match link.kind() {
+            "inline_link" => self.markdown_link_destination(&inline, link),
+            "uri_autolink" => Some(
+                text_of(link)?
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string(),
+            ),
+            _ => {
+                let label = match link.kind() {
+                    "full_reference_link" => text_of(child_of_kind("link_label")?)?,
+                    _ => text_of(child_of_kind("link_text")?)?,
+                };
+                self.markdown_link_reference(&label)
+            }
+        }
+    }
+
+    /// The destination of the link reference definition labelled `label`,
+    /// labels are matched case-insensitively, ignoring the surrounding brackets and extra whitespace.
+    fn markdown_link_reference(&self, label: &str) -> Option<String> {
+        let normalize = |label: &str| {
+            label
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split_whitespace()
+                .join(" ")
+                .to_lowercase()
+        };
+        let label = normalize(label);
+        let content = self.rope.to_string();
+        self.nodes_of_kind("link_reference_definition")
+            .into_iter()
+            .find(|definition| {
+                (0..definition.child_count())
+                    .filter_map(|index| definition.child(index))
+                    .find(|child| child.kind() == "link_label")
+                    .and_then(|child| content.get(child.byte_range()))
+                    .is_some_and(|other| normalize(other) == label)
+            })
+            .and_then(|definition| self.markdown_link_destination(&content, definition))
+    }
+
+    /// The `link_destination` child of `node`, without the optional angle brackets.
+    fn markdown_link_destination(&self, source: &str, node: Node) -> Option<String> {
+        let destination = (0..node.child_count())
+            .filter_map(|index| node.child(index))
+            .find(|child| child.kind() == "link_destination")?;
+        Some(
+            source
+                .get(destination.byte_range())?
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string(),
+        )
+    }
+
     pub(crate) fn char_to_line(&self, char_index: CharIndex) -> anyhow::Result<usize> {
         Ok(self.rope.try_char_to_line(char_index.0)?)
     }
@@ -385,6 +1183,39 @@ impl Buffer {
         })
     }
 
+    /// Unlike `Position`, the column of `lsp_types::Position` is measured in UTF-16 code units.
+    pub(crate) fn char_to_lsp_position(
+        &self,
+        char_index: CharIndex,
+    ) -> anyhow::Result<lsp_types::Position> {
+        let line = self.char_to_line(char_index)?;
+        let line_start_utf16 = self
+            .rope
+            .try_char_to_utf16_cu(self.rope.try_line_to_char(line)?)?;
+        let utf16 = self.rope.try_char_to_utf16_cu(char_index.0)?;
+        Ok(lsp_types::Position {
+            line: line as u32,
+            character: (utf16 - line_start_utf16) as u32,
+        })
+    }
+
+    /// The inverse of `Buffer::char_to_lsp_position`.
+    pub(crate) fn lsp_position_to_char(
+        &self,
+        position: lsp_types::Position,
+    ) -> anyhow::Result<CharIndex> {
+        let line = position.line as usize;
+        let line_start = self.rope.try_line_to_char(line)?;
+        let line_start_utf16 = self.rope.try_char_to_utf16_cu(line_start)?;
+        // Columns past the end of the line are clamped, instead of landing on the next line
+        let line_end_utf16 = self
+            .rope
+            .try_char_to_utf16_cu(line_start + self.line_len_chars(line))?;
+        Ok(CharIndex(self.rope.try_utf16_cu_to_char(
+            (line_start_utf16 + position.character as usize).min(line_end_utf16),
+        )?))
+    }
+
     pub(crate) fn position_to_char(&self, position: Position) -> anyhow::Result<CharIndex> {
         let line = position.line.clamp(0, self.len_lines());
         let column = position.column.clamp(
@@ -428,24 +1259,175 @@ impl Buffer {
         })
     }
 
-    pub(crate) fn get_current_node<'a>(
-        &'a self,
-        selection: &Selection,
-        get_largest_end: bool,
-    ) -> anyhow::Result<Option<Node<'a>>> {
-        let Some(tree) = self.tree.as_ref() else {
-            return Ok(None);
-        };
-        let range = selection.range();
-        let start = self.char_to_byte(range.start)?;
-        let (start, end) = if get_largest_end {
-            (start, start + 1)
+    /// Returns the smallest node that contains `char_index`,
+    /// `char_index` beyond the end of the buffer is treated as the last character.
+    ///
+    /// Returns `None` if the buffer is empty.
+    pub(crate) fn node_at(&self, char_index: CharIndex, named: bool) -> Option<Node> {
+        let last_char_index = self.len_chars().checked_sub(1)?;
+        let byte = self
+            .char_to_byte(CharIndex(char_index.0.min(last_char_index)))
+            .ok()?;
+        let root_node = self.tree.as_ref()?.root_node();
+        if named {
+            root_node.named_descendant_for_byte_range(byte, byte)
         } else {
-            (start, self.char_to_byte(range.end)?)
-        };
-        let node = tree
-            .root_node()
-            .descendant_for_byte_range(start, end)
+            root_node.descendant_for_byte_range(byte, byte)
+        }
+    }
+
+    /// If `named` is true, anonymous ancestors (if any) are skipped.
+    #[allow(dead_code)]
+    pub(crate) fn parent_node<'a>(&self, node: Node<'a>, named: bool) -> Option<Node<'a>> {
+        std::iter::successors(node.parent(), |node| node.parent())
+            .find(|node| !named || node.is_named())
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn first_child_node<'a>(&self, node: Node<'a>, named: bool) -> Option<Node<'a>> {
+        if named {
+            node.named_child(0)
+        } else {
+            node.child(0)
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn next_sibling_node<'a>(&self, node: Node<'a>, named: bool) -> Option<Node<'a>> {
+        if named {
+            node.next_named_sibling()
+        } else {
+            node.next_sibling()
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn prev_sibling_node<'a>(&self, node: Node<'a>, named: bool) -> Option<Node<'a>> {
+        if named {
+            node.prev_named_sibling()
+        } else {
+            node.prev_sibling()
+        }
+    }
+
+    /// The text of `node`, which is sliced by its byte range.
+    pub(crate) fn node_text(&self, node: Node) -> anyhow::Result<String> {
+        Ok(self
+            .rope
+            .get_byte_slice(node.byte_range())
+            .ok_or_else(|| anyhow::anyhow!("The node is out of the bounds of the buffer"))?
+            .to_string())
+    }
+
+    /// A selection that covers the char range of `node`.
+    pub(crate) fn select_node(&self, node: Node) -> anyhow::Result<Selection> {
+        Ok(Selection::new(
+            self.byte_range_to_char_index_range(&node.byte_range())?,
+        ))
+    }
+
+    /// Expands `selection` to the nearest enclosing named node that is larger than `selection`.
+    #[allow(dead_code)]
+    pub(crate) fn expand_selection(
+        &self,
+        selection: &Selection,
+        expansions: &mut SelectionExpansions,
+    ) -> anyhow::Result<Option<Selection>> {
+        let byte_range = self.char_index_range_to_byte_range(selection.range())?;
+        let Some(node) = self.tree.as_ref().and_then(|tree| {
+            tree.root_node()
+                .named_descendant_for_byte_range(byte_range.start, byte_range.end)
+        }) else {
+            return Ok(None);
+        };
+        let Some(node) = std::iter::successors(Some(node), |node| node.parent())
+            .find(|node| node.is_named() && node.byte_range() != byte_range)
+        else {
+            return Ok(None);
+        };
+        expansions.0.push(selection.clone());
+        Ok(Some(self.select_node(node)?))
+    }
+
+    /// Reverses the last expansion made by `Buffer::expand_selection`, if any.
+    /// Otherwise, shrinks `selection` to the largest named node within it that contains `cursor`.
+    #[allow(dead_code)]
+    pub(crate) fn shrink_selection(
+        &self,
+        selection: &Selection,
+        cursor: CharIndex,
+        expansions: &mut SelectionExpansions,
+    ) -> anyhow::Result<Option<Selection>> {
+        let range = selection.range();
+        if let Some(previous) = expansions.0.pop() {
+            if range.start <= previous.range().start && previous.range().end <= range.end {
+                return Ok(Some(previous));
+            }
+            // The expansions are stale, because the selection was changed by other means
+            expansions.0.clear();
+        }
+
+        let byte_range = self.char_index_range_to_byte_range(range)?;
+        let cursor_byte = self.char_to_byte(cursor)?;
+        let Some(node) = self.tree.as_ref().and_then(|tree| {
+            tree.root_node()
+                .named_descendant_for_byte_range(cursor_byte, cursor_byte)
+        }) else {
+            return Ok(None);
+        };
+        let Some(node) = std::iter::successors(Some(node), |node| node.parent())
+            .take_while(|node| {
+                node.byte_range() != byte_range
+                    && byte_range.start <= node.start_byte()
+                    && node.end_byte() <= byte_range.end
+            })
+            .filter(|node| node.is_named())
+            .last()
+        else {
+            return Ok(None);
+        };
+        Ok(Some(self.select_node(node)?))
+    }
+
+    /// Returns the S-expression of the smallest named node covering `range`,
+    /// or of the root node if `range` is `None`, which helps when writing Tree-sitter queries.
+    pub(crate) fn sexp(&self, range: Option<CharIndexRange>) -> anyhow::Result<String> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Err(anyhow::anyhow!("This buffer is not parsed by Tree-sitter."));
+        };
+        let root_node = tree.root_node();
+        let node = match range {
+            Some(range) => root_node
+                .named_descendant_for_byte_range(
+                    self.char_to_byte(range.start)?,
+                    self.char_to_byte(range.end)?,
+                )
+                .unwrap_or(root_node),
+            None => root_node,
+        };
+        Ok(node.to_sexp())
+    }
+
+    /// The node is resolved from the range of `selection` against the current tree,
+    /// so it is never stale after an edit.
+    pub(crate) fn get_current_node<'a>(
+        &'a self,
+        selection: &Selection,
+        get_largest_end: bool,
+    ) -> anyhow::Result<Option<Node<'a>>> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(None);
+        };
+        let range = selection.range();
+        let start = self.char_to_byte(range.start)?;
+        let (start, end) = if get_largest_end {
+            (start, start + 1)
+        } else {
+            (start, self.char_to_byte(range.end)?)
+        };
+        let node = tree
+            .root_node()
+            .descendant_for_byte_range(start, end)
             .unwrap_or_else(|| tree.root_node());
 
         // Get the most ancestral node of this range
@@ -481,12 +1463,193 @@ impl Buffer {
         })
     }
 
-    #[cfg(test)]
+    /// Returns the range and capture index of the captures of `query` that overlap with `range`.
+    /// The query only runs within `range`, so that re-highlighting a small portion
+    /// of a large buffer stays cheap.
+    #[allow(dead_code)]
+    pub(crate) fn highlight_ranges(
+        &self,
+        range: Range<CharIndex>,
+        query: &Query,
+    ) -> anyhow::Result<impl Iterator<Item = (Range<CharIndex>, u32)>> {
+        let byte_range = self.char_to_byte(range.start)?..self.char_to_byte(range.end)?;
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(Vec::new().into_iter());
+        };
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(byte_range.clone());
+        let mut captures = cursor.captures(query, tree.root_node(), |node: Node| {
+            self.rope.byte_slice(node.byte_range()).chunks()
+        });
+
+        let mut result = Vec::new();
+        while let Some((query_match, capture_index)) = captures.next() {
+            let capture = query_match.captures[*capture_index];
+            let node_range = capture.node.byte_range();
+            if node_range.start < byte_range.end && node_range.end > byte_range.start {
+                result.push((
+                    self.byte_to_char(node_range.start)?..self.byte_to_char(node_range.end)?,
+                    capture.index,
+                ));
+            }
+        }
+        Ok(result.into_iter())
+    }
+
+    /// Returns the ranges of the nodes captured by `pattern`, which is a Tree-sitter query
+    /// where metavariables such as `$A` are captures such as `@A`.
+    ///
+    /// A pattern without any capture captures the whole match,
+    /// for example `(call_expression function: (identifier))`.
+    #[allow(dead_code)]
+    pub(crate) fn structural_search(&self, pattern: &str) -> anyhow::Result<Vec<CharIndexRange>> {
+        let (Some(language), Some(tree)) = (self.treesitter_language(), self.tree.as_ref()) else {
+            return Err(anyhow::anyhow!(
+                "Unable to run structural search because no Tree-sitter language is found."
+            ));
+        };
+        let pattern = lazy_regex::regex!(r"\$([A-Za-z_][A-Za-z0-9_]*)").replace_all(pattern, "@$1");
+        let query = match Query::new(&language, &pattern) {
+            Ok(query) if query.capture_names().is_empty() => {
+                Query::new(&language, &format!("{pattern} @match"))
+            }
+            result => result,
+        }
+        .map_err(|error| {
+            anyhow::anyhow!(
+                "Invalid pattern at row {} column {}: {:?} {}",
+                error.row + 1,
+                error.column + 1,
+                error.kind,
+                error.message
+            )
+        })?;
+
+        let mut cursor = QueryCursor::new();
+        let mut captures = cursor.captures(&query, tree.root_node(), |node: Node| {
+            self.rope.byte_slice(node.byte_range()).chunks()
+        });
+        let mut byte_ranges = Vec::new();
+        while let Some((query_match, capture_index)) = captures.next() {
+            byte_ranges.push(query_match.captures[*capture_index].node.byte_range());
+        }
+        byte_ranges
+            .into_iter()
+            .sorted_by_key(|range| (range.start, range.end))
+            .dedup()
+            .map(|range| self.byte_range_to_char_index_range(&range))
+            .collect()
+    }
+
     pub(crate) fn traverse(&self, order: Order) -> Option<impl Iterator<Item = Node>> {
         self.tree.as_ref().map(|tree| traverse(tree.walk(), order))
     }
 
+    /// Returns the nodes of the given kind, in document order.
+    #[allow(dead_code)]
+    pub(crate) fn nodes_of_kind(&self, kind: &str) -> Vec<Node> {
+        self.nodes_of_kinds(&[kind])
+    }
+
+    /// Returns the ancestors of the node at `cursor` (inclusive) that define a named item,
+    /// such as `impl Foo` and `fn bar`, ordered outermost-first.
+    ///
+    /// The definition kinds are configured per language, see `Language::definition_kinds`.
+    pub(crate) fn enclosing_definitions(&self, cursor: CharIndex) -> Vec<Node> {
+        let kinds = self
+            .language
+            .as_ref()
+            .map(|language| language.definition_kinds())
+            .unwrap_or_default();
+        self.enclosing_nodes_of_kinds(cursor, kinds)
+    }
+
+    /// The names of the definitions enclosing `cursor`, ordered outermost-first,
+    /// for example `["Foo", "bar"]` within `fn bar` of `impl Foo`.
+    pub(crate) fn breadcrumbs(&self, cursor: CharIndex) -> Vec<String> {
+        self.enclosing_definitions(cursor)
+            .into_iter()
+            .filter_map(|node| {
+                let name = node
+                    .child_by_field_name("name")
+                    // `impl_item` has no name, but the type being implemented
+                    .or_else(|| node.child_by_field_name("type"))?;
+                self.node_text(name).ok()
+            })
+            .collect()
+    }
+
+    /// Returns the ancestors of the node at `cursor` (inclusive) whose kind is any of `kinds`,
+    /// ordered outermost-first.
+    pub(crate) fn enclosing_nodes_of_kinds(&self, cursor: CharIndex, kinds: &[&str]) -> Vec<Node> {
+        let mut nodes = std::iter::successors(self.node_at(cursor, false), |node| node.parent())
+            .filter(|node| kinds.contains(&node.kind()))
+            .collect_vec();
+        nodes.reverse();
+        nodes
+    }
+
+    /// Returns the nodes whose kind is any of `kinds`, in document order.
+    pub(crate) fn nodes_of_kinds(&self, kinds: &[&str]) -> Vec<Node> {
+        self.traverse(Order::Pre)
+            .map(|nodes| nodes.filter(|node| kinds.contains(&node.kind())).collect())
+            .unwrap_or_default()
+    }
+
+    /// Like `Buffer::traverse`, but only visits the nodes that overlap with `range`,
+    /// subtrees entirely outside of `range` are skipped without being visited.
+    ///
+    /// A node overlaps with `range` if it ends after the start of `range`,
+    /// and starts before the end of `range`.
+    #[allow(dead_code)]
+    pub(crate) fn traverse_range(
+        &self,
+        range: CharIndexRange,
+        order: Order,
+    ) -> anyhow::Result<impl Iterator<Item = Node<'_>>> {
+        let start = self.char_to_byte(range.start)?;
+        let end = self.char_to_byte(range.end)?;
+        let overlaps = |node: Node| node.end_byte() > start && node.start_byte() < end;
+        let mut nodes = Vec::new();
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(nodes.into_iter());
+        };
+        let mut cursor = tree.walk();
+        'visit: loop {
+            let node = cursor.node();
+            if overlaps(node) {
+                if matches!(order, Order::Pre) {
+                    nodes.push(node);
+                }
+                if cursor.goto_first_child() {
+                    continue;
+                }
+                if matches!(order, Order::Post) {
+                    nodes.push(node);
+                }
+            }
+            // Move to the next sibling, or else leave the parent
+            loop {
+                // Siblings are ordered, so the siblings starting after `range` are skipped
+                if cursor.goto_next_sibling() && cursor.node().start_byte() < end {
+                    continue 'visit;
+                }
+                if !cursor.goto_parent() {
+                    break 'visit;
+                }
+                if matches!(order, Order::Post) {
+                    nodes.push(cursor.node());
+                }
+            }
+        }
+        Ok(nodes.into_iter())
+    }
+
     /// Returns the new selection set
+    ///
+    /// The edits of `edit_transaction` are already offset by each other (see `EditTransaction::from_action_groups`),
+    /// so they can be applied sequentially.
     pub(crate) fn apply_edit_transaction(
         &mut self,
         edit_transaction: &EditTransaction,
@@ -495,6 +1658,94 @@ impl Buffer {
         update_undo_stack: bool,
         last_visible_line: u16,
     ) -> Result<SelectionSet, anyhow::Error> {
+        self.apply_edit_transaction_with_edited_ranges(
+            edit_transaction,
+            current_selection_set,
+            reparse_tree,
+            update_undo_stack,
+            last_visible_line,
+        )
+        .map(|(selection_set, _)| selection_set)
+    }
+
+    /// Inserts `text` at `at` as a single undoable edit.
+    #[allow(dead_code)]
+    pub(crate) fn insert(
+        &mut self,
+        at: CharIndex,
+        text: &str,
+        current_selection_set: SelectionSet,
+    ) -> anyhow::Result<()> {
+        self.apply_single_edit((at..at).into(), text.into(), current_selection_set)
+    }
+
+    /// Deletes the content of `range` as a single undoable edit.
+    #[allow(dead_code)]
+    pub(crate) fn delete(
+        &mut self,
+        range: Range<CharIndex>,
+        current_selection_set: SelectionSet,
+    ) -> anyhow::Result<()> {
+        self.apply_single_edit(range.into(), Rope::new(), current_selection_set)
+    }
+
+    fn apply_single_edit(
+        &mut self,
+        range: CharIndexRange,
+        new: Rope,
+        current_selection_set: SelectionSet,
+    ) -> anyhow::Result<()> {
+        let edit_transaction = EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [Action::Edit(Edit::new(&self.rope, range, new))].to_vec(),
+            )]
+            .to_vec(),
+        );
+        // Without knowing the viewport, treat every line as visible
+        // so that all highlighted spans after the edit are shifted.
+        let last_visible_line = self.len_lines().try_into().unwrap_or(u16::MAX);
+        self.apply_edit_transaction(
+            &edit_transaction,
+            current_selection_set,
+            true,
+            true,
+            last_visible_line,
+        )?;
+        Ok(())
+    }
+
+    /// Same as `apply_edit_transaction`, but also returns the post-edit ranges
+    /// of each applied edit, which already account for the shifts caused by
+    /// earlier edits of the same transaction.
+    pub(crate) fn apply_edit_transaction_with_edited_ranges(
+        &mut self,
+        edit_transaction: &EditTransaction,
+        current_selection_set: SelectionSet,
+        reparse_tree: bool,
+        update_undo_stack: bool,
+        last_visible_line: u16,
+    ) -> Result<(SelectionSet, Vec<CharIndexRange>), anyhow::Error> {
+        self.ensure_writable()?;
+        self.apply_edit_transaction_unguarded(
+            edit_transaction,
+            current_selection_set,
+            reparse_tree,
+            update_undo_stack,
+            last_visible_line,
+        )
+    }
+
+    /// Same as `apply_edit_transaction_with_edited_ranges`, but also applies to read-only buffers,
+    /// so that they can still be reloaded from disk.
+    fn apply_edit_transaction_unguarded(
+        &mut self,
+        edit_transaction: &EditTransaction,
+        current_selection_set: SelectionSet,
+        reparse_tree: bool,
+        update_undo_stack: bool,
+        last_visible_line: u16,
+    ) -> Result<(SelectionSet, Vec<CharIndexRange>), anyhow::Error> {
+        edit_transaction.validate(self.rope.len_chars())?;
         let new_selection_set = edit_transaction
             .non_empty_selections()
             .map(|selections| current_selection_set.clone().set_selections(selections))
@@ -517,7 +1768,7 @@ impl Buffer {
         };
 
         if update_undo_stack {
-            self.undo_stack.push(EditHistory {
+            self.push_undo_history(EditHistory {
                 edit_transaction: inverted_edit_transaction,
                 old_state: current_buffer_state,
                 new_state: new_buffer_state,
@@ -533,7 +1784,7 @@ impl Buffer {
 
         self.batch_id.increment();
 
-        Ok(new_selection_set)
+        Ok((new_selection_set, edit_transaction.edited_ranges()))
     }
 
     // Add these methods for undo/redo
@@ -551,12 +1802,43 @@ impl Buffer {
                 + last_line_len_bytes;
             let affected_range = byte_range.start..range_end;
 
-            self.highlighted_spans.apply_edit_mut(
-                &affected_range,
-                edit.new.len_bytes() as isize - byte_range.len() as isize,
-            );
+            let change = edit.new.len_bytes() as isize - byte_range.len() as isize;
+            self.highlighted_spans
+                .apply_edit_mut(&affected_range, change);
+
+            // Semantic spans that are touched by the edit are outdated
+            // until the language server responds with the new tokens
+            self.semantic_highlighted_spans.0.retain_mut(|span| {
+                if span.byte_range.end <= byte_range.start {
+                    true
+                } else if span.byte_range.start >= byte_range.end {
+                    span.byte_range.start = (span.byte_range.start as isize + change) as usize;
+                    span.byte_range.end = (span.byte_range.end as isize + change) as usize;
+                    true
+                } else {
+                    false
+                }
+            });
         }
 
+        // The line numbers of the folding ranges are outdated
+        // until the language server responds with the new ranges
+        self.folding_ranges = None;
+        self.document_highlights.clear();
+
+        // Likewise, inlay hints within the edited range are discarded
+        let char_change = edit.new.len_chars() as isize - edit.range.len() as isize;
+        self.inlay_hints.retain_mut(|(char_index, _)| {
+            if *char_index < edit.range.start {
+                true
+            } else if *char_index > edit.end() {
+                *char_index = CharIndex((char_index.0 as isize + char_change) as usize);
+                true
+            } else {
+                false
+            }
+        });
+
         let quickfix_list_items_with_char_index_range =
             std::mem::take(&mut self.quickfix_list_items)
                 .into_iter()
@@ -569,12 +1851,54 @@ impl Buffer {
                 })
                 .collect_vec();
 
+        // The range is computed against the content before this edit,
+        // because language servers apply the changes one after another
+        let lsp_range = self
+            .char_to_lsp_position(edit.range.start)
+            .and_then(|start| {
+                Ok(lsp_types::Range::new(
+                    start,
+                    self.char_to_lsp_position(edit.end())?,
+                ))
+            });
+        match lsp_range {
+            Ok(range) => {
+                if let Some(content_changes) = self.content_changes.as_mut() {
+                    content_changes.push(lsp_types::TextDocumentContentChangeEvent {
+                        range: Some(range),
+                        range_length: None,
+                        text: edit.new.to_string(),
+                    })
+                }
+            }
+            Err(_) => self.content_changes = None,
+        }
+
+        let start_byte = self.char_to_byte(edit.range.start)?;
+        let old_end_byte = self.char_to_byte(edit.end())?;
+        let start_position = self.byte_to_point(start_byte)?;
+        let old_end_position = self.byte_to_point(old_end_byte)?;
+
         // Update the content
         self.rope.try_remove(edit.range.start.0..edit.end().0)?;
         self.rope
             .try_insert(edit.range.start.0, edit.new.to_string().as_str())?;
         self.dirty = true;
 
+        // Update the syntax tree, so that it can be reused for incremental reparsing
+        let new_end_byte = start_byte + edit.new.len_bytes();
+        let new_end_position = self.byte_to_point(new_end_byte)?;
+        if let Some(tree) = self.tree.as_mut() {
+            tree.edit(&InputEdit {
+                start_byte,
+                old_end_byte,
+                new_end_byte,
+                start_position,
+                old_end_position,
+                new_end_position,
+            });
+        }
+
         self.owner = BufferOwner::User;
 
         // Update all the positional spans (by using the char index ranges computed before the content is updated
@@ -597,14 +1921,16 @@ impl Buffer {
                 false
             }
         });
-        self.diagnostics.retain_mut(|diagnostic| {
-            if let Some(range) = diagnostic.range.apply_edit(edit) {
-                diagnostic.range = range;
-                true
-            } else {
-                false
-            }
-        });
+        for diagnostics in self.diagnostics.values_mut() {
+            diagnostics.retain_mut(|diagnostic| {
+                if let Some(range) = diagnostic.range.apply_edit(edit) {
+                    diagnostic.range = range;
+                    true
+                } else {
+                    false
+                }
+            });
+        }
         let max_char_index = CharIndex(self.len_chars());
         self.selection_set_history = std::mem::take(&mut self.selection_set_history)
             .apply(|selection_set| selection_set.apply_edit(edit, max_char_index));
@@ -612,6 +1938,21 @@ impl Buffer {
         Ok(())
     }
 
+    /// The version of the latest edit of the document,
+    /// which language servers might not be notified of yet, due to debouncing.
+    pub(crate) fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Returns the new version of the document, and the changes since the previous version,
+    /// which are `None` if only a full sync is possible.
+    pub(crate) fn take_content_changes(
+        &mut self,
+    ) -> (i32, Option<Vec<lsp_types::TextDocumentContentChangeEvent>>) {
+        self.version += 1;
+        (self.version, self.content_changes.replace(Vec::new()))
+    }
+
     pub(crate) fn batch_id(&self) -> &SyntaxHighlightRequestBatchId {
         &self.batch_id
     }
@@ -630,39 +1971,133 @@ impl Buffer {
         }
     }
 
+    /// Returns every error and missing node of the syntax tree, sorted by their position.
+    /// Note that the range of a missing node is always empty.
+    #[allow(dead_code)]
+    pub(crate) fn syntax_errors(&self) -> Vec<(CharIndexRange, SyntaxErrorKind)> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Vec::new();
+        };
+        if !tree.root_node().has_error() {
+            return Vec::new();
+        }
+        traverse(tree.walk(), Order::Pre)
+            .filter_map(|node| {
+                let kind = if node.is_error() {
+                    SyntaxErrorKind::Error
+                } else if node.is_missing() {
+                    SyntaxErrorKind::Missing
+                } else {
+                    return None;
+                };
+                Some((
+                    self.byte_range_to_char_index_range(&node.byte_range())
+                        .ok()?,
+                    kind,
+                ))
+            })
+            .collect()
+    }
+
+    /// `language_registry` is used for detecting the language of `path`,
+    /// the builtin languages are used if it is `None`.
     pub(crate) fn from_path(
         path: &CanonicalizedPath,
         enable_tree_sitter: bool,
+        language_registry: Option<&LanguageRegistry>,
     ) -> anyhow::Result<Buffer> {
-        let content = path.read()?;
+        let (content, encoding, had_bom) = decode(&std::fs::read(path)?)?;
         let language = if enable_tree_sitter {
-            language::from_path(path).or_else(|| language::from_content_directive(&content))
+            let default_registry = LanguageRegistry::default();
+            language_registry
+                .unwrap_or(&default_registry)
+                .find_by_path(path)
+                .map_err(|error| log::info!("Buffer::from_path: {error}"))
+                .ok()
+                .or_else(|| language::from_content_directive(&content))
         } else {
             None
         };
 
-        let mut buffer = Buffer::new(
+        let mut buffer = Buffer::try_new(
             language
                 .as_ref()
                 .and_then(|language| language.tree_sitter_language()),
             &content,
-        );
+        )?;
 
         buffer.path = Some(path.clone());
         buffer.language = language;
+        buffer.encoding = encoding;
+        buffer.had_bom = had_bom;
+        buffer.set_read_only(
+            std::fs::metadata(path)
+                .map(|metadata| metadata.permissions().readonly())
+                .unwrap_or(false),
+        );
+        buffer.set_parse_timeout(PARSE_TIMEOUT_MICROS);
 
         Ok(buffer)
     }
 
+    /// The parser of this buffer is reused, it is only constructed once in `Buffer::new`.
+    ///
+    /// The reparsing is incremental, because every `apply_edit` also edits the existing tree.
+    ///
+    /// A buffer whose previous parse timed out is reparsed even without a tree.
     pub(crate) fn reparse_tree(&mut self) -> anyhow::Result<()> {
-        let mut parser = tree_sitter::Parser::new();
-        if let Some(tree) = self.tree.as_ref() {
-            parser.set_language(&tree.language())?;
-            self.tree = parser.parse(self.rope.to_string(), None);
+        if self.tree.is_some() || self.stale_tree {
+            #[cfg(feature = "metrics")]
+            let start = std::time::Instant::now();
+
+            match self.parser.parse_rope(&self.rope, self.tree.as_ref()) {
+                Some(tree) => {
+                    self.tree = Some(tree);
+                    self.stale_tree = false;
+                }
+                None => {
+                    log::warn!(
+                        "Buffer::reparse_tree: parsing timed out, keeping the previous tree"
+                    );
+                    self.stale_tree = true;
+                }
+            }
+
+            #[cfg(feature = "metrics")]
+            {
+                let duration = start.elapsed();
+                self.parse_metrics.last = Some(duration);
+                self.parse_metrics.total += duration;
+                self.parse_metrics.count += 1;
+            }
         }
         Ok(())
     }
 
+    /// Limits the duration of each reparse, 0 means unlimited.
+    ///
+    /// A reparse that times out keeps the previous tree, see `Buffer::is_tree_stale`.
+    pub(crate) fn set_parse_timeout(&mut self, micros: u64) {
+        self.parser.set_timeout_micros(micros)
+    }
+
+    /// Whether the latest reparse timed out, so the tree does not reflect the latest content.
+    pub(crate) fn is_tree_stale(&self) -> bool {
+        self.stale_tree
+    }
+
+    /// The duration of the latest reparse, `None` if this buffer has not been reparsed.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn last_parse_duration(&self) -> Option<std::time::Duration> {
+        self.parse_metrics.last
+    }
+
+    /// The average duration of every reparse so far.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn average_parse_duration(&self) -> Option<std::time::Duration> {
+        (self.parse_metrics.count > 0).then(|| self.parse_metrics.total / self.parse_metrics.count)
+    }
+
     pub(crate) fn get_formatted_content(&self) -> Option<String> {
         if let Some(content) = self.language.as_ref().and_then(|language| {
             language.formatter().map(|formatter| {
@@ -691,13 +2126,67 @@ impl Buffer {
         }
 
         if let Some(path) = &self.path {
-            path.write(&self.content())?;
+            std::fs::write(path, self.encoded_content()?)?;
             self.dirty = false;
             Ok(Some(path.clone()))
         } else {
-            log::info!("Buffer has no path");
-            Ok(None)
+            Err(anyhow::anyhow!(
+                "Unable to save a buffer that has no path, use save as instead"
+            ))
+        }
+    }
+
+    /// Writes the content to `path`, which becomes the path of this buffer.
+    ///
+    /// The buffer is reparsed if the language implied by `path` differs from the current language.
+    /// Returns an error without writing anything if the extension of `path` is unsupported.
+    #[allow(dead_code)]
+    pub(crate) fn save_as(&mut self, path: &std::path::Path) -> anyhow::Result<CanonicalizedPath> {
+        let language_by_extension =
+            match path.extension() {
+                Some(extension) => {
+                    let extension = extension.to_string_lossy();
+                    Some(language::from_extension(&extension).ok_or_else(|| {
+                        anyhow::anyhow!("Unsupported file extension: {extension:?}")
+                    })?)
+                }
+                None => None,
+            };
+
+        std::fs::write(path, self.encoded_content()?)?;
+        let path = CanonicalizedPath::try_from(path)?;
+        let language = language_by_extension.or_else(|| language::from_filename(&path));
+
+        if language != self.language {
+            self.parser = BufferParser::new(
+                language
+                    .as_ref()
+                    .and_then(|language| language.tree_sitter_language()),
+            );
+            self.treesitter_language = self.parser.language.clone();
+            self.tree = self.parser.parse_rope(&self.rope, None);
+            self.highlighted_spans = HighlightedSpans::default();
+            self.language = language;
         }
+
+        self.path = Some(path.clone());
+        self.dirty = false;
+        Ok(path)
+    }
+
+    /// The content to be written to disk, with the original line ending and encoding.
+    fn encoded_content(&self) -> anyhow::Result<Vec<u8>> {
+        encode(
+            &self.line_ending.restore(&self.content()),
+            self.encoding,
+            self.had_bom,
+        )
+    }
+
+    /// The encoding detected when the file was read, which is also used for saving.
+    #[cfg(test)]
+    pub(crate) fn encoding(&self) -> &'static Encoding {
+        self.encoding
     }
 
     pub(crate) fn save(
@@ -721,7 +2210,7 @@ impl Buffer {
         current_selection_set: SelectionSet,
         last_visible_line: u16,
     ) -> anyhow::Result<SelectionSet> {
-        let edit_transaction = self.get_edit_transaction(new_content)?;
+        let edit_transaction = self.get_edit_transaction(&LineEnding::normalize(new_content))?;
         self.apply_edit_transaction(
             &edit_transaction,
             current_selection_set,
@@ -779,14 +2268,64 @@ impl Buffer {
         self.marks.clone()
     }
 
-    /// Has the buffer changed since its last save?
-    pub(crate) fn dirty(&self) -> bool {
-        self.dirty
+    /// A read-only buffer rejects every edit, including undo and redo.
+    pub(crate) fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only
     }
 
-    pub(crate) fn byte_to_position(&self, byte_index: usize) -> anyhow::Result<Position> {
-        let char_index = self.byte_to_char(byte_index)?;
-        self.char_to_position(char_index)
+    #[cfg(test)]
+    pub(crate) fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ensure_writable(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            let name = self
+                .path
+                .as_ref()
+                .map(|path| path.display_absolute())
+                .unwrap_or_else(|| "This buffer".to_string());
+            Err(anyhow::anyhow!("{name} is read-only"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Has the buffer changed since its last save?
+    pub(crate) fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// The line ending that will be used when saving this buffer.
+    pub(crate) fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.mixed_line_endings = false;
+            self.dirty = true;
+        }
+    }
+
+    /// Returns true if the original content contains both `\n` and `\r\n`,
+    /// in which case the majority is used as the line ending of this buffer.
+    pub(crate) fn has_mixed_line_endings(&self) -> bool {
+        self.mixed_line_endings
+    }
+
+    pub(crate) fn byte_to_position(&self, byte_index: usize) -> anyhow::Result<Position> {
+        let char_index = self.byte_to_char(byte_index)?;
+        self.char_to_position(char_index)
+    }
+
+    /// Unlike `Position`, the column of `tree_sitter::Point` is measured in bytes.
+    fn byte_to_point(&self, byte_index: usize) -> anyhow::Result<Point> {
+        let row = self.byte_to_line(byte_index)?;
+        let column = byte_index - self.line_to_byte(row)?;
+        Ok(Point { row, column })
     }
 
     pub(crate) fn byte_to_line(&self, byte: usize) -> anyhow::Result<usize> {
@@ -916,7 +2455,553 @@ impl Buffer {
         last_visible_line: u16,
     ) -> anyhow::Result<(bool, SelectionSet)> {
         let before = self.rope.to_string();
-        let edit_transaction = match config.mode {
+        let edit_transaction = self.replace_all(&config)?;
+        let selection_set = self.apply_edit_transaction(
+            &edit_transaction,
+            current_selection_set,
+            true,
+            true,
+            last_visible_line,
+        )?;
+        let after = self.content();
+        let modified = before != after;
+        Ok((modified, selection_set))
+    }
+
+    /// Returns the leading whitespace of a line that is inserted at `cursor`, which is:
+    /// - one level deeper than the current line, if the cursor is after an opening bracket
+    /// - the indentation of the line of the matching opening bracket,
+    ///   if the cursor is before a closing bracket
+    /// - otherwise, the indentation of the current line
+    ///
+    /// Brackets within strings and comments are ignored.
+    #[allow(dead_code)]
+    pub(crate) fn indent_for_new_line(
+        &self,
+        cursor: CharIndex,
+        indent_style: IndentStyle,
+    ) -> anyhow::Result<String> {
+        let leading_whitespace = |line_index: usize| {
+            self.rope
+                .get_line(line_index)
+                .map(|line| {
+                    line.chars()
+                        .take_while(|char| char.is_whitespace() && char != &'\n')
+                        .collect::<String>()
+                })
+                .unwrap_or_default()
+        };
+        let line_index = self.char_to_line(cursor)?;
+        let current_indentation = leading_whitespace(line_index);
+        let line_start = self.line_to_char(line_index)?;
+        let before_cursor = self.rope.slice(line_start.0..cursor.0).to_string();
+        let after_cursor =
+            self.slice(&(cursor..self.line_to_char_range(line_index)?.end).into())?;
+        let next_char = after_cursor.chars().find(|char| !char.is_whitespace());
+        let previous_char = before_cursor.trim_end().chars().last();
+
+        if previous_char.is_some_and(|char| "([{".contains(char))
+            && !self.is_within_string_or_comment(cursor)?
+        {
+            return Ok(current_indentation + &indent_style.unit());
+        }
+        if next_char.is_some_and(|char| ")]}".contains(char)) {
+            let closing_byte = self.char_to_byte(
+                cursor
+                    + after_cursor
+                        .chars()
+                        .take_while(|char| char.is_whitespace())
+                        .count(),
+            )?;
+            let opening_row = self.tree.as_ref().and_then(|tree| {
+                let node = tree
+                    .root_node()
+                    .descendant_for_byte_range(closing_byte, closing_byte + 1)?;
+                (node.start_byte() == closing_byte && !node.is_named())
+                    .then(|| node.parent())
+                    .flatten()
+                    .map(|parent| parent.start_position().row)
+            });
+            if let Some(row) = opening_row {
+                return Ok(leading_whitespace(row));
+            }
+        }
+        Ok(current_indentation)
+    }
+
+    /// Returns the position of the bracket that matches the bracket at `cursor`,
+    /// which is the first or last child of the same node.
+    ///
+    /// Returns `None` if the cursor is not on a bracket, including brackets within strings and comments.
+    #[allow(dead_code)]
+    pub(crate) fn matching_bracket(&self, cursor: CharIndex) -> anyhow::Result<Option<CharIndex>> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(None);
+        };
+        let byte = self.char_to_byte(cursor)?;
+        let Some(bracket) = tree
+            .root_node()
+            .descendant_for_byte_range(byte, byte + 1)
+            .filter(|node| {
+                node.start_byte() == byte
+                    && !node.is_named()
+                    && ["(", ")", "[", "]", "{", "}", "<", ">"].contains(&node.kind())
+            })
+        else {
+            return Ok(None);
+        };
+        let Some(parent) = bracket.parent() else {
+            return Ok(None);
+        };
+        let first = parent.child(0);
+        let last = parent.child(parent.child_count().saturating_sub(1));
+        let matching = if first == Some(bracket) {
+            last
+        } else if last == Some(bracket) {
+            first
+        } else {
+            None
+        };
+        matching
+            .filter(|matching| matching != &bracket)
+            .map(|matching| self.byte_to_char(matching.start_byte()))
+            .transpose()
+    }
+
+    /// Recomputes the leading whitespace of the non-blank lines within `lines`,
+    /// where the depth of a line is the number of lines with an unclosed opening bracket
+    /// that encloses the line.
+    ///
+    /// Lines within multi-line strings and comments are left alone.
+    #[allow(dead_code)]
+    pub(crate) fn reindent(
+        &self,
+        lines: Range<usize>,
+        indent_style: IndentStyle,
+    ) -> anyhow::Result<EditTransaction> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Err(anyhow::anyhow!(
+                "Unable to reindent because no Tree-sitter language is found."
+            ));
+        };
+        let is_bracket =
+            |node: &Node, brackets: &str| !node.is_named() && brackets.contains(node.kind());
+        let action_groups = lines
+            .map(|line_index| -> anyhow::Result<Option<ActionGroup>> {
+                let Some(line) = self.rope.get_line(line_index) else {
+                    return Ok(None);
+                };
+                let indentation = line
+                    .chars()
+                    .take_while(|char| char.is_whitespace() && char != &'\n')
+                    .count();
+                let line_start = self.line_to_char(line_index)?;
+                let content_start = line_start + indentation;
+                if line.chars().all(char::is_whitespace)
+                    || self.is_within_string_or_comment(content_start)?
+                {
+                    return Ok(None);
+                }
+                let content_byte = self.char_to_byte(content_start)?;
+                let mut node = tree
+                    .root_node()
+                    .descendant_for_byte_range(content_byte, content_byte);
+                let mut opening_rows = HashSet::new();
+                while let Some(current) = node {
+                    let is_closed_by_this_line = current
+                        .child(current.child_count().saturating_sub(1))
+                        .is_some_and(|closing| {
+                            is_bracket(&closing, ")]}") && closing.start_byte() == content_byte
+                        });
+                    if let Some(opening) = current
+                        .child(0)
+                        .filter(|opening| is_bracket(opening, "([{"))
+                    {
+                        if opening.start_position().row < line_index && !is_closed_by_this_line {
+                            opening_rows.insert(opening.start_position().row);
+                        }
+                    }
+                    node = current.parent();
+                }
+                let new_indentation = indent_style.unit().repeat(opening_rows.len());
+                let old_indentation = line.slice(..indentation).to_string();
+                Ok((new_indentation != old_indentation).then(|| {
+                    ActionGroup::new(
+                        [Action::Edit(Edit::new(
+                            &self.rope,
+                            (line_start..content_start).into(),
+                            new_indentation.into(),
+                        ))]
+                        .to_vec(),
+                    )
+                }))
+            })
+            .filter_map(Result::transpose)
+            .try_collect()?;
+        Ok(EditTransaction::from_action_groups(action_groups))
+    }
+
+    /// Returns the transaction for typing `typed` at `cursor`, which either:
+    /// - skips over `typed` if it is the closing character of a pair and is already the next character
+    /// - inserts `typed` with its closing character, and places the cursor between them
+    ///
+    /// Returns `None` if `typed` should be inserted as usual, for example within strings and comments,
+    /// or before a word.
+    #[allow(dead_code)]
+    pub(crate) fn auto_pair_edit(
+        &self,
+        cursor: CharIndex,
+        typed: char,
+    ) -> anyhow::Result<Option<EditTransaction>> {
+        let pairs = self
+            .language
+            .as_ref()
+            .map(|language| language.auto_pairs())
+            .unwrap_or(shared::language::DEFAULT_AUTO_PAIRS);
+        let previous_char = (cursor.0 > 0)
+            .then(|| self.rope.get_char(cursor.0 - 1))
+            .flatten();
+        let next_char = self.rope.get_char(cursor.0);
+        let move_cursor = |offset: usize| {
+            Action::Select(Selection::new((cursor + offset..cursor + offset).into()))
+        };
+
+        if next_char == Some(typed) && pairs.iter().any(|(_, close)| *close == typed) {
+            return Ok(Some(EditTransaction::from_action_groups(
+                [ActionGroup::new([move_cursor(1)].to_vec())].to_vec(),
+            )));
+        }
+        let Some((open, close)) = pairs.iter().find(|(open, _)| *open == typed) else {
+            return Ok(None);
+        };
+        let is_closing = |char: char| pairs.iter().any(|(_, close)| *close == char);
+        let before_word = next_char.is_some_and(|char| !char.is_whitespace() && !is_closing(char));
+        // Quotes after a word are more likely to be apostrophes
+        let after_word = open == close
+            && previous_char.is_some_and(|char| char.is_alphanumeric() || char == '_');
+        if before_word || after_word || self.is_within_string_or_comment(cursor)? {
+            return Ok(None);
+        }
+        Ok(Some(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit::new(
+                        &self.rope,
+                        (cursor..cursor).into(),
+                        format!("{open}{close}").into(),
+                    )),
+                    move_cursor(1),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        )))
+    }
+
+    fn is_within_string_or_comment(&self, cursor: CharIndex) -> anyhow::Result<bool> {
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(false);
+        };
+        let byte = self.char_to_byte(cursor)?;
+        let mut node = tree.root_node().descendant_for_byte_range(byte, byte);
+        while let Some(current) = node {
+            // The cursor at the start of a node is not within it
+            if current.start_byte() < byte
+                && (current.kind().contains("string") || current.kind().contains("comment"))
+            {
+                return Ok(true);
+            }
+            node = current.parent();
+        }
+        Ok(false)
+    }
+
+    /// Comments the non-blank lines within `lines` with the line comment prefix of the language,
+    /// unless all of them are already commented, in which case they are uncommented.
+    ///
+    /// The prefix is inserted at the smallest indentation among the lines, so that they stay aligned.
+    pub(crate) fn toggle_line_comment(
+        &self,
+        lines: Range<usize>,
+    ) -> anyhow::Result<EditTransaction> {
+        let Some(prefix) = self
+            .language
+            .as_ref()
+            .and_then(|language| language.line_comment_prefix())
+        else {
+            return Err(anyhow::anyhow!(
+                "Unable to toggle comment because the language has no line comment."
+            ));
+        };
+        // The indentation is counted in chars, because the whitespace
+        // of different lines can have different lengths in bytes
+        let lines = lines
+            .filter_map(|line_index| {
+                let line = self.rope.get_line(line_index)?.to_string();
+                let content = line.trim_start();
+                let indentation = line.chars().count() - content.chars().count();
+                (!content.trim_end().is_empty())
+                    .then(|| (line_index, content.to_string(), indentation))
+            })
+            .collect_vec();
+        let uncomment = lines
+            .iter()
+            .all(|(_, content, _)| content.starts_with(prefix));
+        let min_indentation = lines
+            .iter()
+            .map(|(_, _, indentation)| *indentation)
+            .min()
+            .unwrap_or_default();
+        Ok(EditTransaction::from_action_groups(
+            lines
+                .iter()
+                .map(
+                    |(line_index, content, indentation)| -> anyhow::Result<ActionGroup> {
+                        let line_start = self.line_to_char(*line_index)?;
+                        let (range, new) = if uncomment {
+                            let start = line_start + *indentation;
+                            let space = content[prefix.len()..].starts_with(' ');
+                            let end = start + prefix.chars().count() + usize::from(space);
+                            (start..end, "".to_string())
+                        } else {
+                            let start = line_start + min_indentation;
+                            (start..start, format!("{prefix} "))
+                        };
+                        Ok(ActionGroup::new(
+                            [Action::Edit(Edit::new(
+                                &self.rope,
+                                range.into(),
+                                new.into(),
+                            ))]
+                            .to_vec(),
+                        ))
+                    },
+                )
+                .try_collect()?,
+        ))
+    }
+
+    /// Replaces `range` with its case-transformed text, and selects the result.
+    ///
+    /// The new range is computed from the transformed text,
+    /// because its length might differ from the original, e.g. `ß` becomes `SS`.
+    #[allow(dead_code)]
+    pub(crate) fn transform_case(
+        &self,
+        range: CharIndexRange,
+        transform: CaseTransform,
+    ) -> anyhow::Result<EditTransaction> {
+        let new = transform.apply(&self.slice(&range)?.to_string());
+        let new_range: CharIndexRange = (range.start..range.start + new.chars().count()).into();
+        Ok(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit::new(&self.rope, range, new.as_str().into())),
+                    Action::Select(Selection::new(new_range)),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        ))
+    }
+
+    /// Replaces the given lines with the sorted lines, and selects them.
+    ///
+    /// Whether the last of the lines ends with a newline is preserved.
+    pub(crate) fn sort_lines(
+        &self,
+        lines: Range<usize>,
+        options: SortOptions,
+    ) -> anyhow::Result<EditTransaction> {
+        let line_count = self.rope.len_lines();
+        let range: CharIndexRange = (self.line_to_char(lines.start.min(line_count))?
+            ..self.line_to_char(lines.end.min(line_count))?)
+            .into();
+        let text = self.slice(&range)?.to_string();
+        let key = |line: &str| {
+            let text = if options.case_insensitive {
+                line.to_lowercase()
+            } else {
+                line.to_string()
+            };
+            let number = options
+                .numeric
+                .then(|| {
+                    lazy_regex::regex!(r"^\s*[-+]?\d+")
+                        .find(line)
+                        .and_then(|found| found.as_str().trim().parse::<i128>().ok())
+                })
+                .flatten();
+            // Lines with a number are ordered before the lines without one,
+            // even if the order is reversed
+            (number.is_none(), (number, text))
+        };
+        let sorted = text
+            .lines()
+            .map(|line| (key(line), line))
+            .sorted_by(|((a_group, a), _), ((b_group, b), _)| {
+                a_group
+                    .cmp(b_group)
+                    .then_with(|| if options.reverse { b.cmp(a) } else { a.cmp(b) })
+            })
+            .dedup_by(|(a, _), (b, _)| options.remove_duplicates && a == b)
+            .map(|(_, line)| line)
+            .join("\n");
+        let new = if text.ends_with('\n') {
+            format!("{sorted}\n")
+        } else {
+            sorted
+        };
+        let new_range: CharIndexRange = (range.start..range.start + new.chars().count()).into();
+        Ok(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit::new(&self.rope, range, new.as_str().into())),
+                    Action::Select(Selection::new(new_range)),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        ))
+    }
+
+    /// Joins the given lines into one line, and selects it.
+    ///
+    /// The whitespace around each line break is collapsed into one space,
+    /// or removed entirely before a closing punctuation such as `)` or `,`.
+    /// A range of a single line is joined with its following line.
+    #[allow(dead_code)]
+    pub(crate) fn join_lines(&self, lines: Range<usize>) -> anyhow::Result<EditTransaction> {
+        let last_line = lines
+            .end
+            .saturating_sub(1)
+            .max(lines.start + 1)
+            .min(self.len_lines().saturating_sub(1));
+        if last_line <= lines.start {
+            return Ok(EditTransaction::from_action_groups(Vec::new()));
+        }
+        let range: CharIndexRange = (self.line_to_char(lines.start)?
+            ..self.line_to_char(last_line)? + self.line_len_chars(last_line))
+            .into();
+        let text = self.slice(&range)?.to_string();
+        let mut text_lines = text.lines();
+        let first = text_lines.next().unwrap_or_default().trim_end().to_string();
+        let new = text_lines.fold(first, |joined, line| {
+            let line = line.trim();
+            let separator = if joined.trim().is_empty()
+                || line.is_empty()
+                || line.starts_with([')', ']', '}', ',', '.', ';', ':'])
+            {
+                ""
+            } else {
+                " "
+            };
+            format!("{joined}{separator}{line}")
+        });
+        let new_range: CharIndexRange = (range.start..range.start + new.chars().count()).into();
+        Ok(EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [
+                    Action::Edit(Edit::new(&self.rope, range, new.as_str().into())),
+                    Action::Select(Selection::new(new_range)),
+                ]
+                .to_vec(),
+            )]
+            .to_vec(),
+        ))
+    }
+
+    /// Returns the ranges of every match of `search`, ordered by their start.
+    ///
+    /// Matches are found on the UTF-8 content, so they always start and end at a character boundary.
+    #[allow(dead_code)]
+    pub(crate) fn search(&self, search: &Search) -> anyhow::Result<Vec<CharIndexRange>> {
+        let content = self.rope.to_string();
+        let byte_ranges: Vec<Range<usize>> = match search.mode {
+            LocalSearchConfigMode::Regex(regex_config) => regex_config
+                .to_regex(&search.search)?
+                .find_iter(&content)
+                .map_ok(|found| found.range())
+                .try_collect()?,
+            LocalSearchConfigMode::NamingConventionAgnostic => {
+                NamingConventionAgnostic::new(search.search.clone())
+                    .find_all(&content)
+                    .into_iter()
+                    .map(|(byte_range, _)| byte_range.range)
+                    .collect_vec()
+            }
+            LocalSearchConfigMode::AstGrep => AstGrep::new(self, &search.search)?
+                .find_all()
+                .map(|node| node.range())
+                .collect_vec(),
+        };
+        byte_ranges
+            .into_iter()
+            .sorted_by_key(|range| (range.start, range.end))
+            .map(|range| self.byte_range_to_char_index_range(&range))
+            .collect()
+    }
+
+    /// Returns the start of the first occurrence of `text` after `from`,
+    /// or the first occurrence in the buffer if `wrap` is true and there is none after `from`.
+    #[allow(dead_code)]
+    pub(crate) fn find_next_occurrence(
+        &self,
+        text: &str,
+        from: CharIndex,
+        wrap: bool,
+    ) -> anyhow::Result<Option<CharIndex>> {
+        let starts = self.occurrences(text)?;
+        Ok(starts
+            .iter()
+            .find(|start| **start > from)
+            .or_else(|| starts.first().filter(|_| wrap))
+            .copied())
+    }
+
+    /// Returns the start of the last occurrence of `text` before `from`,
+    /// or the last occurrence in the buffer if `wrap` is true and there is none before `from`.
+    #[allow(dead_code)]
+    pub(crate) fn find_previous_occurrence(
+        &self,
+        text: &str,
+        from: CharIndex,
+        wrap: bool,
+    ) -> anyhow::Result<Option<CharIndex>> {
+        let starts = self.occurrences(text)?;
+        Ok(starts
+            .iter()
+            .rfind(|start| **start < from)
+            .or_else(|| starts.last().filter(|_| wrap))
+            .copied())
+    }
+
+    /// The starts of the case-sensitive literal matches of `text`.
+    fn occurrences(&self, text: &str) -> anyhow::Result<Vec<CharIndex>> {
+        Ok(self
+            .search(&Search {
+                mode: LocalSearchConfigMode::Regex(RegexConfig {
+                    escaped: true,
+                    case_sensitive: true,
+                    match_whole_word: false,
+                }),
+                search: text.to_string(),
+            })?
+            .into_iter()
+            .map(|range| range.start)
+            .collect())
+    }
+
+    /// Returns a single transaction that replaces every non-overlapping match of `config`,
+    /// so that the replacement is undone in one step once it is applied.
+    ///
+    /// For regexes, the replacement can refer to capture groups, such as `$1`.
+    pub(crate) fn replace_all(
+        &self,
+        config: &LocalSearchConfig,
+    ) -> anyhow::Result<EditTransaction> {
+        let before = self.rope.to_string();
+        Ok(match config.mode {
             LocalSearchConfigMode::NamingConventionAgnostic => {
                 let replaced = NamingConventionAgnostic::new(config.search())
                     .replace_all(&before, config.replacement());
@@ -957,17 +3042,7 @@ impl Buffer {
                         .try_collect()?,
                 )
             }
-        };
-        let selection_set = self.apply_edit_transaction(
-            &edit_transaction,
-            current_selection_set,
-            true,
-            true,
-            last_visible_line,
-        )?;
-        let after = self.content();
-        let modified = before != after;
-        Ok((modified, selection_set))
+        })
     }
 
     pub(crate) fn char_index_range_to_byte_range(
@@ -1037,10 +3112,12 @@ impl Buffer {
         Ok(start..end)
     }
 
+    /// Returns the restored selection set, and the ranges changed by the redo.
     pub(crate) fn redo(
         &mut self,
         last_visible_line: u16,
-    ) -> Result<Option<SelectionSet>, anyhow::Error> {
+    ) -> Result<Option<(SelectionSet, Vec<CharIndexRange>)>, anyhow::Error> {
+        self.ensure_writable()?;
         if let Some(history) = self.redo_stack.pop() {
             history
                 .edit_transaction
@@ -1049,17 +3126,20 @@ impl Buffer {
                 .try_fold((), |_, edit| self.apply_edit(edit, last_visible_line))?;
             self.reparse_tree()?;
             let selection_set = history.old_state.selection_set.clone();
-            self.undo_stack.push(history.inverse());
-            Ok(Some(selection_set))
+            let edited_ranges = history.edit_transaction.edited_ranges();
+            self.push_undo_history(history.inverse());
+            Ok(Some((selection_set, edited_ranges)))
         } else {
             Ok(None)
         }
     }
 
+    /// Returns the restored selection set, and the ranges changed by the undo.
     pub(crate) fn undo(
         &mut self,
         last_visible_line: u16,
-    ) -> Result<Option<SelectionSet>, anyhow::Error> {
+    ) -> Result<Option<(SelectionSet, Vec<CharIndexRange>)>, anyhow::Error> {
+        self.ensure_writable()?;
         if let Some(history) = self.undo_stack.pop() {
             history
                 .edit_transaction
@@ -1068,8 +3148,9 @@ impl Buffer {
                 .try_fold((), |_, edit| self.apply_edit(edit, last_visible_line))?;
             self.reparse_tree()?;
             let selection_set = history.old_state.selection_set.clone();
+            let edited_ranges = history.edit_transaction.edited_ranges();
             self.redo_stack.push(history.inverse());
-            Ok(Some(selection_set))
+            Ok(Some((selection_set, edited_ranges)))
         } else {
             Ok(None)
         }
@@ -1088,6 +3169,43 @@ impl Buffer {
     }
 }
 
+/// Splits `word` at underscores and case changes, for example
+/// `snake_case` becomes `snake` and `case`, and `HTTPServer` becomes `HTTP` and `Server`.
+fn subword_byte_ranges(word: &str) -> Vec<Range<usize>> {
+    let chars = word.char_indices().collect_vec();
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (index, (byte_index, char)) in chars.iter().enumerate() {
+        if *char == '_' {
+            if let Some(start) = start.take() {
+                ranges.push(start..*byte_index)
+            }
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|index| chars[index].1);
+        let next = chars.get(index + 1).map(|(_, char)| *char);
+        let is_case_change = match (previous, next) {
+            (Some(previous), _) if !previous.is_uppercase() && char.is_uppercase() => true,
+            (Some(previous), Some(next)) => {
+                previous.is_uppercase() && char.is_uppercase() && next.is_lowercase()
+            }
+            _ => false,
+        };
+        if is_case_change {
+            if let Some(start) = start.take() {
+                ranges.push(start..*byte_index)
+            }
+        }
+        if start.is_none() {
+            start = Some(*byte_index)
+        }
+    }
+    if let Some(start) = start {
+        ranges.push(start..word.len())
+    }
+    ranges
+}
+
 #[cfg(test)]
 mod test_buffer {
     use std::fs::File;
@@ -1104,6 +3222,18 @@ mod test_buffer {
 
     use super::Buffer;
 
+    /// A Rust buffer, which is parsed and knows its language.
+    fn rust_buffer(content: &str) -> Buffer {
+        let mut buffer = Buffer::new(
+            shared::language::from_extension("rs")
+                .unwrap()
+                .tree_sitter_language(),
+            content,
+        );
+        buffer.language = shared::language::from_extension("rs");
+        buffer
+    }
+
     #[test]
     fn get_parent_lines_1() {
         let buffer = Buffer::new(
@@ -1138,10 +3268,7 @@ mod test_buffer {
 
     #[test]
     fn get_parent_lines_2() {
-        let buffer = Buffer::new(
-            shared::language::from_extension("rs")
-                .unwrap()
-                .tree_sitter_language(),
+        let buffer = rust_buffer(
             "
 fn f(
   x: X
@@ -1170,21 +3297,19 @@ fn f(
     mod replace {
 
         use crate::{
+            char_index_range::CharIndexRange,
             context::{
                 LocalSearchConfig,
                 LocalSearchConfigMode::{AstGrep, Regex},
+                Search,
             },
             list::grep::RegexConfig,
+            selection::CharIndex,
         };
 
         use super::*;
         fn test(input: &str, config: LocalSearchConfig, expected: &str) -> anyhow::Result<()> {
-            let mut buffer = Buffer::new(
-                shared::language::from_extension("rs")
-                    .unwrap()
-                    .tree_sitter_language(),
-                input,
-            );
+            let mut buffer = rust_buffer(input);
             buffer.replace(config, SelectionSet::default(), 0)?;
             assert_eq!(buffer.content(), expected);
             Ok(())
@@ -1222,6 +3347,99 @@ fn f(
             )
         }
 
+        #[test]
+        fn search_returns_char_ranges_of_multi_byte_content() -> anyhow::Result<()> {
+            let buffer = Buffer::new(None, "ünï cödé ünï");
+            let search = |mode, search: &str| {
+                buffer.search(&Search {
+                    mode,
+                    search: search.to_string(),
+                })
+            };
+            assert_eq!(
+                search(Regex(RegexConfig::default()), "ÜNÏ")?,
+                [
+                    CharIndexRange::from(CharIndex(0)..CharIndex(3)),
+                    CharIndexRange::from(CharIndex(9)..CharIndex(12))
+                ]
+            );
+            assert_eq!(
+                search(
+                    Regex(RegexConfig {
+                        escaped: false,
+                        ..Default::default()
+                    }),
+                    r"c\w+"
+                )?,
+                [CharIndexRange::from(CharIndex(4)..CharIndex(8))]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn structural_search_with_metavariables() -> anyhow::Result<()> {
+            let buffer = rust_buffer("fn main() { foo(1); bar.baz(2); }");
+            let slices = |pattern: &str| -> anyhow::Result<Vec<String>> {
+                buffer
+                    .structural_search(pattern)?
+                    .into_iter()
+                    .map(|range| Ok(buffer.slice(&range)?.to_string()))
+                    .collect()
+            };
+            assert_eq!(
+                slices("(call_expression function: (identifier) $F)")?,
+                ["foo"]
+            );
+            assert_eq!(
+                slices("(call_expression function: (field_expression))")?,
+                ["bar.baz(2)"]
+            );
+            let error = buffer
+                .structural_search("(call_expression (oops))")
+                .unwrap_err();
+            assert!(error
+                .to_string()
+                .starts_with("Invalid pattern at row 1 column 19"));
+            Ok(())
+        }
+
+        #[test]
+        fn find_next_and_previous_occurrence() -> anyhow::Result<()> {
+            let buffer = Buffer::new(None, "ab é ab É ab");
+            let next = |from, wrap| buffer.find_next_occurrence("ab", CharIndex(from), wrap);
+            let previous =
+                |from, wrap| buffer.find_previous_occurrence("ab", CharIndex(from), wrap);
+            assert_eq!(next(0, false)?, Some(CharIndex(5)));
+            assert_eq!(next(10, false)?, None);
+            assert_eq!(next(10, true)?, Some(CharIndex(0)));
+            assert_eq!(previous(5, false)?, Some(CharIndex(0)));
+            assert_eq!(previous(0, false)?, None);
+            assert_eq!(previous(0, true)?, Some(CharIndex(10)));
+            // Occurrences are case-sensitive
+            assert_eq!(
+                buffer.find_next_occurrence("É", CharIndex(0), true)?,
+                Some(CharIndex(8))
+            );
+            assert_eq!(buffer.find_next_occurrence("x", CharIndex(0), true)?, None);
+            Ok(())
+        }
+
+        #[test]
+        fn replace_all_skips_overlapping_matches() -> anyhow::Result<()> {
+            let buffer = Buffer::new(None, "aaaaa");
+            let transaction = buffer.replace_all(
+                LocalSearchConfig::new(Regex(RegexConfig::default()))
+                    .set_search("aa".to_string())
+                    .set_replacment("b".to_string()),
+            )?;
+            assert_eq!(buffer.content(), "aaaaa");
+
+            let mut buffer = buffer;
+            buffer.apply_edit_transaction(&transaction, SelectionSet::default(), true, true, 0)?;
+            assert_eq!(buffer.content(), "bba");
+            Ok(())
+        }
+
         #[test]
         fn ast_group_1() -> anyhow::Result<()> {
             test(
@@ -1245,7 +3463,7 @@ fn f(
         let path = CanonicalizedPath::try_from(file_path).unwrap();
         path.write("").unwrap();
 
-        let buffer = Buffer::from_path(&path, true).unwrap();
+        let buffer = Buffer::from_path(&path, true, None).unwrap();
 
         f(path, buffer)
     }
@@ -1344,6 +3562,1613 @@ fn f(
         }
     }
 
+    #[test]
+    fn save_clears_dirty_flag() {
+        run_test(|path, mut buffer| {
+            assert!(!buffer.dirty());
+
+            buffer.update("fn main() {}\n");
+            assert!(buffer.dirty());
+
+            let saved_path = buffer.save_without_formatting(false).unwrap();
+            assert_eq!(saved_path, Some(path.clone()));
+            assert_eq!(path.read().unwrap(), "fn main() {}\n");
+            assert!(!buffer.dirty());
+        })
+    }
+
+    #[test]
+    fn save_as_updates_path_and_language() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let mut buffer = Buffer::new(None, "fn main() {}\n");
+        buffer.update("fn main() { }\n");
+        assert!(buffer.dirty());
+        assert!(buffer.tree().is_none());
+
+        let path = buffer.save_as(&dir.path().join("main.rs"))?;
+        assert_eq!(path.read()?, "fn main() { }\n");
+        assert_eq!(buffer.path(), Some(path));
+        assert!(!buffer.dirty());
+        assert_eq!(buffer.language(), shared::language::from_extension("rs"));
+        assert_eq!(buffer.tree().unwrap().root_node().kind(), "source_file");
+        Ok(())
+    }
+
+    #[test]
+    fn scratch_buffer_is_untitled_until_saved_as() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let mut buffer = Buffer::scratch(shared::language::from_extension("rs").unwrap());
+        assert!(buffer.is_untitled());
+        assert!(!buffer.dirty());
+        assert_eq!(buffer.tree().unwrap().root_node().kind(), "source_file");
+
+        let edit_transaction = buffer.get_edit_transaction("fn main() {}")?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+        assert!(buffer.dirty());
+        assert!(buffer.save_without_formatting(false).is_err());
+
+        let path = buffer.save_as(&dir.path().join("main.rs"))?;
+        assert!(!buffer.is_untitled());
+        assert!(!buffer.dirty());
+        assert_eq!(path.read()?, "fn main() {}");
+        Ok(())
+    }
+
+    #[test]
+    fn save_as_unsupported_extension_should_fail() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let mut buffer = Buffer::new(None, "hello");
+        let path = dir.path().join("hello.unsupported-extension");
+        assert_eq!(
+            buffer.save_as(&path).unwrap_err().to_string(),
+            "Unsupported file extension: \"unsupported-extension\""
+        );
+        assert!(!path.exists());
+        assert_eq!(buffer.path(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn latin_1_file_is_decoded_and_encoded_back_on_save() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("log.txt");
+        std::fs::write(&file_path, b"caf\xe9\n")?;
+        let path = CanonicalizedPath::try_from(file_path.clone())?;
+
+        let mut buffer = Buffer::from_path(&path, false, None)?;
+        assert_eq!(buffer.content(), "café\n");
+        assert_eq!(buffer.encoding(), encoding_rs::WINDOWS_1252);
+
+        buffer.update("café crème\n");
+        buffer.save_without_formatting(false)?;
+        assert_eq!(std::fs::read(&file_path)?, b"caf\xe9 cr\xe8me\n");
+
+        // Characters that do not exist in the original encoding cannot be saved
+        buffer.update("日本\n");
+        assert!(buffer.save_without_formatting(false).is_err());
+        assert_eq!(std::fs::read(&file_path)?, b"caf\xe9 cr\xe8me\n");
+        Ok(())
+    }
+
+    #[test]
+    fn utf_16_file_is_decoded_and_encoded_back_on_save() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("notes.txt");
+        let utf_16 = |text: &str| {
+            [0xFF, 0xFE]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect_vec()
+        };
+        std::fs::write(&file_path, utf_16("hello\n"))?;
+        let path = CanonicalizedPath::try_from(file_path.clone())?;
+
+        let mut buffer = Buffer::from_path(&path, false, None)?;
+        assert_eq!(buffer.content(), "hello\n");
+        assert_eq!(buffer.encoding(), encoding_rs::UTF_16LE);
+
+        buffer.update("hello wörld\n");
+        buffer.save_without_formatting(false)?;
+        assert_eq!(std::fs::read(&file_path)?, utf_16("hello wörld\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn utf_8_bom_is_excluded_from_content_and_restored_on_save() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("main.rs");
+        std::fs::write(&file_path, "\u{FEFF}fn main() {}\n")?;
+        let path = CanonicalizedPath::try_from(file_path.clone())?;
+
+        let mut buffer = Buffer::from_path(&path, true, None)?;
+        assert_eq!(buffer.content(), "fn main() {}\n");
+        assert_eq!(buffer.encoding(), encoding_rs::UTF_8);
+        let first_node = buffer.tree().unwrap().root_node().child(0).unwrap();
+        assert_eq!(first_node.kind(), "function_item");
+        assert_eq!(first_node.start_byte(), 0);
+
+        buffer.update("fn main() { }\n");
+        buffer.save_without_formatting(false)?;
+        assert_eq!(
+            std::fs::read_to_string(&file_path)?,
+            "\u{FEFF}fn main() { }\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn preserve_crlf_line_endings_on_save() {
+        run_test(|path, _| {
+            path.write("fn main() {\r\n}\r\n").unwrap();
+            let mut buffer = Buffer::from_path(&path, false, None).unwrap();
+
+            // Content is normalized to `\n` internally
+            assert_eq!(buffer.content(), "fn main() {\n}\n");
+            assert_eq!(buffer.line_ending(), super::LineEnding::Crlf);
+            assert!(!buffer.has_mixed_line_endings());
+
+            buffer.save_without_formatting(true).unwrap();
+            assert_eq!(path.read().unwrap(), "fn main() {\r\n}\r\n");
+
+            buffer.set_line_ending(super::LineEnding::Lf);
+            assert!(buffer.dirty());
+            buffer.save_without_formatting(false).unwrap();
+            assert_eq!(path.read().unwrap(), "fn main() {\n}\n");
+        })
+    }
+
+    #[test]
+    fn mixed_line_endings_should_use_the_majority() {
+        let buffer = Buffer::new(None, "a\r\nb\r\nc\nd");
+        assert_eq!(buffer.line_ending(), super::LineEnding::Crlf);
+        assert!(buffer.has_mixed_line_endings());
+        assert_eq!(buffer.content(), "a\nb\nc\nd");
+
+        let buffer = Buffer::new(None, "a\nb");
+        assert_eq!(buffer.line_ending(), super::LineEnding::Lf);
+        assert!(!buffer.has_mixed_line_endings());
+    }
+
+    #[test]
+    fn undo_history_is_capped_by_max_undo_depth() {
+        let mut buffer = Buffer::new(None, "");
+        buffer.set_max_undo_depth(3);
+        let edit = |buffer: &mut Buffer, index: usize| {
+            let edit_transaction = buffer
+                .get_edit_transaction(&format!("{}{index}", buffer.content()))
+                .unwrap();
+            buffer
+                .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+                .unwrap();
+        };
+        for index in 0..8 {
+            edit(&mut buffer, index)
+        }
+        assert_eq!(buffer.content(), "01234567");
+
+        // Only the latest 3 edits can be undone
+        assert!(buffer.undo(0).unwrap().is_some());
+        assert!(buffer.undo(0).unwrap().is_some());
+        assert!(buffer.undo(0).unwrap().is_some());
+        assert!(buffer.undo(0).unwrap().is_none());
+        assert_eq!(buffer.content(), "01234");
+
+        // Redo still works after the older edits are evicted
+        assert!(buffer.redo(0).unwrap().is_some());
+        assert_eq!(buffer.content(), "012345");
+        assert!(buffer.undo(0).unwrap().is_some());
+        assert_eq!(buffer.content(), "01234");
+    }
+
+    #[test]
+    fn undo_and_redo_return_edited_ranges() -> anyhow::Result<()> {
+        use crate::{char_index_range::CharIndexRange, selection::CharIndex};
+        let mut buffer = Buffer::new(None, "foo\nbar\nbaz\n");
+        let edit_transaction = buffer.get_edit_transaction("foo\nquux\nbaz\n")?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+
+        let (_, edited_ranges) = buffer.undo(0)?.unwrap();
+        assert_eq!(buffer.content(), "foo\nbar\nbaz\n");
+        assert_eq!(
+            edited_ranges,
+            vec![CharIndexRange::from(CharIndex(4)..CharIndex(8))]
+        );
+
+        let (_, edited_ranges) = buffer.redo(0)?.unwrap();
+        assert_eq!(buffer.content(), "foo\nquux\nbaz\n");
+        assert_eq!(
+            edited_ranges,
+            vec![CharIndexRange::from(CharIndex(4)..CharIndex(9))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sexp_of_smallest_named_node_covering_range() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let buffer = rust_buffer("fn main() { 1 + 2; }");
+        assert_eq!(
+            buffer.sexp(Some((CharIndex(12)..CharIndex(17)).into()))?,
+            "(binary_expression left: (integer_literal) right: (integer_literal))"
+        );
+        assert!(buffer
+            .sexp(None)?
+            .starts_with("(source_file (function_item name: (identifier)"));
+        assert!(Buffer::new(None, "1 + 2").sexp(None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn node_text_and_selection_of_multi_byte_content() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let buffer = rust_buffer("fn f() { \"日本\"; }");
+        let node = buffer
+            .tree
+            .as_ref()
+            .unwrap()
+            .root_node()
+            .named_descendant_for_byte_range(9, 9)
+            .unwrap();
+        assert_eq!(node.kind(), "string_literal");
+        assert_eq!(buffer.node_text(node)?, "\"日本\"");
+        assert_eq!(
+            buffer.select_node(node)?.range(),
+            crate::char_index_range::CharIndexRange::from(CharIndex(9)..CharIndex(13))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn matching_bracket() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let content = "fn f() { g(\"(\", [1]); }";
+        let buffer = rust_buffer(content);
+        let matching = |index: usize| -> anyhow::Result<Option<char>> {
+            Ok(buffer
+                .matching_bracket(CharIndex(index))?
+                .and_then(|index| content.chars().nth(index.0)))
+        };
+        let position = |pattern: &str| content.find(pattern).unwrap();
+        // Nested brackets
+        assert_eq!(
+            buffer.matching_bracket(CharIndex(position("{")))?,
+            Some(CharIndex(content.len() - 1))
+        );
+        assert_eq!(
+            buffer.matching_bracket(CharIndex(position("[")))?,
+            Some(CharIndex(position("]")))
+        );
+        assert_eq!(
+            buffer.matching_bracket(CharIndex(position("(\"")))?,
+            Some(CharIndex(position(");")))
+        );
+        assert_eq!(matching(position(");"))?, Some('('));
+        // Brackets within strings are not matched
+        assert_eq!(buffer.matching_bracket(CharIndex(position("(\",")))?, None);
+        // Not on a bracket
+        assert_eq!(buffer.matching_bracket(CharIndex(0))?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn reindent() -> anyhow::Result<()> {
+        use super::IndentStyle;
+        let mut buffer =
+            rust_buffer("fn f() {\nlet x = (\n1,\n  );\n\n        let s = \"a\n b\";\n}");
+        let transaction = buffer.reindent(0..buffer.len_lines(), IndentStyle::Spaces(4))?;
+        buffer.apply_edit_transaction(&transaction, SelectionSet::default(), true, true, 0)?;
+        assert_eq!(
+            buffer.content(),
+            "fn f() {\n    let x = (\n        1,\n    );\n\n    let s = \"a\n b\";\n}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn indent_for_new_line() -> anyhow::Result<()> {
+        use super::IndentStyle;
+        use crate::selection::CharIndex;
+        let buffer = rust_buffer("fn foo() {\n    bar(\n        1,\n    );\n    let x = \"{\";}");
+        let indent = |text_before_cursor: &str, indent_style| {
+            let cursor =
+                buffer.content().find(text_before_cursor).unwrap() + text_before_cursor.len();
+            buffer.indent_for_new_line(CharIndex(cursor), indent_style)
+        };
+        let spaces = IndentStyle::Spaces(4);
+        // After an opening bracket
+        assert_eq!(indent("fn foo() {", spaces)?, "    ");
+        assert_eq!(indent("fn foo() {", IndentStyle::Tabs)?, "\t");
+        assert_eq!(indent("bar(", spaces)?, "        ");
+        // Within a multi-line function call
+        assert_eq!(indent("1,", spaces)?, "        ");
+        // Before a closing bracket
+        assert_eq!(indent("    let x = \"{\";", spaces)?, "");
+        // Brackets within strings are ignored
+        assert_eq!(indent("    let x = \"{", spaces)?, "    ");
+        Ok(())
+    }
+
+    #[test]
+    fn auto_pair_edit() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let type_char = |content: &str, cursor: usize, typed: char| -> anyhow::Result<_> {
+            let mut buffer = rust_buffer(content);
+            let Some(transaction) = buffer.auto_pair_edit(CharIndex(cursor), typed)? else {
+                return Ok(None);
+            };
+            let selection_set = buffer.apply_edit_transaction(
+                &transaction,
+                SelectionSet::default(),
+                true,
+                true,
+                0,
+            )?;
+            Ok(Some((
+                buffer.content(),
+                selection_set.primary_selection().range().start.0,
+            )))
+        };
+        assert_eq!(
+            type_char("fn f() { g }", 10, '(')?,
+            Some(("fn f() { g() }".to_string(), 11))
+        );
+        assert_eq!(
+            type_char("fn f() { g() }", 11, ')')?,
+            Some(("fn f() { g() }".to_string(), 12))
+        );
+        // Not paired before a word
+        assert_eq!(type_char("fn f() { x }", 9, '(')?, None);
+        // Not paired within strings
+        assert_eq!(type_char("fn f() { \"a  b\" }", 11, '(')?, None);
+        // Single quotes are not paired in Rust
+        assert_eq!(type_char("fn f() {  }", 9, '\'')?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn toggle_line_comment() -> anyhow::Result<()> {
+        let toggle = |content: &str, lines: std::ops::Range<usize>| -> anyhow::Result<String> {
+            let mut buffer = rust_buffer(content);
+            let transaction = buffer.toggle_line_comment(lines)?;
+            buffer.apply_edit_transaction(&transaction, SelectionSet::default(), true, true, 0)?;
+            Ok(buffer.content())
+        };
+        // Blank lines are skipped, and the prefix is aligned to the smallest indentation
+        assert_eq!(
+            toggle("fn f() {\n    let x = 1;\n\n        x\n}", 1..4)?,
+            "fn f() {\n    // let x = 1;\n\n    //     x\n}"
+        );
+        // Mixed lines are all commented
+        assert_eq!(toggle("// a\nb", 0..2)?, "// // a\n// b");
+        // Lines that are all commented are uncommented
+        assert_eq!(toggle("  // a\n  //b\n", 0..2)?, "  a\n  b\n");
+        // Indentations are counted in chars, regardless of their lengths in bytes
+        assert_eq!(toggle("\u{3000}a\n b", 0..2)?, "\u{3000}// a\n // b");
+        assert_eq!(toggle("\u{3000}// a\n // b", 0..2)?, "\u{3000}a\n b");
+        assert!(Buffer::new(None, "a").toggle_line_comment(0..1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cloned_buffer_reparses_independently() {
+        let language = shared::language::from_extension("rs")
+            .unwrap()
+            .tree_sitter_language();
+        let mut buffer = Buffer::new(language.clone(), "fn main() {}");
+        let original = buffer.clone();
+
+        for content in ["fn main() { let x = 1; }", "struct A;", "fn f(x: u8) {}"] {
+            buffer.update(content);
+            buffer.reparse_tree().unwrap();
+            let expected = Buffer::new(language.clone(), content);
+            assert_eq!(
+                buffer.tree.as_ref().unwrap().root_node().to_sexp(),
+                expected.tree.as_ref().unwrap().root_node().to_sexp()
+            );
+        }
+
+        // The original buffer is unaffected by the edits on its clone
+        assert_eq!(
+            original.tree.as_ref().unwrap().root_node().to_sexp(),
+            Buffer::new(language, "fn main() {}")
+                .tree
+                .unwrap()
+                .root_node()
+                .to_sexp()
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn reparse_durations_are_measured() -> anyhow::Result<()> {
+        let mut buffer = rust_buffer("fn main() {}");
+        assert_eq!(buffer.last_parse_duration(), None);
+        assert_eq!(buffer.average_parse_duration(), None);
+
+        let edit_transaction = buffer.get_edit_transaction("fn main() { }")?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+        assert!(buffer.last_parse_duration().is_some());
+        assert_eq!(
+            buffer.average_parse_duration(),
+            buffer.last_parse_duration()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn traverse_range_yields_the_same_nodes_as_filtered_traverse() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        use tree_sitter_traversal2::Order;
+
+        let buffer = rust_buffer("fn a() { let x = (1, 2); }\nfn b() {}\nstruct C { d: u8 }\n");
+        for pre_order in [true, false] {
+            let order = || if pre_order { Order::Pre } else { Order::Post };
+            for (start, end) in [(0, 0), (0, 5), (9, 24), (27, 30), (30, 40), (0, 56)] {
+                let range = (CharIndex(start)..CharIndex(end)).into();
+                let actual = buffer
+                    .traverse_range(range, order())?
+                    .map(|node| node.id())
+                    .collect_vec();
+                let expected = buffer
+                    .traverse(order())
+                    .unwrap()
+                    .filter(|node| node.end_byte() > start && node.start_byte() < end)
+                    .map(|node| node.id())
+                    .collect_vec();
+                assert_eq!(
+                    actual, expected,
+                    "pre_order = {pre_order}, range = {start}..{end}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn nodes_of_kind() {
+        let buffer =
+            rust_buffer("fn a() { let x = \"x\"; }\nfn b() {}\nstruct C { d: &'static str }\n");
+        let texts = |kinds: &[&str]| {
+            buffer
+                .nodes_of_kinds(kinds)
+                .into_iter()
+                .map(|node| buffer.content()[node.byte_range()].to_string())
+                .collect_vec()
+        };
+        let functions = buffer
+            .nodes_of_kind("function_item")
+            .into_iter()
+            .map(|node| node.start_position().row)
+            .collect_vec();
+        assert_eq!(functions, [0, 1]);
+        assert_eq!(texts(&["string_literal"]), ["\"x\""]);
+        assert_eq!(texts(&["function_item", "struct_item"]).len(), 3);
+        assert!(buffer.nodes_of_kind("no_such_kind").is_empty());
+    }
+
+    #[test]
+    fn enclosing_definitions() {
+        use crate::selection::CharIndex;
+
+        let content = "mod m {\n    impl Foo {\n        fn bar() { 1 }\n    }\n}\nfn baz() {}\n";
+        let mut buffer = rust_buffer(content);
+        let kinds = |buffer: &Buffer, cursor: usize| {
+            buffer
+                .enclosing_definitions(CharIndex(cursor))
+                .into_iter()
+                .map(|node| node.kind())
+                .collect_vec()
+        };
+        assert_eq!(
+            kinds(&buffer, content.find('1').unwrap()),
+            ["mod_item", "impl_item", "function_item"]
+        );
+        assert_eq!(
+            kinds(&buffer, content.find("baz").unwrap()),
+            ["function_item"]
+        );
+        assert!(kinds(&buffer, content.len() - 1).is_empty());
+
+        // Languages without definition kinds have no enclosing definitions
+        buffer.language = None;
+        assert!(kinds(&buffer, content.find('1').unwrap()).is_empty());
+    }
+
+    #[test]
+    fn breadcrumbs_are_the_names_of_the_enclosing_definitions() {
+        use crate::selection::CharIndex;
+
+        let content = "mod m {\n    impl Foo {\n        fn bar() { 1 }\n    }\n}\n";
+        let buffer = rust_buffer(content);
+        assert_eq!(
+            buffer.breadcrumbs(CharIndex(content.find('1').unwrap())),
+            ["m", "Foo", "bar"]
+        );
+    }
+
+    #[test]
+    fn reparse_timeout_keeps_previous_tree() -> anyhow::Result<()> {
+        use crate::{
+            edit::{Action, ActionGroup, Edit, EditTransaction},
+            selection::CharIndex,
+        };
+
+        let content = (0..2_000)
+            .map(|index| format!("fn f{index}() {{ let x = {index}; }}\n"))
+            .join("");
+        let mut buffer = rust_buffer(&content);
+        buffer.set_parse_timeout(1);
+
+        // Replacing everything requires a full reparse, which cannot finish within 1 microsecond
+        let edit_transaction = EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [Action::Edit(Edit::new(
+                    buffer.rope(),
+                    (CharIndex(0)..CharIndex(buffer.len_chars())).into(),
+                    content.replace("let x", "let y").as_str().into(),
+                ))]
+                .to_vec(),
+            )]
+            .to_vec(),
+        );
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+        assert!(buffer.is_tree_stale());
+        assert!(buffer.tree().is_some());
+
+        buffer.set_parse_timeout(0);
+        buffer.reparse_tree()?;
+        assert!(!buffer.is_tree_stale());
+        assert!(!buffer.tree().unwrap().root_node().has_error());
+        Ok(())
+    }
+
+    #[test]
+    fn update_timeout_keeps_previous_tree_until_reparsed() -> anyhow::Result<()> {
+        let content = |name: &str| {
+            (0..2_000)
+                .map(|index| format!("fn {name}{index}() {{ let x = {index}; }}\n"))
+                .join("")
+        };
+        let mut buffer = rust_buffer(&content("f"));
+        buffer.set_parse_timeout(1);
+
+        buffer.update(&content("renamed"));
+        assert!(buffer.is_tree_stale());
+        assert!(buffer.tree().is_some());
+
+        buffer.set_parse_timeout(0);
+        buffer.reparse_tree()?;
+        assert!(!buffer.is_tree_stale());
+        let root_node = buffer.tree().unwrap().root_node();
+        assert_eq!(root_node.end_byte(), content("renamed").len());
+        assert_eq!(
+            root_node.to_sexp(),
+            Buffer::new(buffer.treesitter_language(), &content("renamed"))
+                .tree()
+                .unwrap()
+                .root_node()
+                .to_sexp()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn incremental_reparse_matches_parse_from_scratch() {
+        use crate::edit::{Action, ActionGroup, Edit, EditTransaction};
+
+        let language = shared::language::from_extension("rs")
+            .unwrap()
+            .tree_sitter_language();
+        let content = (0..25_000)
+            .map(|index| format!("fn f{index}() {{ let x = \"ü{index}\"; }}\n"))
+            .join("");
+        assert!(content.len() > 1_000_000);
+
+        let mut buffer = Buffer::new(language.clone(), &content);
+        for index in 0..50 {
+            let line = index * 500;
+            let start = buffer.line_to_char(line).unwrap();
+            let edit_transaction = EditTransaction::from_action_groups(
+                [ActionGroup::new(
+                    [Action::Edit(Edit::new(
+                        buffer.rope(),
+                        (start..start + 3).into(),
+                        format!("struct S{index}; fn ").as_str().into(),
+                    ))]
+                    .to_vec(),
+                )]
+                .to_vec(),
+            );
+            buffer
+                .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+                .unwrap();
+        }
+
+        let expected = Buffer::new(language, &buffer.content());
+        assert_eq!(
+            buffer.tree.as_ref().unwrap().root_node().to_sexp(),
+            expected.tree.as_ref().unwrap().root_node().to_sexp()
+        );
+        assert!(!buffer.tree.as_ref().unwrap().root_node().has_error());
+    }
+
+    #[test]
+    fn reload_from_disk_remaps_selections() -> anyhow::Result<()> {
+        use crate::{
+            char_index_range::CharIndexRange,
+            selection::{CharIndex, Selection},
+        };
+        let dir = tempdir()?;
+        let file_path = dir.path().join("main.rs");
+        std::fs::write(&file_path, "hello\nworld\n")?;
+        let path = CanonicalizedPath::try_from(file_path)?;
+        let mut buffer = Buffer::from_path(&path, false, None)?;
+
+        // Select `world`
+        let selection_set = SelectionSet::default().set_selections(nonempty::NonEmpty::new(
+            Selection::new((CharIndex(6)..CharIndex(11)).into()),
+        ));
+        path.write("new line\nhello\nworld\n")?;
+        let selection_set = buffer.reload_from_disk(selection_set)?;
+        assert_eq!(buffer.content(), "new line\nhello\nworld\n");
+        assert!(!buffer.dirty());
+        assert_eq!(
+            selection_set.primary_selection().range(),
+            CharIndexRange::from(CharIndex(15)..CharIndex(20))
+        );
+
+        // The file is truncated, so the selection is clamped
+        path.write("hi")?;
+        let selection_set = buffer.reload_from_disk(selection_set)?;
+        assert_eq!(buffer.content(), "hi");
+        assert!(selection_set.primary_selection().range().end <= CharIndex(2));
+
+        // Unsaved changes are not discarded
+        buffer.update("unsaved");
+        assert!(buffer.reload_from_disk(selection_set).is_err());
+        assert_eq!(buffer.content(), "unsaved");
+        Ok(())
+    }
+
+    #[test]
+    fn get_current_node_after_edit_is_not_root() -> anyhow::Result<()> {
+        use crate::selection::{CharIndex, Selection};
+        let mut buffer = rust_buffer("fn main() { let x = 1; }");
+        let edit_transaction = buffer.get_edit_transaction("fn main() { let xyz = 1; }")?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+
+        // Select `xyz`
+        let selection = Selection::new((CharIndex(16)..CharIndex(19)).into());
+        let node = buffer.get_current_node(&selection, false)?.unwrap();
+        assert_ne!(node.id(), buffer.tree().unwrap().root_node().id());
+        assert_eq!(node.kind(), "identifier");
+        assert_eq!(buffer.node_text(node)?, "xyz");
+        Ok(())
+    }
+
+    #[test]
+    fn clamp_selection_set_clamps_extended_selections() -> anyhow::Result<()> {
+        use crate::{
+            char_index_range::CharIndexRange,
+            selection::{CharIndex, Selection},
+        };
+        let buffer = Buffer::new(None, "hello");
+        let selection_set = SelectionSet::default().set_selections(nonempty::NonEmpty {
+            head: Selection::new((CharIndex(2)..CharIndex(3)).into()),
+            tail: vec![Selection::new((CharIndex(4)..CharIndex(9)).into())
+                .set_initial_range(Some((CharIndex(7)..CharIndex(8)).into()))],
+        });
+        let selection_set = buffer.clamp_selection_set(selection_set)?;
+        let selections = selection_set.selections();
+        assert_eq!(
+            selections.head.range(),
+            CharIndexRange::from(CharIndex(2)..CharIndex(3))
+        );
+        assert_eq!(
+            selections.tail[0].extended_range(),
+            CharIndexRange::from(CharIndex(4)..CharIndex(5))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn join_lines() -> anyhow::Result<()> {
+        let join = |content: &str, lines: std::ops::Range<usize>| {
+            let mut buffer = Buffer::new(None, content);
+            let edit_transaction = buffer.join_lines(lines)?;
+            buffer.apply_edit_transaction(
+                &edit_transaction,
+                SelectionSet::default(),
+                true,
+                true,
+                0,
+            )?;
+            anyhow::Ok(buffer.content())
+        };
+        assert_eq!(
+            join("  foo(  \n    bar\n  )\nbaz\n", 0..3)?,
+            "  foo( bar)\nbaz\n"
+        );
+        // A single line is joined with the next line
+        assert_eq!(join("a\n  b\nc", 0..1)?, "a b\nc");
+        // The last line has no trailing newline
+        assert_eq!(join("a\nb\n\n  c", 1..4)?, "a\nb c");
+        // There is no next line to join with
+        assert_eq!(join("a\nb", 1..2)?, "a\nb");
+        Ok(())
+    }
+
+    #[test]
+    fn sort_lines_numeric_vs_lexical() -> anyhow::Result<()> {
+        use super::SortOptions;
+        let sort = |content: &str, lines: std::ops::Range<usize>, options: SortOptions| {
+            let mut buffer = Buffer::new(None, content);
+            let edit_transaction = buffer.sort_lines(lines, options)?;
+            buffer.apply_edit_transaction(
+                &edit_transaction,
+                SelectionSet::default(),
+                true,
+                true,
+                0,
+            )?;
+            anyhow::Ok(buffer.content())
+        };
+        let content = "10 apples\n9 pears\nbanana\n100 kiwis\n9 pears\n";
+
+        assert_eq!(
+            sort(content, 0..5, SortOptions::default())?,
+            "10 apples\n100 kiwis\n9 pears\n9 pears\nbanana\n"
+        );
+        assert_eq!(
+            sort(
+                content,
+                0..5,
+                SortOptions {
+                    numeric: true,
+                    ..Default::default()
+                }
+            )?,
+            "9 pears\n9 pears\n10 apples\n100 kiwis\nbanana\n"
+        );
+        assert_eq!(
+            sort(
+                content,
+                0..5,
+                SortOptions {
+                    numeric: true,
+                    reverse: true,
+                    remove_duplicates: true,
+                    ..Default::default()
+                }
+            )?,
+            "100 kiwis\n10 apples\n9 pears\nbanana\n"
+        );
+        assert_eq!(
+            sort(
+                "b\n2\na\n1",
+                0..4,
+                SortOptions {
+                    numeric: true,
+                    reverse: true,
+                    ..Default::default()
+                }
+            )?,
+            "2\n1\nb\na"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sort_lines_preserves_missing_final_newline() -> anyhow::Result<()> {
+        use super::SortOptions;
+        let mut buffer = Buffer::new(None, "head\nb\nA\na");
+        let edit_transaction = buffer.sort_lines(
+            1..3,
+            SortOptions {
+                case_insensitive: true,
+                remove_duplicates: true,
+                ..Default::default()
+            },
+        )?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+        assert_eq!(buffer.content(), "head\nA\nb\na");
+
+        let edit_transaction = buffer.sort_lines(1..10, SortOptions::default())?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+        assert_eq!(buffer.content(), "head\nA\na\nb");
+        Ok(())
+    }
+
+    #[test]
+    fn transform_case_with_length_change() -> anyhow::Result<()> {
+        use crate::{
+            char_index_range::CharIndexRange, selection::CharIndex, transformation::CaseTransform,
+        };
+        let mut buffer = Buffer::new(None, "die straße ist lang");
+        let edit_transaction =
+            buffer.transform_case((CharIndex(4)..CharIndex(10)).into(), CaseTransform::Upper)?;
+        let selection_set = buffer.apply_edit_transaction(
+            &edit_transaction,
+            SelectionSet::default(),
+            true,
+            true,
+            0,
+        )?;
+        assert_eq!(buffer.content(), "die STRASSE ist lang");
+        assert_eq!(
+            selection_set.primary_selection().range(),
+            CharIndexRange::from(CharIndex(4)..CharIndex(11))
+        );
+
+        assert!(buffer.undo(0)?.is_some());
+        assert_eq!(buffer.content(), "die straße ist lang");
+        Ok(())
+    }
+
+    #[test]
+    fn go_to_line_and_percent() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let content = (1..=10).map(|line| format!("line {line}\n")).join("");
+        let buffer = Buffer::new(None, &content);
+        assert_eq!(buffer.len_lines(), 10);
+        assert_eq!(buffer.clamp_line(0), CharIndex(0));
+        assert_eq!(buffer.clamp_line(3), buffer.line_to_char(3)?);
+        assert_eq!(buffer.clamp_line(1000), buffer.line_to_char(9)?);
+
+        assert_eq!(buffer.char_at_percent(0), CharIndex(0));
+        assert_eq!(buffer.char_at_percent(50), buffer.line_to_char(4)?);
+        assert_eq!(buffer.char_at_percent(55), buffer.line_to_char(5)?);
+        assert_eq!(buffer.char_at_percent(100), buffer.line_to_char(9)?);
+        assert_eq!(buffer.char_at_percent(255), buffer.line_to_char(9)?);
+
+        let buffer = Buffer::new(None, "");
+        assert_eq!(buffer.clamp_line(5), CharIndex(0));
+        assert_eq!(buffer.char_at_percent(50), CharIndex(0));
+        Ok(())
+    }
+
+    #[test]
+    fn paragraph_movement() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let buffer = Buffer::new(None, "a\nb\n\n\nc\n  \nd");
+        // Lines: 0 "a", 1 "b", 2 "", 3 "", 4 "c", 5 "  ", 6 "d"
+        assert_eq!(
+            buffer.next_paragraph(CharIndex(0))?,
+            buffer.line_to_char(2)?
+        );
+        assert_eq!(
+            buffer.next_paragraph(buffer.line_to_char(2)?)?,
+            buffer.line_to_char(5)?
+        );
+        assert_eq!(
+            buffer.next_paragraph(buffer.line_to_char(5)?)?,
+            CharIndex(buffer.len_chars())
+        );
+
+        assert_eq!(
+            buffer.previous_paragraph(buffer.line_to_char(6)?)?,
+            buffer.line_to_char(5)?
+        );
+        assert_eq!(
+            buffer.previous_paragraph(buffer.line_to_char(5)?)?,
+            buffer.line_to_char(3)?
+        );
+        assert_eq!(
+            buffer.previous_paragraph(buffer.line_to_char(1)?)?,
+            CharIndex(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn heading_movement() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+        let buffer = Buffer::new(
+            Some(tree_sitter_md::LANGUAGE.into()),
+            "# One\n\ntext\n\n## Two\n\nThree\n=====\n",
+        );
+        let two = buffer.line_to_char(4)?;
+        let three = buffer.line_to_char(6)?;
+        assert_eq!(buffer.next_heading(CharIndex(0))?, Some(two));
+        assert_eq!(buffer.next_heading(two)?, Some(three));
+        assert_eq!(buffer.next_heading(three)?, None);
+        assert_eq!(buffer.previous_heading(three)?, Some(two));
+        assert_eq!(buffer.previous_heading(two)?, Some(CharIndex(0)));
+        assert_eq!(buffer.previous_heading(CharIndex(0))?, None);
+
+        // Not Markdown
+        assert_eq!(Buffer::new(None, "# One").next_heading(CharIndex(0))?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_outline() -> anyhow::Result<()> {
+        let buffer = Buffer::new(
+            Some(tree_sitter_md::LANGUAGE.into()),
+            "# One\n\ntext\n\n### Two  \n\nThree\n=====\n\nFour\n----\n\n#\n",
+        );
+        assert_eq!(
+            buffer.markdown_outline(),
+            [
+                (1, "One".to_string(), buffer.line_to_char(0)?),
+                (3, "Two".to_string(), buffer.line_to_char(4)?),
+                (1, "Three".to_string(), buffer.line_to_char(6)?),
+                (2, "Four".to_string(), buffer.line_to_char(9)?),
+                (1, "".to_string(), buffer.line_to_char(12)?),
+            ]
+        );
+
+        // Not Markdown
+        assert!(Buffer::new(None, "# One").markdown_outline().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_link_at() {
+        use crate::selection::CharIndex;
+        let content = "# Links\n\nSee [other](./other.md) and <https://example.com>.\n\nAlso [full][Ref], [ref][] and [REF], but not [missing].\n\n[ref]: <./ref.md>\n";
+        let buffer = Buffer::new(Some(tree_sitter_md::LANGUAGE.into()), content);
+        let link_at = |needle: &str| {
+            buffer.markdown_link_at(CharIndex(content.find(needle).unwrap() + needle.len() / 2))
+        };
+        assert_eq!(link_at("[other]"), Some("./other.md".to_string()));
+        assert_eq!(link_at("./other.md"), Some("./other.md".to_string()));
+        assert_eq!(
+            link_at("https://example.com"),
+            Some("https://example.com".to_string())
+        );
+        assert_eq!(link_at("[full]"), Some("./ref.md".to_string()));
+        assert_eq!(link_at("[ref][]"), Some("./ref.md".to_string()));
+        assert_eq!(link_at("[REF]"), Some("./ref.md".to_string()));
+        assert_eq!(link_at("[ref]:"), Some("./ref.md".to_string()));
+
+        assert_eq!(link_at("[missing]"), None);
+        assert_eq!(link_at("See"), None);
+        assert_eq!(link_at("Links"), None);
+    }
+
+    #[test]
+    fn word_boundaries_with_and_without_subwords() {
+        use crate::selection::CharIndex;
+        let buffer = Buffer::new(None, "fooBar baz_qux // HTTPServer");
+
+        // Without subwords, CamelCase and snake_case are single words
+        assert_eq!(buffer.next_word_boundary(CharIndex(0), false), CharIndex(7));
+        assert_eq!(
+            buffer.next_word_boundary(CharIndex(7), false),
+            CharIndex(18)
+        );
+        assert_eq!(
+            buffer.next_word_boundary(CharIndex(18), false),
+            CharIndex(28)
+        );
+        assert_eq!(
+            buffer.previous_word_boundary(CharIndex(18), false),
+            CharIndex(7)
+        );
+        assert_eq!(
+            buffer.previous_word_boundary(CharIndex(0), false),
+            CharIndex(0)
+        );
+        assert_eq!(buffer.word_end_boundary(CharIndex(0), false), CharIndex(6));
+        assert_eq!(buffer.word_end_boundary(CharIndex(6), false), CharIndex(14));
+
+        // With subwords, they are split at case changes and underscores
+        assert_eq!(buffer.next_word_boundary(CharIndex(0), true), CharIndex(3));
+        assert_eq!(buffer.next_word_boundary(CharIndex(3), true), CharIndex(7));
+        assert_eq!(buffer.next_word_boundary(CharIndex(7), true), CharIndex(11));
+        assert_eq!(
+            buffer.next_word_boundary(CharIndex(18), true),
+            CharIndex(22)
+        );
+        assert_eq!(
+            buffer.previous_word_boundary(CharIndex(14), true),
+            CharIndex(11)
+        );
+        assert_eq!(buffer.word_end_boundary(CharIndex(0), true), CharIndex(3));
+        assert_eq!(buffer.word_end_boundary(CharIndex(18), true), CharIndex(22));
+    }
+
+    #[test]
+    fn word_boundaries_are_grapheme_aware() {
+        use crate::selection::CharIndex;
+        // The "é" consists of "e" followed by a combining acute accent
+        let buffer = Buffer::new(None, "cafe\u{301} 日本 bar");
+        assert_eq!(buffer.word_end_boundary(CharIndex(0), false), CharIndex(5));
+        assert_eq!(buffer.next_word_boundary(CharIndex(0), false), CharIndex(6));
+        assert_eq!(
+            buffer.previous_word_boundary(CharIndex(11), false),
+            CharIndex(9)
+        );
+    }
+
+    #[test]
+    fn line_len_chars_excludes_trailing_newline() {
+        use crate::selection::CharIndex;
+        let buffer = Buffer::new(None, "héllo\n\nwörld");
+        assert_eq!(buffer.len_lines(), 3);
+        assert_eq!(buffer.line_len_chars(0), 5);
+        assert_eq!(buffer.line_len_chars(1), 0);
+        // The last line has no trailing newline
+        assert_eq!(buffer.line_len_chars(2), 5);
+        assert_eq!(buffer.line_len_chars(3), 0);
+        assert_eq!(buffer.last_char_index(), CharIndex(11));
+
+        let buffer = Buffer::new(None, "a\n");
+        assert_eq!(buffer.len_lines(), 1);
+        assert_eq!(buffer.line_len_chars(0), 1);
+        assert_eq!(buffer.last_char_index(), CharIndex(1));
+
+        assert_eq!(Buffer::new(None, "").last_char_index(), CharIndex(0));
+    }
+
+    #[test]
+    fn read_only_buffer_rejects_edits() -> anyhow::Result<()> {
+        let mut buffer = Buffer::new(None, "hello");
+        let edit_transaction = buffer.get_edit_transaction("hello world")?;
+        buffer.apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)?;
+
+        buffer.set_read_only(true);
+        let edit_transaction = buffer.get_edit_transaction("bye")?;
+        assert_eq!(
+            buffer
+                .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+                .unwrap_err()
+                .to_string(),
+            "This buffer is read-only"
+        );
+        assert!(buffer.undo(0).is_err());
+        assert_eq!(buffer.content(), "hello world");
+
+        buffer.set_read_only(false);
+        assert!(buffer.undo(0)?.is_some());
+        assert_eq!(buffer.content(), "hello");
+        Ok(())
+    }
+
+    #[test]
+    fn read_only_buffer_can_be_reloaded() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("generated.rs");
+        std::fs::write(&file_path, "fn main() {}")?;
+        let path = CanonicalizedPath::try_from(file_path.clone())?;
+        let mut buffer = Buffer::from_path(&path, false, None)?;
+        buffer.set_read_only(true);
+
+        std::fs::write(&file_path, "fn main() { regenerated() }")?;
+        buffer.reload()?;
+        assert_eq!(buffer.content(), "fn main() { regenerated() }");
+
+        std::fs::write(&file_path, "fn main() { regenerated_again() }")?;
+        buffer.reload_from_disk(SelectionSet::default())?;
+        assert_eq!(buffer.content(), "fn main() { regenerated_again() }");
+
+        // The reloads are not undoable by the user
+        assert!(buffer.undo(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn from_path_of_non_writable_file_is_read_only() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("generated.rs");
+        std::fs::write(&file_path, "fn main() {}")?;
+        let mut permissions = std::fs::metadata(&file_path)?.permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&file_path, permissions)?;
+
+        let path = CanonicalizedPath::try_from(file_path)?;
+        assert!(Buffer::from_path(&path, false, None)?.read_only());
+        Ok(())
+    }
+
+    #[test]
+    fn apply_edit_transaction_rejects_edit_past_end_of_file() {
+        use crate::{
+            edit::{Action, ActionGroup, Edit, EditTransaction},
+            selection::CharIndex,
+        };
+
+        let mut buffer = Buffer::new(None, "hello");
+        let edit_transaction = EditTransaction::from_action_groups(
+            [ActionGroup::new(
+                [Action::Edit(Edit {
+                    range: (CharIndex(3)..CharIndex(10)).into(),
+                    new: "p".into(),
+                    old: "lo".into(),
+                })]
+                .to_vec(),
+            )]
+            .to_vec(),
+        );
+        let error = buffer
+            .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Edit 0 has the range 3..10, which exceeds the content length of 5"
+        );
+        assert_eq!(buffer.content(), "hello");
+        assert!(buffer.undo(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn insert_and_delete_are_undoable() -> anyhow::Result<()> {
+        use crate::selection::CharIndex;
+
+        let mut buffer = Buffer::new(None, "hello world");
+        buffer.insert(CharIndex(5), ",", SelectionSet::default())?;
+        assert_eq!(buffer.content(), "hello, world");
+        buffer.delete(CharIndex(0)..CharIndex(7), SelectionSet::default())?;
+        assert_eq!(buffer.content(), "world");
+
+        buffer.undo(0)?;
+        assert_eq!(buffer.content(), "hello, world");
+        buffer.undo(0)?;
+        assert_eq!(buffer.content(), "hello world");
+        buffer.redo(0)?;
+        assert_eq!(buffer.content(), "hello, world");
+        Ok(())
+    }
+
+    #[test]
+    fn apply_edit_transaction_returns_post_edit_ranges() -> anyhow::Result<()> {
+        use crate::{
+            char_index_range::CharIndexRange,
+            edit::{Action, ActionGroup, Edit, EditTransaction},
+            selection::CharIndex,
+        };
+
+        let mut buffer = Buffer::new(None, "a b c");
+        let replace = |buffer: &Buffer, start: usize, new: &str| {
+            ActionGroup::new(
+                [Action::Edit(Edit::new(
+                    buffer.rope(),
+                    (CharIndex(start)..CharIndex(start + 1)).into(),
+                    new.into(),
+                ))]
+                .to_vec(),
+            )
+        };
+        let edit_transaction = EditTransaction::from_action_groups(
+            [replace(&buffer, 4, "zz"), replace(&buffer, 0, "xxx")].to_vec(),
+        );
+        let (_, edited_ranges) = buffer.apply_edit_transaction_with_edited_ranges(
+            &edit_transaction,
+            SelectionSet::default(),
+            true,
+            true,
+            0,
+        )?;
+        assert_eq!(buffer.content(), "xxx b zz");
+        assert_eq!(
+            edited_ranges,
+            vec![
+                CharIndexRange::from(CharIndex(0)..CharIndex(3)),
+                CharIndexRange::from(CharIndex(6)..CharIndex(8)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn try_new_parses_the_given_text() {
+        let language = shared::language::from_extension("rs")
+            .unwrap()
+            .tree_sitter_language();
+        let buffer = Buffer::try_new(language, "fn main() {}").unwrap();
+        assert_eq!(buffer.tree.unwrap().root_node().kind(), "source_file");
+
+        let buffer = Buffer::try_new(None, "hello").unwrap();
+        assert!(buffer.tree.is_none());
+    }
+
+    #[test]
+    fn highlight_ranges_only_emits_captures_within_range() {
+        use crate::selection::CharIndex;
+        use std::ops::Range;
+
+        let language: tree_sitter::Language = tree_sitter_rust::LANGUAGE.into();
+        let query = tree_sitter::Query::new(&language, tree_sitter_rust::HIGHLIGHTS_QUERY).unwrap();
+        let buffer = Buffer::new(
+            Some(language),
+            "fn main() {}\nfn foo() { let x = \"hello\"; }",
+        );
+        let capture_name = |range: &Range<CharIndex>, buffer: &Buffer| {
+            buffer
+                .highlight_ranges(range.clone(), &query)
+                .unwrap()
+                .find(|(capture_range, _)| capture_range == range)
+                .map(|(_, index)| query.capture_names()[index as usize])
+        };
+
+        let keyword = CharIndex(13)..CharIndex(15);
+        let string = CharIndex(32)..CharIndex(39);
+        assert_eq!(buffer.slice(&(keyword.clone().into())).unwrap(), "fn");
+        assert_eq!(buffer.slice(&(string.clone().into())).unwrap(), "\"hello\"");
+        assert_eq!(capture_name(&keyword, &buffer), Some("keyword"));
+        assert_eq!(capture_name(&string, &buffer), Some("string"));
+
+        // Captures outside of the requested range are excluded
+        assert!(buffer
+            .highlight_ranges(CharIndex(13)..CharIndex(15), &query)
+            .unwrap()
+            .all(|(range, _)| range.start < CharIndex(15) && range.end > CharIndex(13)));
+    }
+
+    #[test]
+    fn node_at() {
+        use crate::selection::CharIndex;
+
+        let buffer = Buffer::new(
+            Some(tree_sitter_rust::LANGUAGE.into()),
+            "fn main() { let x = 1; }",
+        );
+        let kind = |char_index: usize, named: bool| {
+            buffer
+                .node_at(CharIndex(char_index), named)
+                .map(|node| node.kind())
+        };
+
+        // Inside an identifier
+        assert_eq!(kind(5, true), Some("identifier"));
+        assert_eq!(kind(1, false), Some("fn"));
+        assert_eq!(kind(1, true), Some("function_item"));
+
+        // On whitespace, the enclosing node is returned
+        assert_eq!(kind(11, true), Some("block"));
+
+        // Beyond the end of the buffer
+        assert_eq!(kind(100, false), Some("}"));
+
+        // Multi-byte characters
+        let buffer = Buffer::new(
+            Some(tree_sitter_rust::LANGUAGE.into()),
+            "const S: &str = \"大學之道\";",
+        );
+        assert_eq!(
+            buffer.node_at(CharIndex(18), true).map(|node| node.kind()),
+            Some("string_content")
+        );
+
+        let buffer = Buffer::new(Some(tree_sitter_rust::LANGUAGE.into()), "");
+        assert!(buffer.node_at(CharIndex(0), false).is_none());
+    }
+
+    #[test]
+    fn navigate_between_nodes() {
+        use crate::selection::CharIndex;
+
+        let buffer = Buffer::new(
+            Some(tree_sitter_rust::LANGUAGE.into()),
+            "fn main() { let x = 1; }",
+        );
+        let identifier = buffer.node_at(CharIndex(5), true).unwrap();
+        assert_eq!(
+            buffer.slice(&(CharIndex(3)..CharIndex(7)).into()).unwrap(),
+            "main"
+        );
+
+        let function = buffer.parent_node(identifier, true).unwrap();
+        assert_eq!(function.kind(), "function_item");
+        assert_eq!(buffer.first_child_node(function, true), Some(identifier));
+        assert_eq!(
+            buffer.first_child_node(function, false).unwrap().kind(),
+            "fn"
+        );
+
+        let parameters = buffer.next_sibling_node(identifier, true).unwrap();
+        assert_eq!(parameters.kind(), "parameters");
+        assert_eq!(buffer.prev_sibling_node(parameters, true), Some(identifier));
+        assert_eq!(
+            buffer.prev_sibling_node(identifier, false).unwrap().kind(),
+            "fn"
+        );
+        assert_eq!(buffer.prev_sibling_node(identifier, true), None);
+
+        let root = buffer.parent_node(function, true).unwrap();
+        assert_eq!(root.kind(), "source_file");
+        assert_eq!(buffer.parent_node(root, false), None);
+    }
+
+    #[test]
+    fn expand_and_shrink_selection_are_inverses() {
+        use super::SelectionExpansions;
+        use crate::selection::{CharIndex, Selection};
+
+        let buffer = Buffer::new(
+            Some(tree_sitter_rust::LANGUAGE.into()),
+            "fn main() { let x = 1 + 2; }",
+        );
+        let text = |selection: &Selection| buffer.slice(&selection.range()).unwrap().to_string();
+        let cursor = CharIndex(20);
+        let mut expansions = SelectionExpansions::default();
+
+        let mut selections = vec![Selection::new((cursor..cursor + 1).into())];
+        while let Some(selection) = buffer
+            .expand_selection(selections.last().unwrap(), &mut expansions)
+            .unwrap()
+        {
+            selections.push(selection)
+        }
+        assert_eq!(
+            selections.iter().map(text).collect_vec(),
+            [
+                "1",
+                "1 + 2",
+                "let x = 1 + 2;",
+                "{ let x = 1 + 2; }",
+                "fn main() { let x = 1 + 2; }"
+            ]
+        );
+
+        // Shrinking reverses the expansions exactly
+        let mut current = selections.last().unwrap().clone();
+        for expected in selections.iter().rev().skip(1) {
+            current = buffer
+                .shrink_selection(&current, cursor, &mut expansions)
+                .unwrap()
+                .unwrap();
+            assert_eq!(&current, expected);
+        }
+
+        // Without expansion history, shrink descends towards the cursor
+        let selection = buffer
+            .shrink_selection(selections.last().unwrap(), cursor, &mut expansions)
+            .unwrap()
+            .unwrap();
+        assert_eq!(text(&selection), "{ let x = 1 + 2; }");
+    }
+
+    #[test]
+    fn diagnostics_of_different_language_servers_are_merged() {
+        let mut buffer = Buffer::new(None, "hello world");
+        let diagnostic = |start: u32, end: u32, message: &str| {
+            lsp_types::Diagnostic::new_simple(
+                lsp_types::Range::new(
+                    lsp_types::Position::new(0, start),
+                    lsp_types::Position::new(0, end),
+                ),
+                message.to_string(),
+            )
+        };
+        let messages = |buffer: &Buffer| {
+            buffer
+                .diagnostics()
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect_vec()
+        };
+        buffer.set_diagnostics("linter", [diagnostic(6, 11, "world")].to_vec());
+        buffer.set_diagnostics("compiler", [diagnostic(0, 5, "hello")].to_vec());
+        assert_eq!(messages(&buffer), ["hello", "world"]);
+
+        // Only the diagnostics of the same language server are replaced
+        buffer.set_diagnostics("compiler", Vec::new());
+        assert_eq!(messages(&buffer), ["world"]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        use super::SyntaxErrorKind;
+        use crate::selection::CharIndex;
+
+        let buffer = Buffer::new(
+            Some(tree_sitter_rust::LANGUAGE.into()),
+            "fn main() { let x = 1 }",
+        );
+        assert_eq!(
+            buffer.syntax_errors(),
+            [(
+                (CharIndex(21)..CharIndex(21)).into(),
+                SyntaxErrorKind::Missing
+            )]
+        );
+
+        let buffer = Buffer::new(Some(tree_sitter_rust::LANGUAGE.into()), "fn main() { ) }");
+        // The unexpected `)` is enclosed by an error node
+        assert!(buffer.syntax_errors().iter().any(|(range, kind)| {
+            kind == &SyntaxErrorKind::Error
+                && range.start <= CharIndex(12)
+                && CharIndex(13) <= range.end
+        }));
+
+        let buffer = Buffer::new(Some(tree_sitter_rust::LANGUAGE.into()), "fn main() {}");
+        assert!(buffer.syntax_errors().is_empty());
+    }
+
+    #[test]
+    fn lsp_position_uses_utf16_columns() {
+        use crate::selection::CharIndex;
+
+        let buffer = Buffer::new(None, "hello\nlet 😀 = x;");
+        // The char index of `=`
+        let char_index = CharIndex(12);
+        assert_eq!(buffer.char(char_index).unwrap(), '=');
+
+        // The emoji is one char, but two UTF-16 code units
+        assert_eq!(buffer.char_to_position(char_index).unwrap().column, 6);
+        let lsp_position = buffer.char_to_lsp_position(char_index).unwrap();
+        assert_eq!(
+            lsp_position,
+            lsp_types::Position {
+                line: 1,
+                character: 7
+            }
+        );
+        assert_eq!(
+            buffer.lsp_position_to_char(lsp_position).unwrap(),
+            char_index
+        );
+        assert_eq!(
+            buffer
+                .lsp_position_to_char(lsp_types::Position {
+                    line: 0,
+                    character: 99
+                })
+                .unwrap(),
+            CharIndex(5)
+        );
+    }
+
+    #[test]
+    fn semantic_highlighted_spans_follow_edits() {
+        use crate::{lsp::semantic_tokens::SemanticToken, position::Position};
+        use lsp_types::SemanticTokenType;
+
+        let mut buffer = Buffer::new(None, "😀 foo\nbar");
+        let token = |line: usize, column: usize, token_type: SemanticTokenType| SemanticToken {
+            range: Position { line, column }..Position {
+                line,
+                column: column + 3,
+            },
+            token_type,
+            modifiers: Vec::new(),
+        };
+        // The columns are in UTF-16 code units, the emoji takes up two of them
+        buffer.set_semantic_tokens(&[
+            token(0, 3, SemanticTokenType::FUNCTION),
+            token(1, 0, SemanticTokenType::VARIABLE),
+            // Keywords are left to Tree-sitter
+            token(1, 0, SemanticTokenType::KEYWORD),
+        ]);
+        let byte_ranges = |buffer: &Buffer| {
+            buffer
+                .semantic_highlighted_spans()
+                .iter()
+                .map(|span| span.byte_range.clone())
+                .collect_vec()
+        };
+        assert_eq!(byte_ranges(&buffer), [5..8, 9..12]);
+
+        let edit = |buffer: &mut Buffer, new_content: &str| {
+            let edit_transaction = buffer.get_edit_transaction(new_content).unwrap();
+            buffer
+                .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+                .unwrap();
+        };
+
+        // Spans after the edit are shifted
+        edit(&mut buffer, "x\n😀 foo\nbar");
+        assert_eq!(byte_ranges(&buffer), [7..10, 11..14]);
+
+        // Spans touched by the edit are removed
+        edit(&mut buffer, "x\n😀 f\nbar");
+        assert_eq!(byte_ranges(&buffer), [9..12]);
+    }
+
+    #[test]
+    fn folding_ranges_prefer_language_server_until_edited() {
+        let language = shared::language::from_extension("rs")
+            .unwrap()
+            .tree_sitter_language();
+        let content = "fn main() {\n    let x = (\n        1,\n    );\n}";
+        let mut buffer = Buffer::new(language, content);
+
+        // Derived from the syntax tree, nodes spanning the same lines are deduplicated
+        assert_eq!(buffer.folding_ranges(), [0..5, 1..4]);
+
+        buffer.set_folding_ranges(vec![2..3]);
+        assert_eq!(buffer.folding_ranges(), [2..3]);
+
+        // Ranges from the language server are discarded once the buffer changes
+        let edit_transaction = buffer
+            .get_edit_transaction(&format!("{content}\n"))
+            .unwrap();
+        buffer
+            .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+            .unwrap();
+        assert_eq!(buffer.folding_ranges(), [0..5, 1..4]);
+    }
+
+    #[test]
+    fn document_highlights_are_sorted_and_cleared_on_edit() {
+        use crate::{
+            char_index_range::CharIndexRange,
+            lsp::document_highlight::{DocumentHighlight, DocumentHighlightKind},
+            position::Position,
+            selection::CharIndex,
+        };
+
+        let mut buffer = Buffer::new(None, "😀 x = x");
+        let highlight = |start, end, kind| DocumentHighlight {
+            range: Position::new(0, start)..Position::new(0, end),
+            kind,
+        };
+        buffer.set_document_highlights(vec![
+            highlight(6, 7, DocumentHighlightKind::Read),
+            highlight(2, 3, DocumentHighlightKind::Write),
+        ]);
+        assert_eq!(
+            buffer.document_highlights(),
+            [
+                (
+                    CharIndexRange::from(CharIndex(2)..CharIndex(3)),
+                    DocumentHighlightKind::Write
+                ),
+                (
+                    CharIndexRange::from(CharIndex(6)..CharIndex(7)),
+                    DocumentHighlightKind::Read
+                ),
+            ]
+        );
+
+        let edit_transaction = buffer.get_edit_transaction("😀 y = x").unwrap();
+        buffer
+            .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+            .unwrap();
+        assert!(buffer.document_highlights().is_empty());
+    }
+
+    #[test]
+    fn content_changes_have_utf16_ranges_of_the_content_before_each_edit() {
+        let mut buffer = Buffer::new(None, "😀 foo\nbar\n");
+        let edit_transaction = buffer.get_edit_transaction("😀 fooo\nbaz\n").unwrap();
+        buffer
+            .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+            .unwrap();
+
+        let (version, changes) = buffer.take_content_changes();
+        assert_eq!(version, 2);
+        let changes = changes.unwrap();
+        let mut content = "😀 foo\nbar\n".encode_utf16().collect_vec();
+        // Applying the changes in order should yield the new content
+        for change in changes {
+            let range = change.range.unwrap();
+            let offset = |position: lsp_types::Position| {
+                let line_start = String::from_utf16(&content)
+                    .unwrap()
+                    .split_inclusive('\n')
+                    .take(position.line as usize)
+                    .map(|line| line.encode_utf16().count())
+                    .sum::<usize>();
+                line_start + position.character as usize
+            };
+            let (start, end) = (offset(range.start), offset(range.end));
+            content.splice(start..end, change.text.encode_utf16());
+        }
+        assert_eq!(String::from_utf16(&content).unwrap(), "😀 fooo\nbaz\n");
+
+        // Taking the changes resets them
+        assert_eq!(buffer.take_content_changes(), (3, Some(Vec::new())));
+
+        // The changes are unknown once the content is replaced wholesale
+        buffer.update("hello");
+        assert_eq!(buffer.take_content_changes(), (4, None));
+    }
+
+    #[test]
+    fn saving_dirty_buffer_without_path_should_fail() {
+        let mut buffer = Buffer::new(None, "");
+
+        // Clean buffers have nothing to save
+        assert_eq!(buffer.save_without_formatting(false).unwrap(), None);
+
+        buffer.update("hello");
+        assert!(buffer.save_without_formatting(false).is_err());
+        assert!(buffer.dirty());
+    }
+
     #[test]
     fn only_update_syntax_highlight_spans_of_same_batch_id() {
         run_test(|_, mut buffer| {
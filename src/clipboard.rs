@@ -1,11 +1,73 @@
+use std::collections::HashMap;
+
 use itertools::Itertools;
 use nonempty::NonEmpty;
 
 use crate::osc52;
 
-#[derive(Clone)]
+/// Access to the system clipboard, which can be replaced in tests
+/// (see `Context::with_clipboard`) so that they never touch the real clipboard.
+pub(crate) trait ClipboardProvider {
+    fn get(&self) -> anyhow::Result<String>;
+    fn set(&mut self, text: String) -> anyhow::Result<()>;
+}
+
+/// Falls back to OSC 52 when copying, for terminals without access to the system clipboard (e.g. over SSH).
+pub(crate) struct SystemClipboardProvider;
+
+impl ClipboardProvider for SystemClipboardProvider {
+    fn get(&self) -> anyhow::Result<String> {
+        Ok(arboard::Clipboard::new()?.get_text()?)
+    }
+
+    fn set(&mut self, text: String) -> anyhow::Result<()> {
+        Ok(arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(&text))
+            .or_else(|_| osc52::copy_to_clipboard(&text))?)
+    }
+}
+
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct InMemoryClipboardProvider {
+    text: Option<String>,
+}
+
+#[cfg(test)]
+impl ClipboardProvider for InMemoryClipboardProvider {
+    fn get(&self) -> anyhow::Result<String> {
+        self.text
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("The clipboard is empty"))
+    }
+
+    fn set(&mut self, text: String) -> anyhow::Result<()> {
+        self.text = Some(text);
+        Ok(())
+    }
+}
+
 pub(crate) struct Clipboard {
+    /// Serves as the unnamed register
     history: RingHistory<CopiedTexts>,
+    /// Named registers, which are independent of the unnamed register
+    /// and the system clipboard
+    registers: HashMap<char, String>,
+    /// The offset of the entry last returned by `Clipboard::cycle_paste`
+    paste_cycle_offset: isize,
+    system_clipboard: Box<dyn ClipboardProvider>,
+}
+
+/// Decides where copied texts are placed when pasted, like in Vim.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub(crate) enum ClipboardKind {
+    /// Pasted inline
+    #[default]
+    CharWise,
+    /// Pasted on lines of their own
+    LineWise,
+    /// Pasted as a rectangle, one row per line
+    BlockWise,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -15,13 +77,26 @@ pub(crate) struct Clipboard {
 /// and so forth.
 pub(crate) struct CopiedTexts {
     texts: NonEmpty<String>,
+    kind: ClipboardKind,
 }
 impl CopiedTexts {
+    /// The kind defaults to `ClipboardKind::CharWise`, see `CopiedTexts::with_kind`.
     pub(crate) fn new(texts: NonEmpty<String>) -> Self {
-        Self { texts }
+        Self {
+            texts,
+            kind: ClipboardKind::default(),
+        }
+    }
+
+    pub(crate) fn with_kind(self, kind: ClipboardKind) -> Self {
+        Self { kind, ..self }
     }
 
-    fn join(&self, separator: &str) -> String {
+    pub(crate) fn kind(&self) -> ClipboardKind {
+        self.kind
+    }
+
+    pub(crate) fn join(&self, separator: &str) -> String {
         self.texts.clone().into_iter().join(separator)
     }
 
@@ -43,15 +118,53 @@ impl Clipboard {
     pub(crate) fn new() -> Clipboard {
         Clipboard {
             history: RingHistory::new(),
+            registers: HashMap::new(),
+            paste_cycle_offset: 0,
+            system_clipboard: Box::new(SystemClipboardProvider),
         }
     }
 
+    #[cfg(test)]
+    pub(crate) fn set_system_clipboard(&mut self, provider: Box<dyn ClipboardProvider>) {
+        self.system_clipboard = provider
+    }
+
+    /// The maximum number of entries kept in the clipboard history,
+    /// the oldest entries are discarded first.
+    const MAX_HISTORY_LEN: usize = 50;
+
+    /// Ordered from the oldest to the newest.
+    pub(crate) fn history(&self) -> &[CopiedTexts] {
+        self.history.items()
+    }
+
+    /// Like yank-pop in Emacs, each call returns an entry that is older than
+    /// the one returned by the previous call, wrapping around to the newest entry.
+    ///
+    /// Copying resets the cycle.
+    pub(crate) fn cycle_paste(&mut self) -> Option<CopiedTexts> {
+        self.paste_cycle_offset -= 1;
+        self.history.get(self.paste_cycle_offset)
+    }
+
+    pub(crate) fn set_register(&mut self, name: char, content: String) {
+        self.registers.insert(name, content);
+    }
+
+    pub(crate) fn get_register(&self, name: char) -> Option<String> {
+        self.registers.get(&name).cloned()
+    }
+
+    pub(crate) fn registers(&self) -> &HashMap<char, String> {
+        &self.registers
+    }
+
     pub(crate) fn get(&self, history_offset: isize) -> Option<CopiedTexts> {
         self.history.get(history_offset)
     }
 
     pub(crate) fn get_from_system_clipboard(&self) -> anyhow::Result<String> {
-        Ok(arboard::Clipboard::new()?.get_text()?)
+        self.system_clipboard.get()
     }
 
     pub(crate) fn set(
@@ -59,11 +172,14 @@ impl Clipboard {
         copied_texts: CopiedTexts,
         use_system_clipboard: bool,
     ) -> anyhow::Result<()> {
-        self.history.add(copied_texts.clone());
+        // Copying the same texts again should not push older entries out of the history
+        if self.history.get(0).as_ref() != Some(&copied_texts) {
+            self.history.add(copied_texts.clone());
+            self.history.truncate_oldest(Self::MAX_HISTORY_LEN);
+        }
+        self.paste_cycle_offset = 0;
         if use_system_clipboard {
-            arboard::Clipboard::new()
-                .and_then(|mut clipboard| clipboard.set_text(copied_texts.join("\n")))
-                .or_else(|_| osc52::copy_to_clipboard(&copied_texts.join("\n")))?
+            self.system_clipboard.set(copied_texts.join("\n"))?
         }
         Ok(())
     }
@@ -102,6 +218,16 @@ impl<T: Clone> RingHistory<T> {
         self.items.push(item)
     }
 
+    pub(crate) fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Keeps only the newest `max_len` items.
+    pub(crate) fn truncate_oldest(&mut self, max_len: usize) {
+        let excess = self.items.len().saturating_sub(max_len);
+        self.items.drain(..excess);
+    }
+
     fn new() -> Self {
         Self {
             items: Default::default(),
@@ -109,6 +235,92 @@ impl<T: Clone> RingHistory<T> {
     }
 }
 
+#[cfg(test)]
+mod test_clipboard {
+    use super::*;
+
+    #[test]
+    fn named_registers_are_independent_of_the_unnamed_register() {
+        let mut clipboard = Clipboard::new();
+        clipboard
+            .set(CopiedTexts::one("unnamed".to_string()), false)
+            .unwrap();
+        clipboard.set_register('a', "yanked to a".to_string());
+
+        assert_eq!(clipboard.get_register('a'), Some("yanked to a".to_string()));
+        assert_eq!(clipboard.get_register('b'), None);
+        assert_eq!(
+            clipboard.get(0),
+            Some(CopiedTexts::one("unnamed".to_string()))
+        );
+
+        // Overwriting the unnamed register leaves the named registers intact
+        clipboard
+            .set(CopiedTexts::one("another".to_string()), false)
+            .unwrap();
+        assert_eq!(clipboard.get_register('a'), Some("yanked to a".to_string()));
+    }
+
+    #[test]
+    fn history_is_bounded_and_skips_consecutive_duplicates() {
+        let mut clipboard = Clipboard::new();
+        let copy = |clipboard: &mut Clipboard, text: &str| {
+            clipboard
+                .set(CopiedTexts::one(text.to_string()), false)
+                .unwrap()
+        };
+        copy(&mut clipboard, "a");
+        copy(&mut clipboard, "a");
+        copy(&mut clipboard, "b");
+        copy(&mut clipboard, "a");
+        assert_eq!(
+            clipboard.history(),
+            ["a", "b", "a"].map(|text| CopiedTexts::one(text.to_string()))
+        );
+
+        for index in 0..Clipboard::MAX_HISTORY_LEN {
+            copy(&mut clipboard, &index.to_string());
+        }
+        assert_eq!(clipboard.history().len(), Clipboard::MAX_HISTORY_LEN);
+        assert_eq!(clipboard.history()[0], CopiedTexts::one("0".to_string()));
+    }
+
+    #[test]
+    fn cycle_paste_walks_older_entries_until_the_next_copy() {
+        let mut clipboard = Clipboard::new();
+        for text in ["a", "b", "c"] {
+            clipboard
+                .set(CopiedTexts::one(text.to_string()), false)
+                .unwrap();
+        }
+        let mut cycle = || clipboard.cycle_paste().unwrap().get(0);
+        assert_eq!([cycle(), cycle(), cycle()], ["b", "a", "c"]);
+
+        clipboard
+            .set(CopiedTexts::one("d".to_string()), false)
+            .unwrap();
+        assert_eq!(
+            clipboard.cycle_paste(),
+            Some(CopiedTexts::one("c".to_string()))
+        );
+    }
+
+    #[test]
+    fn copying_the_same_texts_with_another_kind_is_a_new_entry() {
+        let mut clipboard = Clipboard::new();
+        let texts = CopiedTexts::one("a\n".to_string());
+        assert_eq!(texts.kind(), ClipboardKind::CharWise);
+        clipboard.set(texts.clone(), false).unwrap();
+        clipboard
+            .set(texts.clone().with_kind(ClipboardKind::LineWise), false)
+            .unwrap();
+        assert_eq!(
+            clipboard.history(),
+            [texts.clone(), texts.with_kind(ClipboardKind::LineWise)]
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_ring_history {
     use super::*;
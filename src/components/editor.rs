@@ -8,7 +8,7 @@ use super::{
 };
 use crate::{
     app::{Dimension, Dispatch},
-    buffer::Buffer,
+    buffer::{Buffer, SortOptions},
     components::component::Component,
     edit::{Action, ActionGroup, Edit, EditTransaction},
     lsp::completion::PositionalEdit,
@@ -20,7 +20,7 @@ use crate::{
     app::{Dispatches, RequestParams, Scope},
     buffer::Line,
     char_index_range::CharIndexRange,
-    clipboard::CopiedTexts,
+    clipboard::{ClipboardKind, CopiedTexts},
     context::{Context, GlobalMode, LocalSearchConfigMode, Search},
     lsp::{completion::CompletionItemEdit, process::ResponseContext},
     selection_mode::{self, regex::get_regex},
@@ -193,6 +193,8 @@ impl Component for Editor {
             Copy {
                 use_system_clipboard,
             } => return self.copy(use_system_clipboard),
+            CopyToRegister(name) => return self.copy_to_register(name),
+            PasteFromRegister(name) => return self.paste_from_register(name, context),
             ReplaceWithCopiedText {
                 cut,
                 use_system_clipboard,
@@ -256,6 +258,7 @@ impl Component for Editor {
             }
             Save => return self.do_save(false, context),
             ForceSave => return self.do_save(true, context),
+            ReloadFile => return self.reload_file(context),
             ReplaceCurrentSelectionWith(string) => {
                 return self
                     .replace_current_selection_with(|_| Some(Rope::from_str(&string)), context)
@@ -311,6 +314,8 @@ impl Component for Editor {
             PipeToShell { command } => return self.pipe_to_shell(command, context),
             ShowCurrentTreeSitterNodeSexp => return self.show_current_tree_sitter_node_sexp(),
             Indent => return self.indent(context),
+            ToggleLineComment => return self.toggle_line_comment(context),
+            SortLines => return self.sort_lines(context),
             Dedent => return self.dedent(context),
             CyclePrimarySelection(direction) => self.cycle_primary_selection(direction),
             SwapExtensionAnchor => self.selection_set.swap_anchor(),
@@ -1036,16 +1041,35 @@ impl Editor {
     pub(crate) fn copy(&mut self, use_system_clipboard: bool) -> anyhow::Result<Dispatches> {
         Ok(Dispatches::one(Dispatch::SetClipboardContent {
             use_system_clipboard,
-            copied_texts: CopiedTexts::new(self.selection_set.map(|selection| {
-                self.buffer()
-                    .slice(&selection.extended_range())
-                    .ok()
-                    .map(|s| s.to_string())
-                    .unwrap_or_default()
-            })),
+            copied_texts: self.copied_texts(),
         }))
     }
 
+    /// The texts of multiple selections are joined by newlines,
+    /// because a register holds a single text.
+    fn copy_to_register(&self, name: char) -> anyhow::Result<Dispatches> {
+        Ok(Dispatches::one(Dispatch::SetRegister {
+            name,
+            content: self.copied_texts().join("\n"),
+        }))
+    }
+
+    /// The texts of the selections, which are line-wise if whole lines are selected.
+    fn copied_texts(&self) -> CopiedTexts {
+        let kind = match self.selection_set.mode {
+            SelectionMode::Line | SelectionMode::LineFull => ClipboardKind::LineWise,
+            _ => ClipboardKind::CharWise,
+        };
+        CopiedTexts::new(self.selection_set.map(|selection| {
+            self.buffer()
+                .slice(&selection.extended_range())
+                .ok()
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+        }))
+        .with_kind(kind)
+    }
+
     fn replace_current_selection_with<F>(
         &mut self,
         f: F,
@@ -1204,6 +1228,17 @@ impl Editor {
         self.paste_text(direction, copied_texts, context)
     }
 
+    fn paste_from_register(&mut self, name: char, context: &Context) -> anyhow::Result<Dispatches> {
+        let content = context
+            .get_register(name)
+            .ok_or_else(|| anyhow::anyhow!("Register {name:?} is empty"))?;
+        self.paste_text(
+            Direction::End,
+            CopiedTexts::new(NonEmpty::singleton(content)),
+            context,
+        )
+    }
+
     /// If `cut` if true, the replaced text will override the clipboard.  
     ///
     /// If `history_offset` is 0, it means select the latest copied text;  
@@ -1260,12 +1295,15 @@ impl Editor {
     }
 
     pub(crate) fn get_document_did_change_dispatch(&mut self) -> Dispatches {
+        let (version, changes) = self.buffer_mut().take_content_changes();
         [Dispatch::DocumentDidChange {
             component_id: self.id(),
             batch_id: self.buffer().batch_id().clone(),
             path: self.buffer().path(),
             content: self.buffer().rope().to_string(),
             language: self.buffer().language(),
+            version,
+            changes,
         }]
         .into_iter()
         .collect_vec()
@@ -1476,10 +1514,24 @@ impl Editor {
             context: ResponseContext {
                 scope: None,
                 description: None,
+                path: None,
+                document_version: None,
             },
         })
     }
 
+    pub(crate) fn primary_selection_position_range(&self) -> anyhow::Result<Range<Position>> {
+        self.buffer().char_index_range_to_position_range(
+            self.selection_set.primary_selection().extended_range(),
+        )
+    }
+
+    pub(crate) fn document_position_range(&self) -> anyhow::Result<Range<Position>> {
+        let buffer = self.buffer();
+        Ok(buffer.char_to_position(CharIndex(0))?
+            ..buffer.char_to_position(CharIndex(buffer.len_chars()))?)
+    }
+
     pub(crate) fn set_selection_mode(
         &mut self,
         if_current_not_found: IfCurrentNotFound,
@@ -2423,10 +2475,20 @@ impl Editor {
             }))
     }
 
+    fn reload_file(&mut self, context: &Context) -> anyhow::Result<Dispatches> {
+        let selection_set = self
+            .buffer_mut()
+            .reload_from_disk(self.selection_set.clone())?;
+        self.set_selection_set(selection_set, context);
+        Ok(self.get_document_did_change_dispatch())
+    }
+
     /// Clamp everything that might be out of bound after the buffer content is modified elsewhere
     fn clamp(&mut self, context: &Context) -> anyhow::Result<()> {
-        let len_chars = self.buffer().len_chars();
-        self.set_selection_set(self.selection_set.clamp(CharIndex(len_chars))?, context);
+        let selection_set = self
+            .buffer()
+            .clamp_selection_set(self.selection_set.clone())?;
+        self.set_selection_set(selection_set, context);
 
         let len_lines = self.buffer().len_lines();
         self.scroll_offset = self.scroll_offset.clamp(0, len_lines as u16);
@@ -2747,7 +2809,7 @@ impl Editor {
         };
 
         Ok(selection_set
-            .map(|selection_set| self.update_selection_set(selection_set, false, context))
+            .map(|(selection_set, _)| self.update_selection_set(selection_set, false, context))
             .unwrap_or_default()
             .chain(self.get_document_did_change_dispatch()))
     }
@@ -3131,17 +3193,74 @@ impl Editor {
     }
 
     fn show_current_tree_sitter_node_sexp(&self) -> Result<Dispatches, anyhow::Error> {
-        let buffer = self.buffer();
-        let node = buffer.get_current_node(self.selection_set.primary_selection(), false)?;
-        let info = node
-            .map(|node| node.to_sexp())
-            .unwrap_or("[No node found]".to_string());
+        let info = self
+            .buffer()
+            .sexp(Some(self.selection_set.primary_selection().range()))?;
         Ok(Dispatches::one(Dispatch::ShowEditorInfo(Info::new(
             "Tree-sitter node S-expression".to_string(),
             info,
         ))))
     }
 
+    /// Comments or uncomments the lines of every selection, see `Buffer::toggle_line_comment`.
+    fn toggle_line_comment(&mut self, context: &Context) -> anyhow::Result<Dispatches> {
+        let edit_transaction = {
+            let buffer = self.buffer();
+            let line_ranges: Vec<Range<usize>> = self
+                .selection_set
+                .map(|selection| buffer.char_index_range_to_line_range(selection.extended_range()))
+                .into_iter()
+                .map_ok(|lines| lines.start..lines.end + 1)
+                .try_collect()?;
+            // Selections on the same lines must not comment them twice
+            EditTransaction::merge(
+                line_ranges
+                    .into_iter()
+                    .sorted_by_key(|lines| lines.start)
+                    .coalesce(|a, b| {
+                        if b.start <= a.end {
+                            Ok(a.start..a.end.max(b.end))
+                        } else {
+                            Err((a, b))
+                        }
+                    })
+                    .map(|lines| buffer.toggle_line_comment(lines))
+                    .try_collect()?,
+            )
+        };
+        let selection_set =
+            self.selection_set
+                .apply(self.selection_set.mode.clone(), |selection| {
+                    let range = selection.range();
+                    Ok(selection.clone().set_range(
+                        (edit_transaction.map_char_index(range.start)
+                            ..edit_transaction.map_char_index(range.end))
+                            .into(),
+                    ))
+                })?;
+        let dispatches = self.apply_edit_transaction(edit_transaction, context)?;
+        self.set_selection_set(selection_set, context);
+        Ok(dispatches)
+    }
+
+    /// Sorts the lines of every selection, see `Buffer::sort_lines`.
+    fn sort_lines(&mut self, context: &Context) -> anyhow::Result<Dispatches> {
+        let edit_transaction = {
+            let buffer = self.buffer();
+            EditTransaction::merge(
+                self.selection_set
+                    .map(|selection| -> anyhow::Result<_> {
+                        let lines =
+                            buffer.char_index_range_to_line_range(selection.extended_range())?;
+                        buffer.sort_lines(lines.start..lines.end + 1, SortOptions::default())
+                    })
+                    .into_iter()
+                    .try_collect()?,
+            )
+        };
+        self.apply_edit_transaction(edit_transaction, context)
+    }
+
     fn indent(&mut self, context: &Context) -> Result<Dispatches, anyhow::Error> {
         let indentation: Rope = std::iter::repeat(INDENT_CHAR)
             .take(INDENT_WIDTH)
@@ -3671,12 +3790,16 @@ pub(crate) enum DispatchEditor {
     SetSelectionMode(IfCurrentNotFound, SelectionMode),
     Save,
     ForceSave,
+    /// Replaces the content with the file on disk, unless there are unsaved changes
+    ReloadFile,
     FindOneChar(IfCurrentNotFound),
     MoveSelection(Movement),
     SwitchViewAlignment,
     Copy {
         use_system_clipboard: bool,
     },
+    CopyToRegister(char),
+    PasteFromRegister(char),
     GoBack,
     GoForward,
     SelectAll,
@@ -3758,6 +3881,8 @@ pub(crate) enum DispatchEditor {
     ShowCurrentTreeSitterNodeSexp,
     Indent,
     Dedent,
+    ToggleLineComment,
+    SortLines,
     SwapExtensionAnchor,
     CollapseSelection(Direction),
     FilterSelectionMatchingSearch {
@@ -68,13 +68,13 @@ pub(crate) const KEYMAP_FIND_LOCAL: [[Meaning; 10]; 3] = [
 ];
 pub(crate) const KEYMAP_FIND_LOCAL_SHIFTED: [[Meaning; 10]; 3] = [
     [
-        _____, _____, _____, _____, _____, /****/ _____, _____, _____, _____, _____,
+        _____, _____, Fold_, _____, _____, /****/ _____, _____, _____, _____, _____,
     ],
     [
         _____, _____, _____, DgInf, GHnkM, /****/ _____, _____, _____, _____, _____,
     ],
     [
-        _____, LDecl, _____, LRfrI, _____, /****/ _____, _____, _____, _____, _____,
+        LClIn, LDecl, LClOt, LRfrI, _____, /****/ _____, _____, _____, _____, _____,
     ],
 ];
 
@@ -99,7 +99,7 @@ pub(crate) const KEYMAP_FIND_GLOBAL_SHIFTED: KeyboardMeaningLayout = [
         _____, _____, _____, DgInf, GHnkM, /****/ _____, _____, _____, _____, _____,
     ],
     [
-        _____, LDecl, _____, LRfrI, _____, /****/ _____, _____, _____, _____, _____,
+        LClIn, LDecl, LClOt, LRfrI, _____, /****/ _____, _____, _____, _____, _____,
     ],
 ];
 
@@ -123,19 +123,19 @@ pub(crate) const KEYMAP_SPACE: KeyboardMeaningLayout = [
         Theme, Symbl, Buffr, File_, GitFC, /****/ _____, LHovr, LCdAc, Pipe_, _____,
     ],
     [
-        UndoT, _____, _____, _____, TSNSx, /****/ _____, LRnme, _____, _____, _____,
+        UndoT, _____, _____, _____, TSNSx, /****/ _____, LRnme, LFmtD, LFmtS, _____,
     ],
 ];
 
 pub(crate) const KEYMAP_SPACE_SHIFTED: KeyboardMeaningLayout = [
     [
-        QNSav, _____, _____, _____, _____, /****/ _____, _____, _____, _____, _____,
+        QNSav, RldFl, TglCm, _____, _____, /****/ _____, _____, _____, _____, _____,
     ],
     [
-        _____, _____, _____, _____, GitFM, /****/ _____, _____, _____, _____, _____,
+        ThmVr, WSymb, _____, _____, GitFM, /****/ _____, _____, _____, _____, _____,
     ],
     [
-        _____, _____, _____, _____, _____, /****/ _____, _____, _____, _____, _____,
+        _____, _____, RegCp, RegPs, _____, /****/ _____, _____, _____, _____, _____,
     ],
 ];
 
@@ -153,7 +153,7 @@ pub(crate) const KEYMAP_SEARCH_CONFIG: KeyboardMeaningLayout = [
 
 pub(crate) const KEYMAP_TRANSFORM: KeyboardMeaningLayout = [
     [
-        _____, USnke, Pscal, _____, _____, /****/ _____, _____, UKbab, Upper, _____,
+        _____, USnke, Pscal, SortL, _____, /****/ _____, _____, UKbab, Upper, _____,
     ],
     [
         _____, Snke_, Camel, _____, _____, /****/ _____, Wrap_, Kbab_, Lower, Title,
@@ -676,10 +676,16 @@ pub(crate) enum Meaning {
     LRfrI,
     /// Lsp Type Definition
     LType,
+    /// Lsp Incoming Calls
+    LClIn,
+    /// Lsp Outgoing Calls
+    LClOt,
     /// Natural Number
     NtrlN,
     /// One Character
     OneCh,
+    /// Folding Range
+    Fold_,
     /// Parenthesis
     Paren,
     /// Curly Braces
@@ -698,6 +704,10 @@ pub(crate) enum Meaning {
     SHelp,
     /// Quit No Save
     QNSav,
+    /// Reload File
+    RldFl,
+    /// Toggle Line Comment
+    TglCm,
     /// Quit Save
     QSave,
     /// Save All
@@ -706,10 +716,18 @@ pub(crate) enum Meaning {
     Explr,
     /// LSP Rename
     LRnme,
+    /// LSP Format Document
+    LFmtD,
+    /// LSP Format Selection
+    LFmtS,
     /// Pick Theme
     Theme,
+    /// Toggle Light/Dark Theme
+    ThmVr,
     /// Pick Symbol
     Symbl,
+    /// Pick Workspace Symbol
+    WSymb,
     /// Pick File
     File_,
     /// Pick Git Status File (against current branch)
@@ -768,6 +786,8 @@ pub(crate) enum Meaning {
     Camel,
     /// Wrap
     Wrap_,
+    /// Sort Lines
+    SortL,
     /// kebab-case
     Kbab_,
     /// lower case
@@ -778,6 +798,10 @@ pub(crate) enum Meaning {
     No___,
     /// Pipe selection to shell
     Pipe_,
+    /// Copy to register
+    RegCp,
+    /// Paste from register
+    RegPs,
     /// Open matching files
     OpenM,
     /// Reveal selections
@@ -11,6 +11,7 @@ use crate::{
     context::{Context, LocalSearchConfigMode, Search},
     git::DiffMode,
     list::grep::RegexConfig,
+    lsp::call_hierarchy::CallDirection,
     quickfix_list::{DiagnosticSeverityRange, QuickfixListType},
     selection::SelectionMode,
     surround::EnclosureKind,
@@ -937,6 +938,13 @@ impl Editor {
                     "Wrap".to_string(),
                     Dispatch::ToEditor(Transform(Transformation::Wrap)),
                 )))
+                .chain(Some(Keymap::new(
+                    context
+                        .keyboard_layout_kind()
+                        .get_transform_key(&Meaning::SortL),
+                    "Sort Lines".to_string(),
+                    Dispatch::ToEditor(SortLines),
+                )))
                 .collect_vec(),
             )),
         }
@@ -998,6 +1006,13 @@ impl Editor {
                     "Symbol".to_string(),
                     Dispatch::RequestDocumentSymbols,
                 )))
+                .chain(Some(Keymap::new(
+                    context
+                        .keyboard_layout_kind()
+                        .get_space_keymap(&Meaning::WSymb),
+                    "Workspace Symbol".to_string(),
+                    Dispatch::OpenWorkspaceSymbolPrompt,
+                )))
                 .chain(Some(Keymap::new(
                     context
                         .keyboard_layout_kind()
@@ -1005,6 +1020,13 @@ impl Editor {
                     "Theme".to_string(),
                     Dispatch::OpenThemePrompt,
                 )))
+                .chain(Some(Keymap::new(
+                    context
+                        .keyboard_layout_kind()
+                        .get_space_keymap(&Meaning::ThmVr),
+                    "Toggle Light/Dark Theme".to_string(),
+                    Dispatch::ToggleThemeVariant,
+                )))
                 .chain(Some(Keymap::new(
                     context
                         .keyboard_layout_kind()
@@ -1057,6 +1079,20 @@ impl Editor {
                         "Quit No Save".to_string(),
                         Dispatch::QuitAll,
                     ),
+                    Keymap::new(
+                        context
+                            .keyboard_layout_kind()
+                            .get_space_keymap(&Meaning::RldFl),
+                        "Reload File".to_string(),
+                        Dispatch::ToEditor(DispatchEditor::ReloadFile),
+                    ),
+                    Keymap::new(
+                        context
+                            .keyboard_layout_kind()
+                            .get_space_keymap(&Meaning::TglCm),
+                        "Toggle Comment".to_string(),
+                        Dispatch::ToEditor(DispatchEditor::ToggleLineComment),
+                    ),
                     Keymap::new(
                         context
                             .keyboard_layout_kind()
@@ -1064,6 +1100,20 @@ impl Editor {
                         "Pipe".to_string(),
                         Dispatch::OpenPipeToShellPrompt,
                     ),
+                    Keymap::new(
+                        context
+                            .keyboard_layout_kind()
+                            .get_space_keymap(&Meaning::RegCp),
+                        "Copy to register".to_string(),
+                        Dispatch::OpenCopyToRegisterPrompt,
+                    ),
+                    Keymap::new(
+                        context
+                            .keyboard_layout_kind()
+                            .get_space_keymap(&Meaning::RegPs),
+                        "Paste from register".to_string(),
+                        Dispatch::OpenPasteFromRegisterPrompt,
+                    ),
                     Keymap::new(
                         context
                             .keyboard_layout_kind()
@@ -1101,6 +1151,20 @@ impl Editor {
                         "Rename".to_string(),
                         Dispatch::PrepareRename,
                     ),
+                    Keymap::new(
+                        context
+                            .keyboard_layout_kind()
+                            .get_space_keymap(&Meaning::LFmtD),
+                        "Format".to_string(),
+                        Dispatch::RequestFormatting,
+                    ),
+                    Keymap::new(
+                        context
+                            .keyboard_layout_kind()
+                            .get_space_keymap(&Meaning::LFmtS),
+                        "Format Selection".to_string(),
+                        Dispatch::RequestRangeFormatting,
+                    ),
                     Keymap::new(
                         context
                             .keyboard_layout_kind()
@@ -1338,6 +1402,26 @@ impl Editor {
                 "Type".to_string(),
                 Dispatch::RequestTypeDefinitions(scope),
             ),
+            Keymap::new(
+                context
+                    .keyboard_layout_kind()
+                    .get_find_keymap(scope, &Meaning::LClIn),
+                "Callers".to_string(),
+                Dispatch::RequestCalls {
+                    direction: CallDirection::Incoming,
+                    scope,
+                },
+            ),
+            Keymap::new(
+                context
+                    .keyboard_layout_kind()
+                    .get_find_keymap(scope, &Meaning::LClOt),
+                "Callees".to_string(),
+                Dispatch::RequestCalls {
+                    direction: CallDirection::Outgoing,
+                    scope,
+                },
+            ),
         ];
         let scope_specific_keymaps = match scope {
             Scope::Local => [(
@@ -1361,13 +1445,22 @@ impl Editor {
                     Dispatch::ToEditor(SetSelectionMode(if_current_not_found, Find { search }));
                 Keymap::new(key, description.to_string(), dispatch)
             })
-            .chain([Keymap::new(
-                context
-                    .keyboard_layout_kind()
-                    .get_find_keymap(scope, &Meaning::OneCh),
-                "One".to_string(),
-                Dispatch::ToEditor(FindOneChar(if_current_not_found)),
-            )])
+            .chain([
+                Keymap::new(
+                    context
+                        .keyboard_layout_kind()
+                        .get_find_keymap(scope, &Meaning::OneCh),
+                    "One".to_string(),
+                    Dispatch::ToEditor(FindOneChar(if_current_not_found)),
+                ),
+                Keymap::new(
+                    context
+                        .keyboard_layout_kind()
+                        .get_find_keymap(scope, &Meaning::Fold_),
+                    "Fold".to_string(),
+                    Dispatch::ToEditor(SetSelectionMode(if_current_not_found, Fold)),
+                ),
+            ])
             .collect_vec(),
             Scope::Global => [Keymap::new_extended(
                 context
@@ -49,6 +49,7 @@ pub(crate) enum PromptHistoryKey {
     MovePath,
     CopyFile,
     Symbol,
+    WorkspaceSymbol,
     OpenFile,
     FilterGlob(GlobalSearchFilterGlob),
     Replacement(Scope),
@@ -57,6 +58,7 @@ pub(crate) enum PromptHistoryKey {
     Null,
     Theme,
     PipeToShell,
+    Register,
     FilterSelectionsMatchingSearch {
         maintain: bool,
     },
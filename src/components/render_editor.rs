@@ -15,12 +15,14 @@ use crate::{
     context::Context,
     divide_viewport::{calculate_window_position, divide_viewport},
     format_path_list::get_formatted_paths,
-    grid::{CellUpdate, Grid, RenderContentLineNumber, StyleKey},
+    grid::{CellUpdate, Grid, IndexedHighlightGroup, RenderContentLineNumber, StyleKey},
+    lsp::document_highlight::DocumentHighlightKind,
     position::Position,
     selection::{CharIndex, Selection},
     selection_mode::{self, ByteRange},
     soft_wrap::wrap_items,
     style::Style,
+    syntax_highlight::HighlightedSpan,
     themes::{Theme, UiStyles},
     utils::trim_array,
 };
@@ -357,6 +359,36 @@ impl Editor {
         };
 
         let grid = {
+            // Inlay hints are rendered at the end of their line,
+            // so that they do not shift the positions of the actual content
+            let inlay_hint_style_key = crate::themes::highlight_names()
+                .iter()
+                .position(|name| *name == "comment")
+                .map(|index| StyleKey::Syntax(IndexedHighlightGroup::new(index)));
+            let mut inlay_hint_updates = Vec::new();
+            let visible_lines_content = visible_lines
+                .map(|(line_index, line)| {
+                    let Some(hints) = buffer.line_inlay_hints(line_index) else {
+                        return line;
+                    };
+                    let content = line.trim_end_matches('\n');
+                    let column = content.chars().count() + 1;
+                    inlay_hint_updates.extend((0..hints.chars().count()).map(|offset| {
+                        CellUpdate {
+                            position: Position::new(line_index, column + offset),
+                            symbol: None,
+                            style: inlay_hint_style_key
+                                .as_ref()
+                                .map(|style_key| theme.get_style(style_key))
+                                .unwrap_or_default(),
+                            is_cursor: false,
+                            is_protected_range_start: false,
+                            source: inlay_hint_style_key.clone(),
+                        }
+                    }));
+                    format!("{content} {hints}{}", &line[content.len()..])
+                })
+                .join("");
             let visible_lines_updates = {
                 let boundaries = [Boundary::new(&buffer, visible_line_range)];
                 highlight_spans
@@ -379,9 +411,9 @@ impl Editor {
                             })
                             .ok()
                     }))
+                    .chain(inlay_hint_updates)
                     .collect_vec()
             };
-            let visible_lines_content = visible_lines.map(|(_, line)| line).join("");
             let visible_lines_grid = visible_lines_grid.render_content(
                 &visible_lines_content,
                 if render_line_number {
@@ -691,6 +723,23 @@ impl Editor {
                 is_protected_range_start: false,
             });
 
+        let document_highlights =
+            buffer
+                .document_highlights()
+                .iter()
+                .map(|(range, kind)| HighlightSpan {
+                    set_symbol: None,
+                    is_cursor: false,
+                    range: HighlightSpanRange::CharIndexRange(*range),
+                    source: Source::StyleKey(match kind {
+                        DocumentHighlightKind::Write => UiDocumentHighlightWrite,
+                        DocumentHighlightKind::Text | DocumentHighlightKind::Read => {
+                            UiDocumentHighlightRead
+                        }
+                    }),
+                    is_protected_range_start: false,
+                });
+
         let jumps = self.jumps().into_iter().enumerate().map(|(index, jump)| {
             let style = if index % 2 == 0 {
                 theme.ui.jump_mark_even
@@ -725,8 +774,7 @@ impl Editor {
         let visible_line_byte_range = buffer
             .line_range_to_byte_range(visible_line_range)
             .unwrap_or_default();
-        let spans = buffer.highlighted_spans();
-        let filtered_highlighted_spans = {
+        let filter_highlighted_spans = |spans: &[HighlightedSpan]| {
             filter_items_by_range(
                 spans,
                 visible_line_byte_range.start,
@@ -749,7 +797,11 @@ impl Editor {
                 is_cursor: false,
                 is_protected_range_start: false,
             })
+            .collect_vec()
         };
+        let filtered_highlighted_spans = filter_highlighted_spans(buffer.highlighted_spans());
+        let filtered_semantic_highlighted_spans =
+            filter_highlighted_spans(buffer.semantic_highlighted_spans());
         let custom_regex_highlights = lazy_regex::regex!("(?i)#[0-9a-f]{6}")
             .find_iter(&content)
             .map(|m| (m.as_str().to_string(), m.range()))
@@ -812,7 +864,9 @@ impl Editor {
             .into_iter()
             .chain(visible_parent_lines)
             .chain(filtered_highlighted_spans)
+            .chain(filtered_semantic_highlighted_spans)
             .chain(extra_decorations)
+            .chain(document_highlights)
             .chain(possible_selections)
             .chain(primary_selection_highlight_span)
             .chain(secondary_selections_highlight_spans)
@@ -1817,8 +1817,9 @@ fn diagnostics_range_updated_by_edit() -> anyhow::Result<()> {
                 focus: true,
             }),
             Editor(SetContent("fn main() { let x = 123 }".trim().to_string())),
-            App(HandleLspNotification(LspNotification::PublishDiagnostics(
-                lsp_types::PublishDiagnosticsParams {
+            App(HandleLspNotification(LspNotification::PublishDiagnostics {
+                server_name: "rust-analyzer",
+                params: lsp_types::PublishDiagnosticsParams {
                     uri: s.main_rs().to_url().unwrap(),
                     diagnostics: [lsp_types::Diagnostic {
                         range: lsp_types::Range::new(
@@ -1836,7 +1837,7 @@ fn diagnostics_range_updated_by_edit() -> anyhow::Result<()> {
                     .to_vec(),
                     version: None,
                 },
-            ))),
+            })),
             Expect(ExpectKind::DiagnosticsRanges(
                 [CharIndexRange::from(CharIndex(3)..CharIndex(7))].to_vec(),
             )),
@@ -4488,6 +4489,50 @@ fn insert_multiwidth_unicode_characters() -> Result<(), anyhow::Error> {
     })
 }
 
+#[test]
+fn toggle_line_comment() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent(
+                "fn main() {\n    foo();\n    bar();\n}".to_string(),
+            )),
+            Editor(MatchLiteral("foo".to_string())),
+            Editor(ToggleLineComment),
+            Expect(CurrentComponentContent(
+                "fn main() {\n    // foo();\n    bar();\n}",
+            )),
+            Expect(CurrentSelectedTexts(&["foo"])),
+            Editor(ToggleLineComment),
+            Expect(CurrentComponentContent(
+                "fn main() {\n    foo();\n    bar();\n}",
+            )),
+        ])
+    })
+}
+
+#[test]
+fn sort_lines() -> Result<(), anyhow::Error> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("head\ncherry\napple\nbanana".to_string())),
+            Editor(MatchLiteral("cherry\napple\nbanana".to_string())),
+            Editor(SortLines),
+            Expect(CurrentComponentContent("head\napple\nbanana\ncherry")),
+            Expect(CurrentSelectedTexts(&["apple\nbanana\ncherry"])),
+        ])
+    })
+}
+
 #[test]
 fn go_to_line_number() -> Result<(), anyhow::Error> {
     execute_test(|s| {
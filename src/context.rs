@@ -1,20 +1,23 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path, path::PathBuf, time::Duration};
 
 use globset::Glob;
 
 use indexmap::IndexSet;
 use itertools::{Either, Itertools};
-use shared::canonicalized_path::CanonicalizedPath;
+use shared::{
+    canonicalized_path::CanonicalizedPath,
+    language::{Language, LanguageRegistry},
+};
 use strum::IntoEnumIterator;
 
 use crate::{
     app::{GlobalSearchConfigUpdate, GlobalSearchFilterGlob, LocalSearchConfigUpdate, Scope},
-    clipboard::{Clipboard, CopiedTexts},
+    clipboard::{Clipboard, ClipboardKind, CopiedTexts},
     components::{editor_keymap::KeyboardLayoutKind, prompt::PromptHistoryKey},
     list::grep::RegexConfig,
     quickfix_list::{DiagnosticSeverityRange, Location},
     selection::SelectionMode,
-    themes::Theme,
+    themes::{Theme, ThemeVariant},
 };
 
 pub(crate) struct Context {
@@ -34,8 +37,20 @@ pub(crate) struct Context {
     location_history_backward: Vec<Location>,
     location_history_forward: Vec<Location>,
     marked_paths: IndexSet<CanonicalizedPath>,
+    language_registry: LanguageRegistry,
+    max_undo_depth: Option<usize>,
+    lsp_request_timeout: Option<Duration>,
+    lsp_change_debounce: Option<Duration>,
+    /// The settings of the language servers of each language, see `LspManager::update_configuration`
+    lsp_settings: Vec<(Language, serde_json::Value)>,
+    max_prompt_history_len: usize,
+    /// Used in sessions without a clipboard backend, such as SSH sessions,
+    /// so that copying and pasting never wait for the system clipboard.
+    system_clipboard_disabled: bool,
 }
 
+const DEFAULT_MAX_PROMPT_HISTORY_LEN: usize = 100;
+
 pub(crate) struct QuickfixListState {
     pub(crate) title: String,
     pub(crate) source: QuickfixListSource,
@@ -60,7 +75,40 @@ impl GlobalMode {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+/// The search and replacement histories of every scope, persisted across sessions.
+/// They are kept apart because replacements rarely resemble the searches.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct SearchHistory {
+    #[serde(default)]
+    searches: HashMap<Scope, Vec<String>>,
+    #[serde(default)]
+    replacements: HashMap<Scope, Vec<String>>,
+}
+
+/// Where the search history is persisted across sessions.
+pub(crate) fn default_search_history_file() -> PathBuf {
+    grammar::cache_dir().join("search_history.json")
+}
+
+/// Where the named registers are persisted across sessions.
+pub(crate) fn default_registers_file() -> PathBuf {
+    grammar::cache_dir().join("registers.json")
+}
+
+/// Parses a JSON object of the language server settings of each file extension,
+/// for example `{ "rs": { "rust-analyzer": { "checkOnSave": false } } }`.
+fn parse_lsp_settings(content: &str) -> anyhow::Result<Vec<(Language, serde_json::Value)>> {
+    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(content)?
+        .into_iter()
+        .map(|(extension, settings)| {
+            let language = shared::language::from_extension(&extension)
+                .ok_or_else(|| anyhow::anyhow!("Unsupported file extension: {extension:?}"))?;
+            Ok((language, settings))
+        })
+        .collect()
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct Search {
     pub(crate) mode: LocalSearchConfigMode,
     pub(crate) search: String,
@@ -92,40 +140,211 @@ impl Default for Context {
             location_history_backward: Vec::new(),
             location_history_forward: Vec::new(),
             marked_paths: Default::default(),
+            language_registry: std::env::var("KI_EDITOR_FILE_EXTENSIONS")
+                .map(|mappings| {
+                    LanguageRegistry::parse(&mappings).unwrap_or_else(|error| {
+                        panic!("Please update your KI_EDITOR_FILE_EXTENSIONS environment variable: {error}")
+                    })
+                })
+                .unwrap_or_default(),
+            max_undo_depth: std::env::var("KI_EDITOR_MAX_UNDO_DEPTH").ok().map(|depth| {
+                depth.parse().unwrap_or_else(|error| {
+                    panic!("Please update your KI_EDITOR_MAX_UNDO_DEPTH environment variable: {error}")
+                })
+            }),
+            lsp_request_timeout: std::env::var("KI_EDITOR_LSP_REQUEST_TIMEOUT_MS").ok().map(|milliseconds| {
+                Duration::from_millis(milliseconds.parse().unwrap_or_else(|error| {
+                    panic!("Please update your KI_EDITOR_LSP_REQUEST_TIMEOUT_MS environment variable: {error}")
+                }))
+            }),
+            lsp_change_debounce: std::env::var("KI_EDITOR_LSP_CHANGE_DEBOUNCE_MS").ok().map(|milliseconds| {
+                Duration::from_millis(milliseconds.parse().unwrap_or_else(|error| {
+                    panic!("Please update your KI_EDITOR_LSP_CHANGE_DEBOUNCE_MS environment variable: {error}")
+                }))
+            }),
+            lsp_settings: std::env::var("KI_EDITOR_LSP_SETTINGS_FILE").ok().map(|path| {
+                std::fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| parse_lsp_settings(&content))
+                    .unwrap_or_else(|error| {
+                        panic!("Please update your KI_EDITOR_LSP_SETTINGS_FILE environment variable: {error}")
+                    })
+            }).unwrap_or_default(),
+            max_prompt_history_len: std::env::var("KI_EDITOR_MAX_PROMPT_HISTORY").ok().map(|len| {
+                len.parse().unwrap_or_else(|error| {
+                    panic!("Please update your KI_EDITOR_MAX_PROMPT_HISTORY environment variable: {error}")
+                })
+            }).unwrap_or(DEFAULT_MAX_PROMPT_HISTORY_LEN),
+            system_clipboard_disabled: std::env::var("KI_EDITOR_DISABLE_SYSTEM_CLIPBOARD").ok().map(|disabled| {
+                disabled.parse().unwrap_or_else(|error| {
+                    panic!("Please update your KI_EDITOR_DISABLE_SYSTEM_CLIPBOARD environment variable: {error}")
+                })
+            }).unwrap_or(false),
         }
     }
 }
 
 impl Context {
     pub(crate) fn new(current_working_directory: CanonicalizedPath) -> Self {
-        Self {
+        let mut context = Self {
             current_working_directory,
             ..Self::default()
+        };
+        if !cfg!(test) {
+            let path = default_search_history_file();
+            if path.exists() {
+                match Self::load_searches(&path) {
+                    Ok(searches) => context.restore_searches(searches),
+                    Err(error) => log::error!("Failed to load search history: {error:?}"),
+                }
+            }
+            // A corrupt file is ignored, so that the registers start empty
+            let path = default_registers_file();
+            if path.exists() {
+                match Self::load_registers(&path) {
+                    Ok(registers) => context.restore_registers(registers),
+                    Err(error) => log::error!("Failed to load registers: {error:?}"),
+                }
+            }
         }
+        context
+    }
+
+    /// Replaces the system clipboard, e.g. with an in-memory clipboard in tests.
+    #[cfg(test)]
+    pub(crate) fn with_clipboard(
+        mut self,
+        provider: impl crate::clipboard::ClipboardProvider + 'static,
+    ) -> Self {
+        self.clipboard.set_system_clipboard(Box::new(provider));
+        self
     }
 
-    /// Note: `history_offset` is ignored when `use_system_clipboard` is true.
+    /// Note: `history_offset` is ignored when the system clipboard is used.
+    ///
+    /// Falls back to the in-memory clipboard if the system clipboard is unavailable.
     pub(crate) fn get_clipboard_content(
         &self,
         use_system_clipboard: bool,
         history_offset: isize,
     ) -> anyhow::Result<Option<CopiedTexts>> {
-        Ok(if use_system_clipboard {
-            Some(CopiedTexts::new(nonempty::NonEmpty::singleton(
-                self.clipboard.get_from_system_clipboard()?,
-            )))
-        } else {
-            self.clipboard.get(history_offset)
-        })
+        if use_system_clipboard && !self.system_clipboard_disabled {
+            match self.clipboard.get_from_system_clipboard() {
+                Ok(content) => {
+                    // The system clipboard has no notion of kind, so the kind is
+                    // recovered from the in-memory clipboard if the content is unchanged
+                    let kind = self
+                        .clipboard
+                        .get(0)
+                        .filter(|copied_texts| copied_texts.join("\n") == content)
+                        .map(|copied_texts| copied_texts.kind())
+                        .unwrap_or_default();
+                    return Ok(Some(
+                        CopiedTexts::new(nonempty::NonEmpty::singleton(content)).with_kind(kind),
+                    ));
+                }
+                Err(error) => log::error!("Failed to read the system clipboard: {error:?}"),
+            }
+        }
+        Ok(self.clipboard.get(history_offset))
     }
 
+    /// The in-memory clipboard is updated even if the system clipboard is unavailable.
     pub(crate) fn set_clipboard_content(
         &mut self,
         contents: CopiedTexts,
         use_system_clipboard: bool,
     ) -> anyhow::Result<()> {
-        self.clipboard.set(contents.clone(), use_system_clipboard)
+        if let Err(error) = self.clipboard.set(
+            contents,
+            use_system_clipboard && !self.system_clipboard_disabled,
+        ) {
+            log::error!("Failed to write to the system clipboard: {error:?}")
+        }
+        Ok(())
+    }
+
+    /// Stores a rectangle of text, one row per line, as `ClipboardKind::BlockWise` copied texts.
+    ///
+    /// The rows are joined by newlines in the system clipboard.
+    #[allow(dead_code)]
+    pub(crate) fn set_block_clipboard(
+        &mut self,
+        rows: Vec<String>,
+        use_system_clipboard: bool,
+    ) -> anyhow::Result<()> {
+        let rows = nonempty::NonEmpty::from_vec(rows)
+            .ok_or_else(|| anyhow::anyhow!("A block must have at least one row"))?;
+        self.set_clipboard_content(
+            CopiedTexts::new(rows).with_kind(ClipboardKind::BlockWise),
+            use_system_clipboard,
+        )
+    }
+
+    /// Returns the rows of the latest copied texts, or `None` if they are not block-wise.
+    #[allow(dead_code)]
+    pub(crate) fn get_block_clipboard(
+        &self,
+        use_system_clipboard: bool,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        Ok(self
+            .get_clipboard_content(use_system_clipboard, 0)?
+            .filter(|copied_texts| copied_texts.kind() == ClipboardKind::BlockWise)
+            .map(|copied_texts| {
+                copied_texts
+                    .join("\n")
+                    .split('\n')
+                    .map(|row| row.to_string())
+                    .collect()
+            }))
+    }
+
+    /// Ordered from the oldest to the newest, the newest is synced with the system clipboard.
+    #[allow(dead_code)]
+    pub(crate) fn clipboard_history(&self) -> &[CopiedTexts] {
+        self.clipboard.history()
+    }
+
+    /// See `Clipboard::cycle_paste`.
+    #[allow(dead_code)]
+    pub(crate) fn cycle_paste(&mut self) -> Option<CopiedTexts> {
+        self.clipboard.cycle_paste()
+    }
+
+    /// Unlike the clipboard content, named registers are never synced with the system clipboard.
+    pub(crate) fn set_register(&mut self, name: char, content: String) {
+        self.clipboard.set_register(name, content)
+    }
+
+    pub(crate) fn get_register(&self, name: char) -> Option<String> {
+        self.clipboard.get_register(name)
+    }
+
+    /// Only named registers are saved, the unnamed register (which is synced with
+    /// the system clipboard) is not.
+    pub(crate) fn save_registers(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(
+            path,
+            serde_json::to_string(self.clipboard.registers())?,
+        )?)
+    }
+
+    pub(crate) fn load_registers(path: &Path) -> anyhow::Result<HashMap<char, String>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
     }
+
+    /// Registers that are already set in the current session are kept.
+    fn restore_registers(&mut self, registers: HashMap<char, String>) {
+        for (name, content) in registers {
+            if self.clipboard.get_register(name).is_none() {
+                self.clipboard.set_register(name, content)
+            }
+        }
+    }
+
     pub(crate) fn mode(&self) -> Option<GlobalMode> {
         self.mode.clone()
     }
@@ -144,6 +363,18 @@ impl Context {
         Self { theme, ..self }
     }
 
+    /// Switches to the default theme of the other variant, keeping the color depth.
+    pub(crate) fn toggle_theme_variant(&mut self) {
+        let theme = match self.theme.variant() {
+            ThemeVariant::Light => Theme::dark(),
+            ThemeVariant::Dark => Theme::light(),
+        };
+        self.theme = Theme {
+            color_depth: self.theme.color_depth,
+            ..theme
+        };
+    }
+
     #[cfg(test)]
     pub(crate) fn highlight(
         &mut self,
@@ -233,18 +464,15 @@ impl Context {
         })
     }
 
+    /// Repeating an entry moves it to the end,
+    /// and the oldest entries are dropped once the history is full.
     pub(crate) fn push_history_prompt(&mut self, key: PromptHistoryKey, line: String) {
-        if let Some(map) = self.prompt_histories.get_mut(&key) {
-            map.shift_remove(&line);
-            let inserted = map.insert(line);
-            debug_assert!(inserted);
-        } else {
-            self.prompt_histories.insert(key, {
-                let mut set = IndexSet::new();
-                set.insert(line);
-                set
-            });
-        }
+        let history = self.prompt_histories.entry(key).or_default();
+        history.shift_remove(&line);
+        let inserted = history.insert(line);
+        debug_assert!(inserted);
+        let excess = history.len().saturating_sub(self.max_prompt_history_len);
+        history.drain(..excess);
     }
 
     pub(crate) fn get_prompt_history(
@@ -263,6 +491,55 @@ impl Context {
             .collect_vec()
     }
 
+    /// Entries are ordered from the oldest to the newest.
+    pub(crate) fn save_searches(&self, path: &Path) -> anyhow::Result<()> {
+        let history = |kind: fn(Scope) -> PromptHistoryKey| {
+            Scope::iter()
+                .filter_map(|scope| {
+                    let history = self.prompt_histories.get(&kind(scope))?;
+                    Some((scope, history.iter().cloned().collect_vec()))
+                })
+                .collect()
+        };
+        let search_history = SearchHistory {
+            searches: history(PromptHistoryKey::Search),
+            replacements: history(PromptHistoryKey::Replacement),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(
+            path,
+            serde_json::to_string(&search_history)?,
+        )?)
+    }
+
+    pub(crate) fn load_searches(path: &Path) -> anyhow::Result<SearchHistory> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// The restored entries are older than the entries of the current session.
+    fn restore_searches(&mut self, search_history: SearchHistory) {
+        let SearchHistory {
+            searches,
+            replacements,
+        } = search_history;
+        let histories = searches
+            .into_iter()
+            .map(|(scope, history)| (PromptHistoryKey::Search(scope), history))
+            .chain(
+                replacements
+                    .into_iter()
+                    .map(|(scope, history)| (PromptHistoryKey::Replacement(scope), history)),
+            );
+        for (key, history) in histories {
+            let current = self.prompt_histories.remove(&key).unwrap_or_default();
+            for line in history.into_iter().chain(current) {
+                self.push_history_prompt(key, line)
+            }
+        }
+    }
+
     pub(crate) fn set_last_non_contiguous_selection_mode(
         &mut self,
         selection_mode: Either<crate::selection::SelectionMode, GlobalMode>,
@@ -301,6 +578,26 @@ impl Context {
         self.location_history_forward.pop()
     }
 
+    pub(crate) fn language_registry(&self) -> &LanguageRegistry {
+        &self.language_registry
+    }
+
+    pub(crate) fn max_undo_depth(&self) -> Option<usize> {
+        self.max_undo_depth
+    }
+
+    pub(crate) fn lsp_request_timeout(&self) -> Option<Duration> {
+        self.lsp_request_timeout
+    }
+
+    pub(crate) fn lsp_change_debounce(&self) -> Option<Duration> {
+        self.lsp_change_debounce
+    }
+
+    pub(crate) fn lsp_settings(&self) -> &[(Language, serde_json::Value)] {
+        &self.lsp_settings
+    }
+
     pub(crate) fn get_marked_paths(&self) -> Vec<&CanonicalizedPath> {
         self.marked_paths.iter().collect()
     }
@@ -372,10 +669,13 @@ impl GlobalSearchConfig {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
 pub(crate) enum LocalSearchConfigMode {
+    /// Literal searches are regexes with `escaped` set.
     Regex(RegexConfig),
+    /// Matches syntax trees, so case sensitivity and whole word matching do not apply.
     AstGrep,
+    /// Always case insensitive, as it matches every casing of the search.
     NamingConventionAgnostic,
 }
 impl LocalSearchConfigMode {
@@ -474,3 +774,189 @@ impl LocalSearchConfig {
         self.mode.display()
     }
 }
+
+#[cfg(test)]
+mod test_context {
+    use super::*;
+    use crate::clipboard::InMemoryClipboardProvider;
+
+    #[test]
+    fn named_registers_are_restored_unless_set_in_the_current_session() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut context = Context::default();
+        context.set_register('a', "foo".to_string());
+        context.set_register('b', "bar".to_string());
+        context
+            .set_clipboard_content(CopiedTexts::one("unnamed".to_string()), false)
+            .unwrap();
+        context.save_registers(file.path()).unwrap();
+
+        let registers = Context::load_registers(file.path()).unwrap();
+        assert_eq!(
+            registers,
+            HashMap::from([('a', "foo".to_string()), ('b', "bar".to_string())])
+        );
+
+        let mut context = Context::default();
+        context.set_register('b', "spam".to_string());
+        context.restore_registers(registers);
+        assert_eq!(context.get_register('a'), Some("foo".to_string()));
+        assert_eq!(context.get_register('b'), Some("spam".to_string()));
+    }
+
+    #[test]
+    fn injected_clipboard_is_used_as_the_system_clipboard() {
+        let mut context = Context::default().with_clipboard(InMemoryClipboardProvider::default());
+        context
+            .set_clipboard_content(CopiedTexts::one("foo".to_string()), false)
+            .unwrap();
+        // Nothing is copied to the system clipboard yet
+        assert_eq!(
+            context.get_clipboard_content(true, 0).unwrap(),
+            Some(CopiedTexts::one("foo".to_string()))
+        );
+
+        context
+            .set_clipboard_content(
+                CopiedTexts::new(nonempty::NonEmpty {
+                    head: "bar".to_string(),
+                    tail: vec!["baz".to_string()],
+                })
+                .with_kind(ClipboardKind::LineWise),
+                true,
+            )
+            .unwrap();
+        assert_eq!(
+            context.get_clipboard_content(true, 0).unwrap(),
+            Some(CopiedTexts::one("bar\nbaz".to_string()).with_kind(ClipboardKind::LineWise))
+        );
+    }
+
+    #[test]
+    fn block_clipboard_keeps_every_row() {
+        let mut context = Context {
+            system_clipboard_disabled: true,
+            ..Context::default()
+        };
+        assert!(context.set_block_clipboard(Vec::new(), false).is_err());
+        assert_eq!(context.get_block_clipboard(false).unwrap(), None);
+
+        let rows = vec!["ab".to_string(), "".to_string(), "cd".to_string()];
+        context.set_block_clipboard(rows.clone(), true).unwrap();
+        assert_eq!(context.get_block_clipboard(true).unwrap(), Some(rows));
+
+        context
+            .set_clipboard_content(CopiedTexts::one("ef".to_string()), false)
+            .unwrap();
+        assert_eq!(context.get_block_clipboard(false).unwrap(), None);
+    }
+
+    #[test]
+    fn lsp_settings_are_keyed_by_file_extension() -> anyhow::Result<()> {
+        let settings =
+            parse_lsp_settings(r#"{ "rs": { "rust-analyzer": { "checkOnSave": false } } }"#)?;
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].0.tree_sitter_grammar_id().unwrap(), "rust");
+        assert_eq!(
+            settings[0].1,
+            serde_json::json!({ "rust-analyzer": { "checkOnSave": false } })
+        );
+
+        assert!(parse_lsp_settings(r#"{ "nope": {} }"#).is_err());
+        assert!(parse_lsp_settings("[]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn toggle_theme_variant_switches_between_light_and_dark() {
+        let mut context = Context::default().set_theme(Theme::light());
+        assert_eq!(context.theme().variant(), ThemeVariant::Light);
+        context.toggle_theme_variant();
+        assert_eq!(context.theme(), &Theme::dark());
+        assert_eq!(context.theme().variant(), ThemeVariant::Dark);
+        context.toggle_theme_variant();
+        assert_eq!(context.theme(), &Theme::light());
+    }
+
+    #[test]
+    fn corrupt_registers_file_fails_to_load() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "{ not json").unwrap();
+        assert!(Context::load_registers(file.path()).is_err());
+    }
+
+    #[test]
+    fn search_history_is_restored_before_the_searches_of_the_current_session() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let local = PromptHistoryKey::Search(Scope::Local);
+        let mut context = Context::default();
+        for search in ["foo", "bar", "foo"] {
+            context.push_history_prompt(local, search.to_string());
+        }
+        context.push_history_prompt(PromptHistoryKey::Rename, "baz".to_string());
+        context.push_history_prompt(
+            PromptHistoryKey::Replacement(Scope::Global),
+            "qux".to_string(),
+        );
+        context.save_searches(file.path()).unwrap();
+
+        let searches = Context::load_searches(file.path()).unwrap();
+        // Repeating a search moves it to the end, and other prompts are not saved
+        assert_eq!(
+            searches,
+            SearchHistory {
+                searches: HashMap::from([(
+                    Scope::Local,
+                    vec!["bar".to_string(), "foo".to_string()]
+                )]),
+                replacements: HashMap::from([(Scope::Global, vec!["qux".to_string()])]),
+            }
+        );
+
+        let mut context = Context::default();
+        context.push_history_prompt(local, "bar".to_string());
+        context.push_history_prompt(local, "spam".to_string());
+        context.restore_searches(searches);
+        assert_eq!(
+            context.get_prompt_history(local, None),
+            ["foo", "bar", "spam"]
+        );
+        assert_eq!(
+            context.get_prompt_history(PromptHistoryKey::Replacement(Scope::Global), None),
+            ["qux"]
+        );
+    }
+
+    #[test]
+    fn disabled_system_clipboard_falls_back_to_the_in_memory_clipboard() {
+        let mut context = Context {
+            system_clipboard_disabled: true,
+            ..Context::default()
+        };
+        let copied_texts = CopiedTexts::one("hello".to_string());
+        context
+            .set_clipboard_content(copied_texts.clone(), true)
+            .unwrap();
+        assert_eq!(
+            context.get_clipboard_content(true, 0).unwrap(),
+            Some(copied_texts)
+        );
+    }
+
+    #[test]
+    fn prompt_history_is_deduplicated_and_capped() {
+        let key = PromptHistoryKey::Search(Scope::Global);
+        let mut context = Context {
+            max_prompt_history_len: 2,
+            ..Context::default()
+        };
+        context.push_history_prompt(key, "foo".to_string());
+        context.push_history_prompt(key, "foo".to_string());
+        assert_eq!(context.get_prompt_history(key, None), ["foo"]);
+
+        context.push_history_prompt(key, "bar".to_string());
+        context.push_history_prompt(key, "foo".to_string());
+        context.push_history_prompt(key, "spam".to_string());
+        assert_eq!(context.get_prompt_history(key, None), ["foo", "spam"]);
+    }
+}
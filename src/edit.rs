@@ -134,6 +134,14 @@ impl EditTransaction {
             .collect_vec()
     }
 
+    /// The ranges occupied by the new content of each edit, after this transaction is applied.
+    pub(crate) fn edited_ranges(&self) -> Vec<CharIndexRange> {
+        self.edits()
+            .into_iter()
+            .map(|edit| (edit.range.start..edit.range.start + edit.new.len_chars()).into())
+            .collect_vec()
+    }
+
     pub(crate) fn from_action_groups(action_groups: Vec<ActionGroup>) -> Self {
         Self {
             action_group: Self::normalize_action_groups(action_groups),
@@ -197,6 +205,77 @@ impl EditTransaction {
         )
     }
 
+    /// Combine `self` and `other` into one transaction, where `other` is
+    /// expressed in terms of the content after `self` is applied.
+    ///
+    /// The edits are applied sequentially, so the ranges of `other` need no further rebasing.
+    /// Only the selections of `other` are kept, because the selections of `self`
+    /// refer to the intermediate content.
+    #[allow(dead_code)]
+    pub(crate) fn compose(self, other: EditTransaction) -> EditTransaction {
+        let other_has_selections = !other.selections().is_empty();
+        EditTransaction {
+            action_group: ActionGroup::new(
+                self.action_group
+                    .actions
+                    .into_iter()
+                    .filter(|action| !(other_has_selections && matches!(action, Action::Select(_))))
+                    .chain(other.action_group.actions)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Maps `char_index` of the content before this transaction
+    /// to the corresponding char index after it.
+    ///
+    /// A char index within an edited range is kept at the same distance from the start of the edit,
+    /// but no further than the end of the new content.
+    pub(crate) fn map_char_index(&self, char_index: CharIndex) -> CharIndex {
+        self.edits()
+            .into_iter()
+            .fold(char_index, |char_index, edit| {
+                if char_index >= edit.range.end {
+                    char_index.apply_offset(edit.chars_offset())
+                } else if char_index > edit.range.start {
+                    let distance = (char_index.0 - edit.range.start.0).min(edit.new.len_chars());
+                    edit.range.start + distance
+                } else {
+                    char_index
+                }
+            })
+    }
+
+    /// Check that every edit falls within the content it is applied to,
+    /// so that applying this transaction cannot panic.
+    ///
+    /// Because the edits are applied sequentially, each edit is checked against
+    /// the length of the content after the preceding edits.
+    pub(crate) fn validate(&self, buffer_len: usize) -> anyhow::Result<()> {
+        self.edits()
+            .into_iter()
+            .enumerate()
+            .try_fold(buffer_len, |len, (index, edit)| {
+                let CharIndexRange { start, end } = edit.range;
+                if start > end {
+                    anyhow::bail!(
+                        "Edit {index} has a start ({}) greater than its end ({})",
+                        start.0,
+                        end.0
+                    )
+                }
+                if end.0 > len {
+                    anyhow::bail!(
+                        "Edit {index} has the range {}..{}, which exceeds the content length of {len}",
+                        start.0,
+                        end.0
+                    )
+                }
+                Ok(len - edit.range.len() + edit.new.len_chars())
+            })
+            .map(|_| ())
+    }
+
     pub(crate) fn selections(&self) -> Vec<&Selection> {
         self.action_group
             .actions
@@ -407,6 +486,26 @@ mod test_normalize_actions {
         assert_eq!(result, Rope::from_str("What see in two pineapple"));
     }
 
+    #[test]
+    /// Insertions from multiple cursors should land at their original positions,
+    /// regardless of how earlier insertions shift the text after them
+    fn multi_cursor_insertions() {
+        let edit_transaction = EditTransaction::from_tuples(vec![
+            ActionGroup::new(vec![Action::edit(15, "", "big "), Action::select(15..19)]),
+            ActionGroup::new(vec![Action::edit(0, "", "So, "), Action::select(0..4)]),
+            ActionGroup::new(vec![Action::edit(10, "", "only "), Action::select(10..15)]),
+        ]);
+
+        let (selections, result) =
+            edit_transaction.apply_to(Rope::from_str("Who lives in a pineapple"));
+
+        assert_eq!(
+            result,
+            Rope::from_str("So, Who lives only in a big pineapple")
+        );
+        assert_eq!(selections, vec!["So, ", "only ", "big "]);
+    }
+
     #[test]
     fn intersected_edits_removed_1() {
         let edit_transaction = EditTransaction::from_tuples(vec![
@@ -600,4 +699,40 @@ mod test_inverse_edit_transaction {
 
         apply_and_verify(&original_rope, &edit_transaction);
     }
+
+    #[test]
+    fn test_compose_insertion_with_subsequent_deletion() {
+        let original_rope = Rope::from_str("Hello");
+        let insertion = EditTransaction::from_tuples(vec![ActionGroup::new(vec![Action::edit(
+            5, "", " World",
+        )])]);
+        // This deletion is expressed in terms of "Hello World"
+        let deletion = EditTransaction::from_tuples(vec![ActionGroup::new(vec![
+            Action::edit(0, "Hello ", ""),
+            Action::select(0..5),
+        ])]);
+        let edit_transaction = insertion.compose(deletion);
+
+        let (selections, modified_rope) = edit_transaction.apply_to(original_rope.clone());
+        assert_eq!(modified_rope.to_string(), "World");
+        assert_eq!(selections, vec!["World".to_string()]);
+
+        apply_and_verify(&original_rope, &edit_transaction);
+    }
+
+    #[test]
+    fn test_compose_edits_that_shift_each_other() {
+        let original_rope = Rope::from_str("ABC DEF");
+        let first =
+            EditTransaction::from_tuples(vec![ActionGroup::new(vec![Action::edit(0, "ABC", "A")])]);
+        // "A DEF" -> "A XYZ DEF"
+        let second =
+            EditTransaction::from_tuples(vec![ActionGroup::new(vec![Action::edit(2, "", "XYZ ")])]);
+        let edit_transaction = first.compose(second);
+
+        let (_, modified_rope) = edit_transaction.apply_to(original_rope.clone());
+        assert_eq!(modified_rope.to_string(), "A XYZ DEF");
+
+        apply_and_verify(&original_rope, &edit_transaction);
+    }
 }
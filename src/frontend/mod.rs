@@ -7,7 +7,9 @@ use std::io::Write;
 #[cfg(test)]
 use std::io::{self};
 
-use crate::{app::Dimension, components::component::Cursor, screen::Screen};
+use crate::{
+    app::Dimension, components::component::Cursor, screen::Screen, style::Style, themes::Theme,
+};
 use ::crossterm::{
     cursor::MoveTo,
     queue,
@@ -32,7 +34,8 @@ pub(crate) trait Frontend {
     fn writer(&mut self) -> &mut Box<dyn MyWriter>;
     fn previous_screen(&mut self) -> Screen;
     fn set_previous_screen(&mut self, previous_screen: Screen);
-    fn render_screen(&mut self, mut screen: Screen) -> anyhow::Result<()> {
+    /// The colors of the cells are resolved against the color depth of `theme`.
+    fn render_screen(&mut self, mut screen: Screen, theme: &Theme) -> anyhow::Result<()> {
         let cells = {
             // Only perform diff if the dimension is the same
             let mut previous_screen = self.previous_screen();
@@ -58,22 +61,31 @@ pub(crate) trait Frontend {
  multi-width characters in terminal displays"
         );
         for cell in cells {
+            let style = theme.resolve(
+                Style {
+                    foreground_color: Some(cell.cell.foreground_color),
+                    background_color: Some(cell.cell.background_color),
+                    line: cell.cell.line,
+                    is_bold: cell.cell.is_bold,
+                },
+                theme.color_depth,
+            );
             queue!(
                 self.writer(),
                 MoveTo(cell.position.column as u16, cell.position.line as u16),
-                SetAttribute(if cell.cell.is_bold {
+                SetAttribute(if style.is_bold {
                     Attribute::Bold
                 } else {
                     Attribute::NoBold
                 }),
                 SetUnderlineColor(
-                    cell.cell
+                    style
                         .line
-                        .map(|line| line.color.into())
+                        .map(|line| line.color.quantize(theme.color_depth))
                         .unwrap_or(Color::Reset),
                 ),
                 SetAttribute(
-                    cell.cell
+                    style
                         .line
                         .map(|line| match line.style {
                             crate::grid::CellLineStyle::Undercurl => Attribute::Undercurled,
@@ -81,8 +93,8 @@ pub(crate) trait Frontend {
                         })
                         .unwrap_or(Attribute::NoUnderline),
                 ),
-                SetBackgroundColor(cell.cell.background_color.into()),
-                SetForegroundColor(cell.cell.foreground_color.into()),
+                SetBackgroundColor(style.background_color.unwrap_or(Color::Reset)),
+                SetForegroundColor(style.foreground_color.unwrap_or(Color::Reset)),
                 Print(reveal(cell.cell.symbol)),
                 SetAttribute(Attribute::Reset),
             )?;
@@ -159,3 +171,42 @@ impl Write for NullWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test_frontend {
+    use super::*;
+    use crate::{
+        frontend::mock::MockFrontend, grid::Grid, position::Position, rectangle::Rectangle,
+        screen::Window, themes::ColorDepth,
+    };
+
+    #[test]
+    fn colors_are_down_sampled_to_the_color_depth_of_the_theme() -> anyhow::Result<()> {
+        let render = |color_depth| -> anyhow::Result<String> {
+            let mut frontend = MockFrontend::new(Box::new(StringWriter::new()));
+            let window = Window::new(
+                Grid::new(Dimension {
+                    height: 1,
+                    width: 1,
+                }),
+                Rectangle {
+                    origin: Position::default(),
+                    width: 1,
+                    height: 1,
+                },
+            );
+            let screen = Screen::new(vec![window], Vec::new(), None, Style::default());
+            let theme = Theme {
+                color_depth,
+                ..Theme::dark()
+            };
+            frontend.render_screen(screen, &theme)?;
+            Ok(frontend.string_content().unwrap_or_default())
+        };
+        assert!(render(ColorDepth::TrueColor)?.contains("\x1b[48;2;"));
+        let output = render(ColorDepth::Ansi16)?;
+        assert!(output.contains("\x1b[48;5;"));
+        assert!(!output.contains("\x1b[48;2;"));
+        Ok(())
+    }
+}
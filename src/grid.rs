@@ -665,6 +665,8 @@ pub(crate) enum StyleKey {
     DiagnosticsInformation,
     UiMark,
     UiPossibleSelection,
+    UiDocumentHighlightRead,
+    UiDocumentHighlightWrite,
 
     DiagnosticsDefault,
     HunkOld,
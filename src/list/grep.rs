@@ -11,7 +11,7 @@ use shared::canonicalized_path::CanonicalizedPath;
 
 use super::WalkBuilderConfig;
 
-#[derive(Clone, Debug, PartialEq, Eq, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
 pub(crate) struct RegexConfig {
     pub(crate) escaped: bool,
     pub(crate) case_sensitive: bool,
@@ -41,7 +41,8 @@ pub(crate) fn replace(
     Ok(walk_builder_config
         .run(Box::new(move |path, sender| {
             let path = path.try_into()?;
-            let mut buffer = Buffer::from_path(&path, local_search_config.require_tree_sitter())?;
+            let mut buffer =
+                Buffer::from_path(&path, local_search_config.require_tree_sitter(), None)?;
             let (modified, _) =
                 buffer.replace(local_search_config.clone(), Default::default(), 0)?;
             if modified {
@@ -69,7 +70,7 @@ pub(crate) fn run(
     Ok(walk_builder_config
         .run(Box::new(move |path, sender| {
             let path = path.try_into()?;
-            let buffer = Buffer::from_path(&path, false)?;
+            let buffer = Buffer::from_path(&path, false, None)?;
             // Tree-sitter should be disabled whenever possible during
             // global search, because it will slow down the operation tremendously
             debug_assert!(buffer.tree().is_none());
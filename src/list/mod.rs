@@ -27,7 +27,7 @@ impl WalkBuilderConfig {
     ) -> anyhow::Result<Vec<Location>> {
         self.run(Box::new(move |path, sender| {
             let path = path.try_into()?;
-            let buffer = Buffer::from_path(&path, enable_tree_sitter)?;
+            let buffer = Buffer::from_path(&path, enable_tree_sitter, None)?;
             // Tree-sitter should be disabled whenever possible during
             // global search, because it will slow down the operation tremendously
             if !enable_tree_sitter {
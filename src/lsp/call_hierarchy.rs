@@ -0,0 +1,153 @@
+use shared::canonicalized_path::CanonicalizedPath;
+
+use crate::{
+    components::suggestive_editor::Info,
+    quickfix_list::{Location, QuickfixListItem},
+};
+
+/// Whether the callers or the callees of an item are requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CallDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// An item returned by `textDocument/prepareCallHierarchy`,
+/// which is the starting point of incoming and outgoing call requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CallHierarchyItem {
+    pub(crate) name: String,
+    /// The range of the name of the item
+    pub(crate) location: Location,
+    /// Sent back as-is when requesting the calls of this item,
+    /// because it might carry server-specific `data`
+    pub(crate) item: lsp_types::CallHierarchyItem,
+}
+
+impl TryFrom<lsp_types::CallHierarchyItem> for CallHierarchyItem {
+    type Error = anyhow::Error;
+
+    fn try_from(value: lsp_types::CallHierarchyItem) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: value.name.clone(),
+            location: lsp_types::Location::new(value.uri.clone(), value.selection_range)
+                .try_into()?,
+            item: value,
+        })
+    }
+}
+
+/// A caller (of incoming calls) or a callee (of outgoing calls) of an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CallHierarchyCall {
+    pub(crate) item: CallHierarchyItem,
+    /// Where the calls happen, which are always in the file of the caller
+    pub(crate) call_sites: Vec<Location>,
+}
+
+impl CallHierarchyCall {
+    pub(crate) fn try_from_incoming(
+        value: lsp_types::CallHierarchyIncomingCall,
+    ) -> anyhow::Result<Self> {
+        let caller_uri = value.from.uri.clone();
+        Ok(Self {
+            item: value.from.try_into()?,
+            call_sites: value
+                .from_ranges
+                .into_iter()
+                .map(|range| lsp_types::Location::new(caller_uri.clone(), range).try_into())
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+
+    /// Unlike incoming calls, the ranges of outgoing calls are in the file of `caller_path`,
+    /// which is the item that the calls are requested for.
+    pub(crate) fn try_from_outgoing(
+        value: lsp_types::CallHierarchyOutgoingCall,
+        caller_path: &CanonicalizedPath,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            item: value.to.try_into()?,
+            call_sites: value
+                .from_ranges
+                .into_iter()
+                .map(|range| Location {
+                    path: caller_path.clone(),
+                    range: range.start.into()..range.end.into(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Every call site becomes an item, which is labelled with the name of the caller or callee.
+    pub(crate) fn into_quickfix_list_items(self) -> Vec<QuickfixListItem> {
+        let name = self.item.name;
+        self.call_sites
+            .into_iter()
+            .map(|location| {
+                QuickfixListItem::new(
+                    location,
+                    Some(Info::new("Call hierarchy".to_string(), name.clone())),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_call_hierarchy {
+    use super::*;
+    use crate::position::Position;
+
+    fn item(name: &str, path: &CanonicalizedPath) -> lsp_types::CallHierarchyItem {
+        lsp_types::CallHierarchyItem {
+            name: name.to_string(),
+            kind: lsp_types::SymbolKind::FUNCTION,
+            tags: None,
+            detail: None,
+            uri: lsp_types::Url::from_file_path(path.display_absolute()).unwrap(),
+            range: lsp_types::Range::new(
+                lsp_types::Position::new(0, 0),
+                lsp_types::Position::new(2, 1),
+            ),
+            selection_range: lsp_types::Range::new(
+                lsp_types::Position::new(0, 3),
+                lsp_types::Position::new(0, 6),
+            ),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn call_sites_are_in_the_file_of_the_caller() -> anyhow::Result<()> {
+        let caller_path: CanonicalizedPath = "Cargo.toml".try_into()?;
+        let callee_path: CanonicalizedPath = "readme.md".try_into()?;
+        let from_ranges = vec![lsp_types::Range::new(
+            lsp_types::Position::new(1, 4),
+            lsp_types::Position::new(1, 7),
+        )];
+        let call_site = Location {
+            path: caller_path.clone(),
+            range: Position::new(1, 4)..Position::new(1, 7),
+        };
+
+        let incoming =
+            CallHierarchyCall::try_from_incoming(lsp_types::CallHierarchyIncomingCall {
+                from: item("caller", &caller_path),
+                from_ranges: from_ranges.clone(),
+            })?;
+        assert_eq!(incoming.item.name, "caller");
+        assert_eq!(incoming.call_sites, vec![call_site.clone()]);
+
+        let outgoing = CallHierarchyCall::try_from_outgoing(
+            lsp_types::CallHierarchyOutgoingCall {
+                to: item("callee", &callee_path),
+                from_ranges,
+            },
+            &caller_path,
+        )?;
+        assert_eq!(outgoing.item.location.path, callee_path);
+        assert_eq!(outgoing.call_sites, vec![call_site]);
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 use itertools::Itertools;
 
-use crate::{app::Dispatch, components::dropdown::DropdownItem};
+use crate::{
+    app::{Dispatch, Dispatches},
+    components::dropdown::DropdownItem,
+};
 
 use super::workspace_edit::WorkspaceEdit;
 
@@ -11,8 +14,48 @@ pub(crate) struct CodeAction {
     pub(crate) kind: Option<String>,
     pub(crate) edit: Option<WorkspaceEdit>,
     pub(crate) command: Option<Command>,
+    /// Present if the server deferred computing the edit of this code action
+    /// until it is chosen, which is done via `codeAction/resolve`.
+    pub(crate) unresolved: Option<UnresolvedCodeAction>,
 }
 
+impl CodeAction {
+    /// The dispatches that apply this code action,
+    /// regardless of whether it is resolved.
+    pub(crate) fn dispatches(self) -> Dispatches {
+        self.edit
+            .map(Dispatch::ApplyWorkspaceEdit)
+            .into_iter()
+            // A command this code action executes. If a code action
+            // provides an edit and a command, first the edit is
+            // executed and then the command.
+            // Refer https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#codeAction
+            .chain(
+                self.command
+                    .map(|command| Dispatch::LspExecuteCommand { command }),
+            )
+            .collect_vec()
+            .into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct UnresolvedCodeAction(lsp_types::CodeAction);
+
+impl UnresolvedCodeAction {
+    pub(crate) fn into_inner(self) -> lsp_types::CodeAction {
+        self.0
+    }
+}
+
+impl PartialEq for UnresolvedCodeAction {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.title.eq(&other.0.title) && self.0.data.eq(&other.0.data)
+    }
+}
+
+impl Eq for UnresolvedCodeAction {}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Command(lsp_types::Command);
 impl Command {
@@ -48,23 +91,11 @@ impl From<CodeAction> for DropdownItem {
                     .and_then(|kind| if kind.is_empty() { None } else { Some(kind) })
                     .unwrap_or("Misc.".to_string()),
             ))
-            .set_dispatches(
-                value
-                    .edit
-                    .map(Dispatch::ApplyWorkspaceEdit)
-                    .into_iter()
-                    // A command this code action executes. If a code action
-                    // provides an edit and a command, first the edit is
-                    // executed and then the command.
-                    // Refer https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#codeAction
-                    .chain(
-                        value
-                            .command
-                            .map(|command| Dispatch::LspExecuteCommand { command }),
-                    )
-                    .collect_vec()
-                    .into(),
-            )
+            .set_dispatches(if value.unresolved.is_some() {
+                Dispatches::one(Dispatch::ResolveCodeAction(value))
+            } else {
+                value.dispatches()
+            })
     }
 }
 
@@ -86,12 +117,30 @@ impl TryFrom<lsp_types::CodeAction> for CodeAction {
     fn try_from(value: lsp_types::CodeAction) -> Result<Self, Self::Error> {
         log::info!("CodeAction: {:#?}", value);
 
+        let unresolved = if value.edit.is_none() && value.data.is_some() {
+            Some(UnresolvedCodeAction(value.clone()))
+        } else {
+            None
+        };
         let title = value.title;
         Ok(CodeAction {
             title,
             kind: value.kind.map(|kind| kind.as_str().to_string()),
             edit: value.edit.map(WorkspaceEdit::try_from).transpose()?,
             command: value.command.map(Command),
+            unresolved,
         })
     }
 }
+
+impl From<lsp_types::Command> for CodeAction {
+    fn from(value: lsp_types::Command) -> Self {
+        CodeAction {
+            title: value.title.clone(),
+            kind: None,
+            edit: None,
+            command: Some(Command(value)),
+            unresolved: None,
+        }
+    }
+}
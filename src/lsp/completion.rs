@@ -18,6 +18,21 @@ pub(crate) struct Completion {
     pub(crate) trigger_characters: Vec<String>,
 }
 
+impl Completion {
+    /// The items of `other` come after the items of `self`.
+    pub(crate) fn merge(self, other: Completion) -> Completion {
+        Completion {
+            items: self.items.into_iter().chain(other.items).collect(),
+            trigger_characters: self
+                .trigger_characters
+                .into_iter()
+                .chain(other.trigger_characters)
+                .unique()
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct CompletionItem {
     pub(crate) label: String,
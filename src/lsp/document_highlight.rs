@@ -0,0 +1,56 @@
+use std::ops::Range;
+
+use crate::position::Position;
+
+/// How an occurrence of the symbol under the cursor is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum DocumentHighlightKind {
+    /// A textual occurrence, which is the default if the language server omits the kind
+    #[default]
+    Text,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DocumentHighlight {
+    pub(crate) range: Range<Position>,
+    pub(crate) kind: DocumentHighlightKind,
+}
+
+impl From<lsp_types::DocumentHighlight> for DocumentHighlight {
+    fn from(value: lsp_types::DocumentHighlight) -> Self {
+        Self {
+            range: value.range.start.into()..value.range.end.into(),
+            kind: match value.kind {
+                Some(lsp_types::DocumentHighlightKind::READ) => DocumentHighlightKind::Read,
+                Some(lsp_types::DocumentHighlightKind::WRITE) => DocumentHighlightKind::Write,
+                _ => DocumentHighlightKind::Text,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_document_highlight {
+    use super::*;
+
+    #[test]
+    fn missing_kind_defaults_to_text() {
+        let highlight = |kind| {
+            DocumentHighlight::from(lsp_types::DocumentHighlight {
+                range: lsp_types::Range::new(
+                    lsp_types::Position::new(0, 4),
+                    lsp_types::Position::new(0, 7),
+                ),
+                kind,
+            })
+            .kind
+        };
+        assert_eq!(highlight(None), DocumentHighlightKind::Text);
+        assert_eq!(
+            highlight(Some(lsp_types::DocumentHighlightKind::WRITE)),
+            DocumentHighlightKind::Write
+        );
+    }
+}
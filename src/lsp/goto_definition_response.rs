@@ -1,3 +1,5 @@
+use itertools::Itertools;
+
 use crate::quickfix_list::Location;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -28,3 +30,33 @@ impl TryFrom<lsp_types::GotoDefinitionResponse> for GotoDefinitionResponse {
         }
     }
 }
+
+impl GotoDefinitionResponse {
+    /// Locations that are returned by multiple language servers are only kept once.
+    pub(crate) fn merge(self, other: GotoDefinitionResponse) -> GotoDefinitionResponse {
+        let mut locations = self
+            .into_locations()
+            .into_iter()
+            .chain(other.into_locations())
+            .unique_by(|location| {
+                (
+                    location.path.clone(),
+                    location.range.start,
+                    location.range.end,
+                )
+            })
+            .collect_vec();
+        if locations.len() == 1 {
+            GotoDefinitionResponse::Single(locations.remove(0))
+        } else {
+            GotoDefinitionResponse::Multiple(locations)
+        }
+    }
+
+    fn into_locations(self) -> Vec<Location> {
+        match self {
+            GotoDefinitionResponse::Single(location) => vec![location],
+            GotoDefinitionResponse::Multiple(locations) => locations,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use itertools::Itertools;
+
+use crate::position::Position;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InlayHint {
+    pub(crate) position: Position,
+    pub(crate) label: String,
+    pub(crate) padding_left: bool,
+    pub(crate) padding_right: bool,
+}
+
+impl From<lsp_types::InlayHint> for InlayHint {
+    fn from(value: lsp_types::InlayHint) -> Self {
+        Self {
+            position: value.position.into(),
+            label: match value.label {
+                lsp_types::InlayHintLabel::String(label) => label,
+                lsp_types::InlayHintLabel::LabelParts(parts) => {
+                    parts.into_iter().map(|part| part.value).join("")
+                }
+            },
+            padding_left: value.padding_left.unwrap_or(false),
+            padding_right: value.padding_right.unwrap_or(false),
+        }
+    }
+}
+
+impl InlayHint {
+    /// The label surrounded by the padding requested by the language server.
+    pub(crate) fn display(&self) -> String {
+        format!(
+            "{}{}{}",
+            if self.padding_left { " " } else { "" },
+            self.label,
+            if self.padding_right { " " } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_inlay_hint {
+    use super::*;
+
+    #[test]
+    fn label_parts_are_joined_and_padded() {
+        let hint: InlayHint = lsp_types::InlayHint {
+            position: lsp_types::Position::new(0, 5),
+            label: lsp_types::InlayHintLabel::LabelParts(vec![
+                lsp_types::InlayHintLabelPart {
+                    value: ": ".to_string(),
+                    ..Default::default()
+                },
+                lsp_types::InlayHintLabelPart {
+                    value: "Vec<u8>".to_string(),
+                    ..Default::default()
+                },
+            ]),
+            kind: Some(lsp_types::InlayHintKind::TYPE),
+            text_edits: None,
+            tooltip: None,
+            padding_left: None,
+            padding_right: Some(true),
+            data: None,
+        }
+        .into();
+        assert_eq!(hint.display(), ": Vec<u8> ");
+    }
+}
@@ -1,17 +1,57 @@
-use std::{collections::HashMap, sync::mpsc::Sender};
+use std::{
+    collections::HashMap,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
 
-use crate::app::AppMessage;
+use crate::{
+    app::{AppMessage, RequestParams, Scope},
+    utils::consolidate_errors,
+};
+use itertools::Itertools;
+use lsp_types::FileChangeType;
 
-use super::process::{FromEditor, LspServerProcessChannel};
+use super::{
+    call_hierarchy::CallHierarchyItem,
+    process::{
+        Configurations, FromEditor, LspNotification, LspServerProcessChannel, RequestKind,
+        ResponseContext, DEFAULT_CHANGE_DEBOUNCE, DEFAULT_REQUEST_TIMEOUT,
+    },
+    progress::Progresses,
+    server_capability::ServerCapability,
+    symbols::Symbols,
+};
 use shared::{
     canonicalized_path::CanonicalizedPath,
-    language::{self, Language, LanguageId},
+    language::{self, Language, LanguageId, LspCommand},
 };
 
 pub(crate) struct LspManager {
-    lsp_server_process_channels: HashMap<LanguageId, LspServerProcessChannel>,
+    /// A language can be served by multiple language servers,
+    /// the main language server always comes first.
+    lsp_server_process_channels: HashMap<LanguageId, Vec<LspServerProcessChannel>>,
     sender: Sender<AppMessage>,
     current_working_directory: CanonicalizedPath,
+    request_timeout: Duration,
+    change_debounce: Duration,
+    /// The number of language servers that have yet to respond
+    /// to the latest workspace symbol request, and the symbols received so far.
+    pending_workspace_symbols: Option<(usize, Symbols)>,
+    /// Likewise, for requests that are sent to multiple language servers of a document,
+    /// see `LspManager::receive_response`.
+    pending_responses: HashMap<MergedRequest, (usize, Option<LspNotification>)>,
+    /// Language servers that exited unexpectedly, keyed by language and server name.
+    /// They are restarted on the next request of their language
+    /// once their backoff elapses.
+    crashed_servers: HashMap<(LanguageId, &'static str), CrashedServer>,
+    /// Language servers that failed to spawn (e.g. not installed), keyed by language and server name,
+    /// with the reason. They are not spawned again until `LspManager::retry_language` is called.
+    unavailable_servers: HashMap<(LanguageId, &'static str), String>,
+    progresses: Progresses,
+    /// The latest settings of each language, which are also sent
+    /// to language servers that are spawned afterwards
+    /// Shared with the server processes, which reply `workspace/configuration` requests from it
+    configurations: Configurations,
     #[cfg(test)]
     /// Used for testing the correctness of LSP requests
     /// We use HashMap instead of Vec because we only one to store the latest
@@ -19,6 +59,133 @@ pub(crate) struct LspManager {
     history: HashMap</* request name */ &'static str, FromEditor>,
 }
 
+/// Requests whose responses are merged when a document has multiple language servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MergedRequest {
+    /// Including declarations, implementations and type definitions,
+    /// which are delivered as definitions too
+    Definition,
+    References,
+    Completion,
+}
+
+impl MergedRequest {
+    fn of_message(from_editor: &FromEditor) -> Option<(MergedRequest, ServerCapability)> {
+        match from_editor {
+            FromEditor::TextDocumentDefinition(_)
+            | FromEditor::TextDocumentDeclaration(_)
+            | FromEditor::TextDocumentImplementation(_)
+            | FromEditor::TextDocumentTypeDefinition(_) => Some((
+                MergedRequest::Definition,
+                from_editor.required_capability()?,
+            )),
+            FromEditor::TextDocumentReferences { .. } => {
+                Some((MergedRequest::References, ServerCapability::References))
+            }
+            FromEditor::TextDocumentCompletion(_) => {
+                Some((MergedRequest::Completion, ServerCapability::Completion))
+            }
+            _ => None,
+        }
+    }
+
+    fn of_method(method: &str) -> Option<MergedRequest> {
+        match method {
+            "textDocument/definition"
+            | "textDocument/declaration"
+            | "textDocument/implementation"
+            | "textDocument/typeDefinition" => Some(MergedRequest::Definition),
+            "textDocument/references" => Some(MergedRequest::References),
+            "textDocument/completion" => Some(MergedRequest::Completion),
+            _ => None,
+        }
+    }
+
+    fn of_notification(notification: &LspNotification) -> Option<MergedRequest> {
+        match notification {
+            LspNotification::Definition(..) => Some(MergedRequest::Definition),
+            LspNotification::References(..) => Some(MergedRequest::References),
+            LspNotification::Completion(..) => Some(MergedRequest::Completion),
+            _ => None,
+        }
+    }
+}
+
+/// The context of the response that arrived first is kept.
+fn merge_responses(merged: LspNotification, other: LspNotification) -> LspNotification {
+    match (merged, other) {
+        (LspNotification::Definition(context, merged), LspNotification::Definition(_, other)) => {
+            LspNotification::Definition(context, merged.merge(other))
+        }
+        (LspNotification::References(context, merged), LspNotification::References(_, other)) => {
+            LspNotification::References(
+                context,
+                merged
+                    .into_iter()
+                    .chain(other)
+                    .unique_by(|location| {
+                        (
+                            location.path.clone(),
+                            location.range.start,
+                            location.range.end,
+                        )
+                    })
+                    .collect(),
+            )
+        }
+        (LspNotification::Completion(context, merged), LspNotification::Completion(_, other)) => {
+            LspNotification::Completion(context, merged.merge(other))
+        }
+        (merged, _) => merged,
+    }
+}
+
+struct CrashedServer {
+    language: Language,
+    /// Consecutive crashes before the server got initialized
+    crash_count: u32,
+    exited_at: Instant,
+}
+
+/// The lifecycle of a language server, see `LspManager::running_languages`.
+///
+/// There is no spawning state, because a server process is spawned synchronously.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ServerStatus {
+    /// Spawned, but has yet to respond to the `initialize` request
+    Initializing,
+    Ready,
+    /// Exited unexpectedly, and will be restarted on the next request of its language
+    Crashed,
+}
+
+impl ServerStatus {
+    fn display(&self) -> &'static str {
+        match self {
+            ServerStatus::Initializing => "initializing",
+            ServerStatus::Ready => "ready",
+            ServerStatus::Crashed => "crashed",
+        }
+    }
+}
+
+impl CrashedServer {
+    const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// Doubles for every consecutive crash, so that a server that crashes
+    /// on startup is not respawned in a tight loop.
+    fn backoff(&self) -> Duration {
+        Self::INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.crash_count.saturating_sub(1)))
+            .min(Self::MAX_BACKOFF)
+    }
+
+    fn can_restart(&self) -> bool {
+        self.exited_at.elapsed() >= self.backoff()
+    }
+}
+
 impl Drop for LspManager {
     fn drop(&mut self) {
         self.shutdown()
@@ -34,23 +201,79 @@ impl LspManager {
             lsp_server_process_channels: HashMap::new(),
             sender,
             current_working_directory,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            change_debounce: DEFAULT_CHANGE_DEBOUNCE,
+            pending_workspace_symbols: None,
+            pending_responses: HashMap::new(),
+            crashed_servers: HashMap::new(),
+            unavailable_servers: HashMap::new(),
+            progresses: Progresses::default(),
+            configurations: Configurations::default(),
             #[cfg(test)]
             history: Default::default(),
         }
     }
 
+    /// Requests that are not responded within `timeout` are cancelled,
+    /// and reported via `LspNotification::RequestTimedOut`.
+    pub(crate) fn set_request_timeout(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        self.request_timeout = timeout;
+        self.lsp_server_process_channels
+            .values()
+            .flatten()
+            .try_for_each(|channel| channel.set_request_timeout(timeout))
+    }
+
+    /// Changes of the same document within `debounce` are coalesced,
+    /// so that only the latest content is sent to the language servers.
+    ///
+    /// Pending changes are always sent before any other message,
+    /// so that requests and saves are handled against the latest content.
+    pub(crate) fn set_change_debounce(&mut self, debounce: Duration) -> anyhow::Result<()> {
+        self.change_debounce = debounce;
+        self.lsp_server_process_channels
+            .values()
+            .flatten()
+            .try_for_each(|channel| channel.set_change_debounce(debounce))
+    }
+
+    fn channels(&self, path: &CanonicalizedPath) -> &[LspServerProcessChannel] {
+        language::from_path(path)
+            .and_then(|language| self.lsp_server_process_channels.get(&language.id()?))
+            .map(|channels| channels.as_slice())
+            .unwrap_or_default()
+    }
+
     fn invoke_channels(
         &self,
         path: &CanonicalizedPath,
-        _error: &str,
+        error: &str,
         f: impl Fn(&LspServerProcessChannel) -> anyhow::Result<()>,
     ) -> anyhow::Result<()> {
-        language::from_path(path)
-            .and_then(|language| self.lsp_server_process_channels.get(&language.id()?))
-            .map(f)
-            .unwrap_or_else(|| Ok(()))
+        consolidate_errors(error, self.channels(path).iter().map(f).collect())
+    }
+
+    /// Returns false if none of the language servers of `path` is initialized
+    /// and advertises `capability`.
+    pub(crate) fn supports(&self, path: &CanonicalizedPath, capability: ServerCapability) -> bool {
+        self.channels(path)
+            .iter()
+            .any(|channel| channel.supports(capability))
+    }
+
+    /// The kinds of the requests of `path` that are not responded yet,
+    /// sorted and without duplicates across language servers.
+    pub(crate) fn pending_request_kinds(&self, path: &CanonicalizedPath) -> Vec<RequestKind> {
+        self.channels(path)
+            .iter()
+            .flat_map(|channel| channel.pending_request_kinds(path))
+            .sorted()
+            .dedup()
+            .collect()
     }
 
+    /// The message is sent to every language server of `path`,
+    /// except servers that are known to not support it.
     pub(crate) fn send_message(
         &mut self,
         path: CanonicalizedPath,
@@ -60,17 +283,246 @@ impl LspManager {
         self.history
             .insert(from_editor.variant(), from_editor.clone());
 
-        self.invoke_channels(
+        if let Some(language) = language::from_path(&path) {
+            self.restart_crashed_servers(&language)?;
+        }
+
+        let capability = from_editor.required_capability();
+        if let Some(capability) = capability {
+            let channels = self.channels(&path);
+            if !channels.is_empty()
+                && channels.iter().all(|channel| channel.is_initialized())
+                && !self.supports(&path, capability)
+            {
+                return Err(anyhow::anyhow!(
+                    "The language server does not support {}",
+                    capability.description()
+                ));
+            }
+        }
+
+        let merged_request = MergedRequest::of_message(&from_editor);
+        if let Some((request, capability)) = merged_request {
+            // Only the servers that support the request respond to it
+            let servers_count = self
+                .channels(&path)
+                .iter()
+                .filter(|channel| channel.supports(capability))
+                .count();
+            // The responses of the previous request, if any, are superseded
+            self.pending_responses.remove(&request);
+            if servers_count > 1 {
+                self.pending_responses
+                    .insert(request, (servers_count, None));
+            }
+        }
+
+        let result = self.invoke_channels(
             &path,
             &format!("Failed to send message '{}'", from_editor.variant()),
-            |channel| channel.send_from_editor(from_editor.clone()),
+            |channel| match capability {
+                Some(capability) if channel.is_initialized() && !channel.supports(capability) => {
+                    Ok(())
+                }
+                _ => channel.send_from_editor(from_editor.clone()),
+            },
+        );
+        if result.is_err() {
+            if let Some((request, _)) = merged_request {
+                self.pending_responses.remove(&request);
+            }
+            self.remove_exited_channels();
+        }
+        result
+    }
+
+    /// Completion items are usually returned without documentation,
+    /// which is requested lazily for the focused item only.
+    ///
+    /// The resolved item is delivered via `LspNotification::CompletionItemResolve`.
+    pub(crate) fn resolve_completion_item(
+        &mut self,
+        params: RequestParams,
+        completion_item: lsp_types::CompletionItem,
+    ) -> anyhow::Result<()> {
+        self.send_message(
+            params.path.clone(),
+            FromEditor::CompletionItemResolve {
+                completion_item,
+                params,
+            },
+        )
+    }
+
+    /// The highlights are delivered via `LspNotification::DocumentHighlights`,
+    /// nothing is requested if the language servers of the file do not support them.
+    pub(crate) fn request_document_highlight(
+        &mut self,
+        params: RequestParams,
+    ) -> anyhow::Result<()> {
+        if !self.supports(&params.path, ServerCapability::DocumentHighlight) {
+            return Ok(());
+        }
+        self.send_message(
+            params.path.clone(),
+            FromEditor::TextDocumentDocumentHighlight(params),
+        )
+    }
+
+    /// The items are delivered via `LspNotification::CallHierarchyItems`,
+    /// which are then passed to `LspManager::incoming_calls` or `LspManager::outgoing_calls`.
+    pub(crate) fn prepare_call_hierarchy(&mut self, params: RequestParams) -> anyhow::Result<()> {
+        let params = params.set_description("Call hierarchy");
+        self.send_message(
+            params.path.clone(),
+            FromEditor::TextDocumentPrepareCallHierarchy(params),
+        )
+    }
+
+    /// The callers of `item` are delivered via `LspNotification::CallHierarchyCalls`.
+    pub(crate) fn incoming_calls(
+        &mut self,
+        item: CallHierarchyItem,
+        scope: Option<Scope>,
+    ) -> anyhow::Result<()> {
+        let params = Self::call_hierarchy_request_params(&item, "Incoming calls").set_kind(scope);
+        self.send_message(
+            params.path.clone(),
+            FromEditor::CallHierarchyIncomingCalls {
+                params,
+                item: item.item,
+            },
+        )
+    }
+
+    /// The callees of `item` are delivered via `LspNotification::CallHierarchyCalls`.
+    pub(crate) fn outgoing_calls(
+        &mut self,
+        item: CallHierarchyItem,
+        scope: Option<Scope>,
+    ) -> anyhow::Result<()> {
+        let params = Self::call_hierarchy_request_params(&item, "Outgoing calls").set_kind(scope);
+        self.send_message(
+            params.path.clone(),
+            FromEditor::CallHierarchyOutgoingCalls {
+                params,
+                item: item.item,
+            },
+        )
+    }
+
+    /// Requests of an item are routed to the language servers of the file of the item.
+    fn call_hierarchy_request_params(item: &CallHierarchyItem, description: &str) -> RequestParams {
+        RequestParams {
+            path: item.location.path.clone(),
+            position: item.location.range.start,
+            context: ResponseContext::default(),
+        }
+        .set_description(description)
+    }
+
+    /// Unlike other requests, workspace symbol requests are not tied to a file,
+    /// thus they are broadcasted to every running language server
+    /// that supports them.
+    pub(crate) fn request_workspace_symbols(&mut self, query: String) -> anyhow::Result<()> {
+        let from_editor = FromEditor::WorkspaceSymbol { query };
+        #[cfg(test)]
+        self.history
+            .insert(from_editor.variant(), from_editor.clone());
+
+        let channels = self
+            .lsp_server_process_channels
+            .values()
+            .flatten()
+            .filter(|channel| channel.supports(ServerCapability::WorkspaceSymbol))
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No running language server supports {}",
+                ServerCapability::WorkspaceSymbol.description()
+            ));
+        }
+        self.pending_workspace_symbols = Some((channels.len(), Symbols::default()));
+        consolidate_errors(
+            "Failed to send message 'WorkspaceSymbol'",
+            channels
+                .into_iter()
+                .map(|channel| channel.send_from_editor(from_editor.clone()))
+                .collect(),
         )
     }
 
+    /// `symbols` is `None` if the language server failed to respond in time.
+    ///
+    /// Returns the merged symbols of every language server once all of them
+    /// have responded.
+    pub(crate) fn receive_workspace_symbols(
+        &mut self,
+        symbols: Option<Symbols>,
+    ) -> Option<Symbols> {
+        let (remaining, merged) = self.pending_workspace_symbols.take()?;
+        let merged = match symbols {
+            Some(symbols) => merged.merge(symbols),
+            None => merged,
+        };
+        let remaining = remaining.saturating_sub(1);
+        if remaining == 0 {
+            Some(merged)
+        } else {
+            self.pending_workspace_symbols = Some((remaining, merged));
+            None
+        }
+    }
+
+    /// Responses of a request that is sent to multiple language servers of a document
+    /// are held back until every server has responded, and then merged into one.
+    ///
+    /// Returns the notification that is ready to be handled, if any.
+    pub(crate) fn receive_response(
+        &mut self,
+        notification: LspNotification,
+    ) -> Option<LspNotification> {
+        let Some(request) = MergedRequest::of_notification(&notification) else {
+            return Some(notification);
+        };
+        self.receive_merged_response(request, Some(notification))
+    }
+
+    /// Called when a language server failed to respond to `method`,
+    /// so that the responses of the other servers are not held back forever.
+    ///
+    /// Returns the merged responses of the other servers once all of them have responded.
+    pub(crate) fn receive_missing_response(&mut self, method: &str) -> Option<LspNotification> {
+        let request = MergedRequest::of_method(method)?;
+        self.receive_merged_response(request, None)
+    }
+
+    fn receive_merged_response(
+        &mut self,
+        request: MergedRequest,
+        notification: Option<LspNotification>,
+    ) -> Option<LspNotification> {
+        // Not merged, because only one server was requested
+        let Some((remaining, merged)) = self.pending_responses.remove(&request) else {
+            return notification;
+        };
+        let merged = match (merged, notification) {
+            (Some(merged), Some(notification)) => Some(merge_responses(merged, notification)),
+            (merged, notification) => merged.or(notification),
+        };
+        let remaining = remaining.saturating_sub(1);
+        if remaining == 0 {
+            merged
+        } else {
+            self.pending_responses.insert(request, (remaining, merged));
+            None
+        }
+    }
+
     /// Open file can do one of the following:
-    /// 1. Start a new LSP server process if it is not started yet.
-    /// 2. Notify the LSP server process that a new file is opened.
-    /// 3. Do nothing if the LSP server process is spawned but not yet initialized.
+    /// 1. Start the LSP server processes if they are not started yet.
+    /// 2. Notify the LSP server processes that a new file is opened.
+    /// 3. Do nothing for LSP server processes that are spawned but not yet initialized.
     pub(crate) fn open_file(&mut self, path: CanonicalizedPath) -> Result<(), anyhow::Error> {
         let Some(language) = language::from_path(&path) else {
             return Ok(());
@@ -79,47 +531,451 @@ impl LspManager {
             return Ok(());
         };
 
-        if let Some(channel) = self.lsp_server_process_channels.get(&language_id) {
-            if channel.is_initialized() {
-                channel.document_did_open(path.clone())
-            } else {
-                Ok(())
-            }
-        } else {
-            LspServerProcessChannel::new(
-                language.clone(),
-                self.sender.clone(),
-                self.current_working_directory.clone(),
+        self.restart_crashed_servers(&language)?;
+
+        if let Some(channels) = self.lsp_server_process_channels.get_mut(&language_id) {
+            consolidate_errors(
+                "[open_file]",
+                channels
+                    .iter_mut()
+                    .filter(|channel| channel.is_initialized())
+                    .map(|channel| channel.document_did_open(path.clone()))
+                    .collect(),
             )
-            .map(|channel| {
-                if let Some(channel) = channel {
-                    self.lsp_server_process_channels
-                        .insert(language.id()?, channel);
+        } else {
+            let mut channels = Vec::new();
+            for lsp_command in language.lsp_commands() {
+                if let Some(channel) = self.spawn_channel(&language, lsp_command)? {
+                    channels.push(channel);
                 }
-                Some(())
-            })?;
+            }
+            if !channels.is_empty() {
+                self.lsp_server_process_channels
+                    .insert(language_id, channels);
+            }
             Ok(())
         }
     }
 
+    /// Notify the LSP server processes of files that are changed on disk
+    /// by something other than the editor, such as a global replace.
+    ///
+    /// Each language server is only notified of the files of its language.
+    pub(crate) fn notify_files_changed(
+        &self,
+        changes: Vec<(CanonicalizedPath, FileChangeType)>,
+    ) -> anyhow::Result<()> {
+        consolidate_errors(
+            "[notify_files_changed]",
+            changes
+                .into_iter()
+                .filter_map(|(path, change_type)| {
+                    Some((language::from_path(&path)?.id()?, (path, change_type)))
+                })
+                .into_group_map()
+                .into_iter()
+                .flat_map(|(language_id, changes)| {
+                    self.lsp_server_process_channels
+                        .get(&language_id)
+                        .into_iter()
+                        .flatten()
+                        .map(move |channel| {
+                            channel.send_from_editor(FromEditor::WorkspaceDidChangeWatchedFiles {
+                                changes: changes.clone(),
+                            })
+                        })
+                })
+                .collect(),
+        )
+    }
+
+    /// Sends `workspace/didChangeConfiguration` to the language servers of `language`.
+    pub(crate) fn update_configuration(
+        &mut self,
+        language: Language,
+        settings: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let Some(language_id) = language.id() else {
+            return Ok(());
+        };
+        self.configurations
+            .lock()
+            .unwrap()
+            .insert(language_id.clone(), settings.clone());
+        let from_editor = FromEditor::WorkspaceDidChangeConfiguration { settings };
+        #[cfg(test)]
+        self.history
+            .insert(from_editor.variant(), from_editor.clone());
+        consolidate_errors(
+            "[update_configuration]",
+            self.lsp_server_process_channels
+                .get(&language_id)
+                .into_iter()
+                .flatten()
+                .filter(|channel| channel.is_initialized())
+                .map(|channel| channel.send_from_editor(from_editor.clone()))
+                .collect(),
+        )
+    }
+
+    /// Notify the LSP server processes that the file is closed,
+    /// so that the servers can stop tracking it.
+    ///
+    /// The servers are shut down once they have no opened documents left,
+    /// and are spawned again by the next `open_file` of their language.
+    pub(crate) fn close_file(&mut self, path: CanonicalizedPath) -> anyhow::Result<()> {
+        #[cfg(test)]
+        {
+            let from_editor = FromEditor::TextDocumentDidClose {
+                file_path: path.clone(),
+            };
+            self.history.insert(from_editor.variant(), from_editor);
+        }
+
+        let Some(language) = language::from_path(&path) else {
+            return Ok(());
+        };
+        let Some(channels) = language
+            .id()
+            .and_then(|language_id| self.lsp_server_process_channels.get_mut(&language_id))
+        else {
+            return Ok(());
+        };
+        consolidate_errors(
+            "[close_file]",
+            channels
+                .iter_mut()
+                .map(|channel| channel.document_did_close(&path))
+                .collect(),
+        )?;
+        // The documents opened before a server is initialized are only sent to it
+        // once it is initialized, so an uninitialized server might not be idle
+        if channels
+            .iter()
+            .all(|channel| channel.is_initialized() && channel.opened_documents_count() == 0)
+        {
+            self.shutdown_language(&language)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn initialized(
         &mut self,
         language: Language,
+        server_name: &'static str,
+        capabilities: lsp_types::ServerCapabilities,
         opened_documents: Vec<CanonicalizedPath>,
-    ) {
+    ) -> anyhow::Result<()> {
+        let Some(language_id) = language.id() else {
+            return Ok(());
+        };
+        let Some(channel) = self
+            .lsp_server_process_channels
+            .get_mut(&language_id)
+            .and_then(|channels| {
+                channels
+                    .iter_mut()
+                    .find(|channel| channel.server_name() == server_name)
+            })
+        else {
+            return Ok(());
+        };
+        channel.initialized(capabilities);
+        let settings = self
+            .configurations
+            .lock()
+            .unwrap()
+            .get(&language_id)
+            .cloned();
+        if let Some(settings) = settings {
+            channel.send_from_editor(FromEditor::WorkspaceDidChangeConfiguration { settings })?;
+        }
+        channel.documents_did_open(opened_documents)
+    }
+
+    /// Called when the process of a language server exited unexpectedly.
+    ///
+    /// The server is restarted on the next request of `language`,
+    /// and the opened documents are reopened once it is initialized.
+    pub(crate) fn server_exited(&mut self, language: &Language, process_id: u32) {
+        let Some(language_id) = language.id() else {
+            return;
+        };
+        let Some(channels) = self.lsp_server_process_channels.get_mut(&language_id) else {
+            return;
+        };
+        // The process might have been shut down on purpose, or already restarted
+        let Some(index) = channels
+            .iter()
+            .position(|channel| channel.process_id() == process_id)
+        else {
+            return;
+        };
+        let channel = channels.remove(index);
+        self.record_crash(language_id, &channel)
+    }
+
+    /// Removes the channels whose message processor loop has stopped,
+    /// which happens when their server process exited.
+    fn remove_exited_channels(&mut self) {
+        let exited_channels = self
+            .lsp_server_process_channels
+            .iter_mut()
+            .flat_map(|(language_id, channels)| {
+                let (exited, running) = std::mem::take(channels)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|channel| channel.has_exited());
+                *channels = running;
+                exited
+                    .into_iter()
+                    .map(move |channel| (language_id.clone(), channel))
+            })
+            .collect::<Vec<_>>();
+        for (language_id, channel) in exited_channels {
+            self.record_crash(language_id, &channel)
+        }
+    }
+
+    fn record_crash(&mut self, language_id: LanguageId, channel: &LspServerProcessChannel) {
+        log::warn!(
+            "LspManager: language server `{}` exited unexpectedly",
+            channel.server_name()
+        );
+        self.progresses.remove_server(channel.server_name());
+        let crash_count = match self
+            .crashed_servers
+            .get(&(language_id.clone(), channel.server_name()))
+        {
+            // The backoff is reset if the server crashed after being initialized
+            Some(crashed_server) if !channel.is_initialized() => crashed_server.crash_count + 1,
+            _ => 1,
+        };
+        self.crashed_servers.insert(
+            (language_id, channel.server_name()),
+            CrashedServer {
+                language: channel.language().clone(),
+                crash_count,
+                exited_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Returns `None` if the server is unavailable, in which case `LspNotification::ServerUnavailable`
+    /// is sent once, and the server is not spawned again until `LspManager::retry_language`.
+    fn spawn_channel(
+        &mut self,
+        language: &Language,
+        lsp_command: LspCommand,
+    ) -> anyhow::Result<Option<LspServerProcessChannel>> {
+        let Some(language_id) = language.id() else {
+            return Ok(None);
+        };
+        let server_name = lsp_command.name();
+        if self
+            .unavailable_servers
+            .contains_key(&(language_id.clone(), server_name))
+        {
+            return Ok(None);
+        }
+        let reason = match LspServerProcessChannel::new(
+            language.clone(),
+            lsp_command,
+            self.sender.clone(),
+            self.current_working_directory.clone(),
+            self.configurations.clone(),
+        ) {
+            Ok(Some(channel)) => {
+                channel.set_request_timeout(self.request_timeout)?;
+                channel.set_change_debounce(self.change_debounce)?;
+                return Ok(Some(channel));
+            }
+            Ok(None) => format!("The command of `{server_name}` is not found"),
+            Err(error) => format!("{error:#}"),
+        };
+        self.unavailable_servers
+            .insert((language_id, server_name), reason.clone());
+        self.sender
+            .send(AppMessage::LspNotification(
+                LspNotification::ServerUnavailable {
+                    language: language.clone(),
+                    server_name,
+                    reason,
+                },
+            ))
+            .map_err(|error| anyhow::anyhow!("Unable to send ServerUnavailable: {}", error))?;
+        Ok(None)
+    }
+
+    /// Forgets that the servers of `language` failed to spawn,
+    /// so that they are spawned again on the next request, e.g. after they are installed.
+    #[allow(dead_code)]
+    pub(crate) fn retry_language(&mut self, language: &Language) {
         let Some(language_id) = language.id() else {
             return;
         };
+        self.unavailable_servers
+            .retain(|(id, _), _| id != &language_id);
+    }
+
+    /// Respawns the crashed servers of `language` whose backoff has elapsed.
+    fn restart_crashed_servers(&mut self, language: &Language) -> anyhow::Result<()> {
+        let Some(language_id) = language.id() else {
+            return Ok(());
+        };
+        let lsp_commands = language.lsp_commands();
+        for lsp_command in lsp_commands.iter().cloned() {
+            let server_name = lsp_command.name();
+            let is_running = self
+                .lsp_server_process_channels
+                .get(&language_id)
+                .is_some_and(|channels| {
+                    channels
+                        .iter()
+                        .any(|channel| channel.server_name() == server_name)
+                });
+            let can_restart = self
+                .crashed_servers
+                .get(&(language_id.clone(), server_name))
+                .is_some_and(|crashed_server| crashed_server.can_restart());
+            if is_running || !can_restart {
+                continue;
+            }
+            let Some(channel) = self.spawn_channel(language, lsp_command)? else {
+                continue;
+            };
+            let channels = self
+                .lsp_server_process_channels
+                .entry(language_id.clone())
+                .or_default();
+            channels.push(channel);
+            // The main language server always comes first
+            channels.sort_by_key(|channel| {
+                lsp_commands
+                    .iter()
+                    .position(|lsp_command| lsp_command.name() == channel.server_name())
+            });
+            self.sender
+                .send(AppMessage::LspNotification(
+                    LspNotification::ServerRestarted {
+                        language: language.clone(),
+                        server_name,
+                    },
+                ))
+                .map_err(|error| anyhow::anyhow!("Unable to send ServerRestarted: {}", error))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn update_progress(
+        &mut self,
+        server_name: &'static str,
+        token: String,
+        progress: lsp_types::WorkDoneProgress,
+    ) {
+        self.progresses.update(server_name, token, progress)
+    }
+
+    /// Describes the ongoing progresses of every language server, such as indexing.
+    pub(crate) fn display_progresses(&self) -> Option<String> {
+        self.progresses.display()
+    }
+
+    /// For example, "rust-analyzer: ready", returns `None` if no language server is running.
+    pub(crate) fn display_server_statuses(&self) -> Option<String> {
+        let statuses = self.running_languages();
+        (!statuses.is_empty()).then(|| {
+            statuses
+                .into_iter()
+                .map(|(_, server_name, status)| format!("{server_name}: {}", status.display()))
+                .join(", ")
+        })
+    }
+
+    /// For example, "finding references…", returns `None` if no request of `path` is pending.
+    pub(crate) fn display_pending_requests(&self, path: &CanonicalizedPath) -> Option<String> {
+        let kinds = self.pending_request_kinds(path);
+        (!kinds.is_empty()).then(|| {
+            let descriptions = kinds
+                .iter()
+                .map(RequestKind::description)
+                .dedup()
+                .join(", ");
+            format!("{descriptions}…")
+        })
+    }
+
+    /// The status of every language server, including the crashed ones,
+    /// ordered by language and then by server name.
+    pub(crate) fn running_languages(&self) -> Vec<(Language, &'static str, ServerStatus)> {
         self.lsp_server_process_channels
-            .get_mut(&language_id)
+            .values()
+            .flatten()
             .map(|channel| {
-                channel.initialized();
-                channel.documents_did_open(opened_documents)
-            });
+                let status = if channel.is_initialized() {
+                    ServerStatus::Ready
+                } else {
+                    ServerStatus::Initializing
+                };
+                (channel.language().clone(), channel.server_name(), status)
+            })
+            .chain(
+                self.crashed_servers
+                    .iter()
+                    .map(|((_, server_name), crashed_server)| {
+                        (
+                            crashed_server.language.clone(),
+                            *server_name,
+                            ServerStatus::Crashed,
+                        )
+                    }),
+            )
+            .sorted_by_key(|(language, server_name, _)| (language.id(), *server_name))
+            .collect()
+    }
+
+    /// Shuts down the language servers of `language` only,
+    /// they are spawned again on the next `open_file` of `language`.
+    pub(crate) fn shutdown_language(&mut self, language: &Language) -> anyhow::Result<()> {
+        let Some(language_id) = language.id() else {
+            return Ok(());
+        };
+        // Otherwise the servers that crashed previously would be restarted by the next request
+        self.crashed_servers
+            .retain(|(crashed_language_id, _), _| crashed_language_id != &language_id);
+        let Some(channels) = self.lsp_server_process_channels.remove(&language_id) else {
+            return Ok(());
+        };
+
+        // These servers will never respond to the pending workspace symbol request
+        let workspace_symbol_servers_count = channels
+            .iter()
+            .filter(|channel| channel.supports(ServerCapability::WorkspaceSymbol))
+            .count();
+        if let Some((remaining, merged)) = self.pending_workspace_symbols.take() {
+            let remaining = remaining.saturating_sub(workspace_symbol_servers_count);
+            if remaining > 0 {
+                self.pending_workspace_symbols = Some((remaining, merged))
+            }
+        }
+
+        consolidate_errors(
+            "[shutdown_language]",
+            channels
+                .into_iter()
+                .map(|channel| {
+                    self.progresses.remove_server(channel.server_name());
+                    channel.shutdown()
+                })
+                .collect(),
+        )
     }
 
     pub(crate) fn shutdown(&mut self) {
-        for (_, channel) in self.lsp_server_process_channels.drain() {
+        for channel in self
+            .lsp_server_process_channels
+            .drain()
+            .flat_map(|(_, channels)| channels)
+        {
             channel
                 .shutdown()
                 .unwrap_or_else(|error| log::error!("{:?}", error));
@@ -131,3 +987,136 @@ impl LspManager {
         self.history.get(from_editor.variant()) == Some(from_editor)
     }
 }
+
+#[cfg(test)]
+mod test_lsp_manager {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_for_every_consecutive_crash() {
+        let backoff = |crash_count| {
+            CrashedServer {
+                language: language::from_extension("rs").unwrap(),
+                crash_count,
+                exited_at: Instant::now(),
+            }
+            .backoff()
+        };
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(100), CrashedServer::MAX_BACKOFF);
+    }
+
+    #[test]
+    fn shutdown_language_forgets_crashed_servers_of_that_language() -> anyhow::Result<()> {
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        let mut manager = LspManager::new(sender, ".".try_into()?);
+        let rust = language::from_extension("rs").unwrap();
+        let python = language::from_extension("py").unwrap();
+        for language in [&rust, &python] {
+            manager.crashed_servers.insert(
+                (language.id().unwrap(), "server"),
+                CrashedServer {
+                    language: language.clone(),
+                    crash_count: 1,
+                    exited_at: Instant::now(),
+                },
+            );
+        }
+
+        assert_eq!(
+            manager.running_languages(),
+            vec![
+                (python.clone(), "server", ServerStatus::Crashed),
+                (rust.clone(), "server", ServerStatus::Crashed),
+            ]
+        );
+
+        manager.shutdown_language(&rust)?;
+        assert_eq!(
+            manager.crashed_servers.keys().cloned().collect_vec(),
+            vec![(python.id().unwrap(), "server")]
+        );
+        assert_eq!(
+            manager.running_languages(),
+            vec![(python, "server", ServerStatus::Crashed)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn responses_of_multiple_servers_are_merged_once_all_of_them_responded() -> anyhow::Result<()> {
+        use crate::{position::Position, quickfix_list::Location};
+
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        let mut manager = LspManager::new(sender, ".".try_into()?);
+        let path: CanonicalizedPath = "Cargo.toml".try_into()?;
+        let references = |lines: &[usize]| {
+            LspNotification::References(
+                ResponseContext::default(),
+                lines
+                    .iter()
+                    .map(|line| Location {
+                        path: path.clone(),
+                        range: Position::new(*line, 0)..Position::new(*line, 1),
+                    })
+                    .collect(),
+            )
+        };
+        manager
+            .pending_responses
+            .insert(MergedRequest::References, (3, None));
+
+        assert_eq!(manager.receive_response(references(&[1, 2])), None);
+        assert_eq!(
+            manager.receive_missing_response("textDocument/references"),
+            None
+        );
+        // References that are returned by multiple servers are only kept once
+        assert_eq!(
+            manager.receive_response(references(&[2, 3])),
+            Some(references(&[1, 2, 3]))
+        );
+
+        // Not held back, because only one server was requested
+        assert_eq!(
+            manager.receive_response(references(&[4])),
+            Some(references(&[4]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unavailable_server_is_reported_once_until_retried() -> anyhow::Result<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut manager = LspManager::new(sender, ".".try_into()?);
+        let rust = language::from_extension("rs").unwrap();
+        let unavailable_notifications = || {
+            receiver
+                .try_iter()
+                .filter(|message| {
+                    matches!(
+                        message,
+                        AppMessage::LspNotification(LspNotification::ServerUnavailable { .. })
+                    )
+                })
+                .count()
+        };
+
+        assert!(manager
+            .spawn_channel(&rust, LspCommand::default())?
+            .is_none());
+        assert!(manager
+            .spawn_channel(&rust, LspCommand::default())?
+            .is_none());
+        assert_eq!(unavailable_notifications(), 1);
+
+        manager.retry_language(&rust);
+        assert!(manager
+            .spawn_channel(&rust, LspCommand::default())?
+            .is_none());
+        assert_eq!(unavailable_notifications(), 1);
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
+pub(crate) mod call_hierarchy;
 pub(crate) mod code_action;
 pub(crate) mod completion;
 pub(crate) mod diagnostic;
+pub(crate) mod document_highlight;
 pub(crate) mod documentation;
 
 pub(crate) mod goto_definition_response;
 pub(crate) mod hover;
+pub(crate) mod inlay_hint;
 pub(crate) mod manager;
+pub(crate) mod position_encoding;
 pub(crate) mod prepare_rename_response;
 pub(crate) mod process;
+pub(crate) mod progress;
+pub(crate) mod semantic_tokens;
+pub(crate) mod server_capability;
 pub(crate) mod signature_help;
 pub(crate) mod symbols;
 pub(crate) mod workspace_edit;
@@ -0,0 +1,246 @@
+//! The column of a position in the LSP is measured in UTF-16 code units,
+//! whereas the column of `crate::position::Position` is measured in chars.
+//!
+//! Positions are converted by `LspServerProcess` when messages cross the boundary
+//! with the language server, so that the rest of the editor only deals with chars.
+
+use lsp_types::Url;
+use ropey::Rope;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Conversion {
+    /// From the editor to the language server
+    CharToUtf16,
+    /// From the language server to the editor
+    Utf16ToChar,
+}
+
+/// Converts the column of every `{ line, character }` object in `value`,
+/// which is the params or the result of a message.
+///
+/// The document of a position is given by the closest enclosing `uri`, `targetUri`
+/// or `textDocument.uri`, otherwise it is `uri`, the document of the request.
+///
+/// Positions of documents that cannot be obtained via `document` are left untouched.
+pub(crate) fn convert_positions(
+    value: &mut Value,
+    uri: Option<&Url>,
+    conversion: Conversion,
+    document: impl Fn(&Url) -> Option<Rope>,
+) {
+    Converter {
+        conversion,
+        document,
+        documents: HashMap::new(),
+        request_uri: uri.cloned(),
+    }
+    .convert(value, uri.cloned())
+}
+
+struct Converter<F> {
+    conversion: Conversion,
+    document: F,
+    /// So that each document is only obtained once
+    documents: HashMap<Url, Option<Rope>>,
+    request_uri: Option<Url>,
+}
+
+impl<F: Fn(&Url) -> Option<Rope>> Converter<F> {
+    fn convert(&mut self, value: &mut Value, uri: Option<Url>) {
+        match value {
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.convert(value, uri.clone())),
+            Value::Object(object) => {
+                if let Some(uri) = &uri {
+                    if let Some(character) = self.convert_position(object, uri) {
+                        object.insert("character".to_string(), character.into());
+                        return;
+                    }
+                }
+                let uri = document_uri(object).or(uri);
+                let caller_uri = object
+                    .get("from")
+                    .and_then(|from| from.get("uri"))
+                    .and_then(parse_url);
+                for (key, value) in object.iter_mut() {
+                    match key.as_str() {
+                        // Opaque to the editor, so it must be sent back to the server as is
+                        "data" => {}
+                        // `WorkspaceEdit.changes` is keyed by the URI of each document
+                        "changes" if value.is_object() => {
+                            for (key, value) in value.as_object_mut().into_iter().flatten() {
+                                self.convert(value, Url::parse(key).ok())
+                            }
+                        }
+                        // The range of `LocationLink` that is in the document of the request
+                        "originSelectionRange" => self.convert(value, self.request_uri.clone()),
+                        // The ranges of `CallHierarchyIncomingCall` are in the document of the caller
+                        "fromRanges" => self.convert(value, caller_uri.clone().or(uri.clone())),
+                        _ => self.convert(value, uri.clone()),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the converted column if `object` is a position.
+    fn convert_position(&mut self, object: &Map<String, Value>, uri: &Url) -> Option<u64> {
+        if object.len() != 2 {
+            return None;
+        }
+        let line = object.get("line")?.as_u64()? as usize;
+        let character = object.get("character")?.as_u64()? as usize;
+        let document = &self.document;
+        let rope = self
+            .documents
+            .entry(uri.clone())
+            .or_insert_with(|| document(uri))
+            .as_ref()?;
+        let line_start = rope.try_line_to_char(line).ok()?;
+        let line_start_utf16 = rope.try_char_to_utf16_cu(line_start).ok()?;
+        let line_len = line_len_chars(rope, line);
+        let column = match self.conversion {
+            Conversion::CharToUtf16 => {
+                rope.try_char_to_utf16_cu(line_start + character.min(line_len))
+                    .ok()?
+                    - line_start_utf16
+            }
+            Conversion::Utf16ToChar => {
+                let line_len_utf16 =
+                    rope.try_char_to_utf16_cu(line_start + line_len).ok()? - line_start_utf16;
+                rope.try_utf16_cu_to_char(line_start_utf16 + character.min(line_len_utf16))
+                    .ok()?
+                    - line_start
+            }
+        };
+        Some(column as u64)
+    }
+}
+
+/// The number of chars of the given line, excluding the line break.
+fn line_len_chars(rope: &Rope, line: usize) -> usize {
+    rope.get_line(line).map_or(0, |slice| {
+        let len = slice.len_chars();
+        match (
+            len.checked_sub(2).map(|index| slice.char(index)),
+            len.checked_sub(1).map(|index| slice.char(index)),
+        ) {
+            (Some('\r'), Some('\n')) => len - 2,
+            (_, Some('\n' | '\r')) => len - 1,
+            _ => len,
+        }
+    })
+}
+
+fn document_uri(object: &Map<String, Value>) -> Option<Url> {
+    object
+        .get("uri")
+        .or_else(|| object.get("targetUri"))
+        .or_else(|| {
+            object
+                .get("textDocument")
+                .and_then(|document| document.get("uri"))
+        })
+        .and_then(parse_url)
+}
+
+fn parse_url(value: &Value) -> Option<Url> {
+    Url::parse(value.as_str()?).ok()
+}
+
+#[cfg(test)]
+mod test_position_encoding {
+    use super::*;
+    use serde_json::json;
+
+    fn convert(mut value: Value, conversion: Conversion) -> Value {
+        let main = Url::parse("file:///main.rs").unwrap();
+        convert_positions(&mut value, Some(&main), conversion, |uri| {
+            match uri.path() {
+                "/main.rs" => Some(Rope::from_str("let 😀 = x;\n")),
+                "/foo.rs" => Some(Rope::from_str("fn 😀😀() {}\r\n")),
+                _ => None,
+            }
+        });
+        value
+    }
+
+    #[test]
+    fn positions_are_converted_against_their_own_document() {
+        let location_link = |origin, target| {
+            json!({
+                "originSelectionRange": {
+                    "start": { "line": 0, "character": origin },
+                    "end": { "line": 0, "character": origin }
+                },
+                "targetUri": "file:///foo.rs",
+                "targetRange": {
+                    "start": { "line": 0, "character": target },
+                    "end": { "line": 0, "character": target }
+                },
+                "data": { "line": 0, "character": 9 }
+            })
+        };
+        // Both the `=` of `main.rs` and the `(` of `foo.rs` are at the UTF-16 column 7
+        assert_eq!(
+            convert(json!([location_link(7, 7)]), Conversion::Utf16ToChar),
+            json!([location_link(6, 5)])
+        );
+        assert_eq!(
+            convert(json!([location_link(6, 5)]), Conversion::CharToUtf16),
+            json!([location_link(7, 7)])
+        );
+    }
+
+    #[test]
+    fn workspace_edit_changes_are_keyed_by_uri() {
+        let edit = |character| {
+            json!({
+                "changes": {
+                    "file:///foo.rs": [{
+                        "range": {
+                            "start": { "line": 0, "character": character },
+                            "end": { "line": 0, "character": 99 }
+                        },
+                        "newText": ""
+                    }]
+                }
+            })
+        };
+        let expected = json!({
+            "changes": {
+                "file:///foo.rs": [{
+                    "range": {
+                        "start": { "line": 0, "character": 5 },
+                        // Clamped to the end of the line, excluding the line break
+                        "end": { "line": 0, "character": 10 }
+                    },
+                    "newText": ""
+                }]
+            }
+        });
+        assert_eq!(convert(edit(7), Conversion::Utf16ToChar), expected);
+    }
+
+    #[test]
+    fn positions_of_unknown_documents_are_untouched() {
+        let diagnostics = json!({
+            "uri": "file:///unknown.rs",
+            "diagnostics": [{
+                "range": {
+                    "start": { "line": 0, "character": 7 },
+                    "end": { "line": 0, "character": 8 }
+                },
+                "message": "x"
+            }]
+        });
+        assert_eq!(
+            convert(diagnostics.clone(), Conversion::Utf16ToChar),
+            diagnostics
+        );
+    }
+}
@@ -7,32 +7,42 @@ use lsp_types::request::{
 };
 use lsp_types::*;
 use name_variant::NamedVariant;
+use ropey::Rope;
 use shared::canonicalized_path::CanonicalizedPath;
-use shared::language::Language;
+use shared::language::{Language, LanguageId, LspCommand};
 use shared::process_command::SpawnCommandResult;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader, Read, Write};
 
 use std::process::{self};
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 use crate::app::AppMessage;
 use crate::utils::consolidate_errors;
 
+use super::call_hierarchy::{CallHierarchyCall, CallHierarchyItem};
 use super::code_action::CodeAction;
 use super::completion::{Completion, CompletionItem};
+use super::document_highlight::DocumentHighlight;
 use super::goto_definition_response::GotoDefinitionResponse;
 use super::hover::Hover;
+use super::inlay_hint::InlayHint;
+use super::position_encoding::{self, Conversion};
 use super::prepare_rename_response::PrepareRenameResponse;
+use super::semantic_tokens::{self, SemanticToken};
+use super::server_capability::ServerCapability;
 use super::signature_help::SignatureHelp;
 use super::symbols::Symbols;
-use super::workspace_edit::WorkspaceEdit;
+use super::workspace_edit::{TextDocumentEdit, WorkspaceEdit};
 use crate::quickfix_list::Location;
 
 struct LspServerProcess {
     language: Language,
+    lsp_command: LspCommand,
+    process_id: u32,
     stdin: process::ChildStdin,
 
     /// This is hacky, but we need to keep the stdout around so that it doesn't get dropped
@@ -42,7 +52,26 @@ struct LspServerProcess {
     server_capabilities: Option<ServerCapabilities>,
     current_working_directory: CanonicalizedPath,
     next_request_id: RequestId,
-    pending_response_requests: HashMap<RequestId, PendingResponseRequest>,
+    /// Shared with `LspServerProcessChannel`, so that the editor can tell what is being waited on
+    pending_response_requests: PendingResponseRequests,
+    /// Shared with `LspManager`, used for replying `workspace/configuration` requests
+    configurations: Configurations,
+    /// The latest diagnostics forwarded to the editor, keyed by document URI
+    published_diagnostics: HashMap<Url, PublishDiagnosticsParams>,
+    /// The latest semantic tokens of each document,
+    /// which are kept so that subsequent requests only need to fetch the delta
+    semantic_tokens: HashMap<CanonicalizedPath, SemanticTokens>,
+    /// Pending requests that are not responded within this duration are cancelled
+    request_timeout: Duration,
+    /// Changes of the same document within this duration are coalesced
+    change_debounce: Duration,
+    pending_changes: HashMap<CanonicalizedPath, PendingChange>,
+    /// The version of each opened document that is last notified to the server,
+    /// incremental changes are only sent if they are based on this version
+    document_versions: HashMap<CanonicalizedPath, i32>,
+    /// The content of each opened document that is last notified to the server,
+    /// which is needed for converting the columns of positions, see `position_encoding`
+    documents: HashMap<Url, Rope>,
     app_message_sender: Sender<AppMessage>,
 
     sender: Sender<LspServerProcessMessage>,
@@ -50,17 +79,49 @@ struct LspServerProcess {
 
 type RequestId = u64;
 
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often pending requests are checked for timeouts
+const REQUEST_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+pub(crate) const DEFAULT_CHANGE_DEBOUNCE: Duration = Duration::from_millis(150);
+
+type PendingResponseRequests = Arc<Mutex<HashMap<RequestId, PendingResponseRequest>>>;
+
+/// The settings of each language, see `LspManager::update_configuration`.
+pub(crate) type Configurations = Arc<Mutex<HashMap<LanguageId, serde_json::Value>>>;
+
 #[derive(Debug)]
 struct PendingResponseRequest {
     method: String,
     context: ResponseContext,
     path: Option<CanonicalizedPath>,
+    sent_at: Instant,
+}
+
+/// A `textDocument/didChange` notification that is held back,
+/// so that it can be superseded by a newer change of the same document.
+#[derive(Debug)]
+struct PendingChange {
+    /// The version of the document before the first coalesced change
+    base_version: i32,
+    version: i32,
+    content: String,
+    changes: Option<Vec<TextDocumentContentChangeEvent>>,
+    due_at: Instant,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum LspNotification {
-    Initialized(Language),
-    PublishDiagnostics(PublishDiagnosticsParams),
+    Initialized {
+        language: Language,
+        server_name: &'static str,
+        capabilities: ServerCapabilities,
+    },
+    PublishDiagnostics {
+        server_name: &'static str,
+        params: PublishDiagnosticsParams,
+    },
     Completion(ResponseContext, Completion),
     Hover(Hover),
     Definition(ResponseContext, GotoDefinitionResponse),
@@ -69,17 +130,88 @@ pub(crate) enum LspNotification {
     Error(String),
     WorkspaceEdit(WorkspaceEdit),
     CodeAction(Vec<CodeAction>),
+    CodeActionResolved(CodeAction),
+    RequestTimedOut {
+        method: String,
+        path: Option<CanonicalizedPath>,
+    },
+    /// The language server responded to `method` with an error,
+    /// which is reported via `LspNotification::Error` too
+    RequestFailed {
+        method: String,
+    },
     SignatureHelp(Option<SignatureHelp>),
     Symbols(Symbols),
+    WorkspaceSymbols(Symbols),
+    SemanticTokens {
+        path: CanonicalizedPath,
+        tokens: Vec<SemanticToken>,
+    },
+    InlayHints {
+        path: CanonicalizedPath,
+        hints: Vec<InlayHint>,
+    },
+    FoldingRanges {
+        path: CanonicalizedPath,
+        /// End-exclusive line ranges
+        ranges: Vec<std::ops::Range<usize>>,
+    },
+    DocumentHighlights {
+        path: CanonicalizedPath,
+        highlights: Vec<DocumentHighlight>,
+    },
+    CallHierarchyItems(ResponseContext, Vec<CallHierarchyItem>),
+    /// The callers or callees of an item, depending on the request
+    CallHierarchyCalls(ResponseContext, Vec<CallHierarchyCall>),
     CompletionItemResolve(lsp_types::CompletionItem),
+    DocumentFormatting(TextDocumentEdit),
+    /// The language server process stopped responding, most likely it crashed.
+    ServerExited {
+        language: Language,
+        process_id: u32,
+    },
+    ServerRestarted {
+        language: Language,
+        server_name: &'static str,
+    },
+    /// The language server failed to spawn, e.g. because it is not installed
+    ServerUnavailable {
+        language: Language,
+        server_name: &'static str,
+        reason: String,
+    },
+    /// From `window/showMessage`, or `window/logMessage` that are errors
+    Log {
+        server_name: &'static str,
+        level: log::Level,
+        message: String,
+    },
+    Progress {
+        server_name: &'static str,
+        token: String,
+        progress: WorkDoneProgress,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub(crate) struct ResponseContext {
     pub(crate) scope: Option<Scope>,
     pub(crate) description: Option<String>,
+    /// The document of the request, which is set by `LspServerProcess::send_request`.
+    pub(crate) path: Option<CanonicalizedPath>,
+    /// The version of the document known by the language server when the request was sent,
+    /// which is set by `LspServerProcess::send_request`.
+    pub(crate) document_version: Option<i32>,
 }
 impl ResponseContext {
+    /// The positions of a response to an outdated document might be misplaced.
+    ///
+    /// Always false if the request is not tied to a document.
+    pub(crate) fn is_outdated(&self, current_document_version: i32) -> bool {
+        self.document_version
+            .is_some_and(|version| version < current_document_version)
+    }
+
     pub(crate) fn set_description(self, descrption: &str) -> Self {
         Self {
             description: Some(descrption.to_owned()),
@@ -95,6 +227,8 @@ enum LspServerProcessMessage {
     FromEditor(FromEditor),
     /// Throttled message should be executed immediately
     Throttled(FromEditor),
+    SetRequestTimeout(Duration),
+    SetChangeDebounce(Duration),
     Shutdown,
 }
 
@@ -118,10 +252,16 @@ pub(crate) enum FromEditor {
         file_path: CanonicalizedPath,
         version: i32,
         content: String,
+        /// The ranged changes since the previous version, `None` if unknown.
+        /// The full `content` is sent instead if the server does not support incremental sync.
+        changes: Option<Vec<TextDocumentContentChangeEvent>>,
     },
     TextDocumentDidSave {
         file_path: CanonicalizedPath,
     },
+    TextDocumentDidClose {
+        file_path: CanonicalizedPath,
+    },
     TextDocumentPrepareRename(RequestParams),
     TextDocumentRename {
         params: RequestParams,
@@ -129,13 +269,43 @@ pub(crate) enum FromEditor {
     },
     TextDocumentCodeAction {
         params: RequestParams,
+        range: std::ops::Range<crate::position::Position>,
         diagnostics: Vec<lsp_types::Diagnostic>,
     },
+    CodeActionResolve {
+        params: RequestParams,
+        code_action: CodeAction,
+    },
     TextDocumentSignatureHelp(RequestParams),
     TextDocumentDeclaration(RequestParams),
     TextDocumentImplementation(RequestParams),
     TextDocumentTypeDefinition(RequestParams),
     TextDocumentDocumentSymbol(RequestParams),
+    TextDocumentSemanticTokens(RequestParams),
+    TextDocumentInlayHint {
+        params: RequestParams,
+        range: std::ops::Range<crate::position::Position>,
+    },
+    TextDocumentFoldingRange(RequestParams),
+    TextDocumentDocumentHighlight(RequestParams),
+    TextDocumentPrepareCallHierarchy(RequestParams),
+    /// `item` is one of the items returned by `TextDocumentPrepareCallHierarchy`
+    CallHierarchyIncomingCalls {
+        params: RequestParams,
+        item: lsp_types::CallHierarchyItem,
+    },
+    CallHierarchyOutgoingCalls {
+        params: RequestParams,
+        item: lsp_types::CallHierarchyItem,
+    },
+    WorkspaceSymbol {
+        query: String,
+    },
+    TextDocumentFormatting(RequestParams),
+    TextDocumentRangeFormatting {
+        params: RequestParams,
+        range: std::ops::Range<crate::position::Position>,
+    },
     WorkspaceDidRenameFiles {
         old: CanonicalizedPath,
         new: CanonicalizedPath,
@@ -143,6 +313,13 @@ pub(crate) enum FromEditor {
     WorkspaceDidCreateFiles {
         file_path: CanonicalizedPath,
     },
+    /// For files that are changed on disk by something other than the editor
+    WorkspaceDidChangeWatchedFiles {
+        changes: Vec<(CanonicalizedPath, FileChangeType)>,
+    },
+    WorkspaceDidChangeConfiguration {
+        settings: serde_json::Value,
+    },
     WorkspaceExecuteCommand {
         params: RequestParams,
         command: super::code_action::Command,
@@ -157,22 +334,156 @@ impl FromEditor {
     pub(crate) fn variant(&self) -> &'static str {
         self.variant_name()
     }
+
+    /// The server capability required by user-initiated requests.
+    /// Requests that are sent automatically (e.g. completion) are
+    /// silently skipped by the server process instead.
+    pub(crate) fn required_capability(&self) -> Option<ServerCapability> {
+        match self {
+            FromEditor::TextDocumentHover(_) => Some(ServerCapability::Hover),
+            FromEditor::TextDocumentDefinition(_) => Some(ServerCapability::Definition),
+            FromEditor::TextDocumentDeclaration(_) => Some(ServerCapability::Declaration),
+            FromEditor::TextDocumentImplementation(_) => Some(ServerCapability::Implementation),
+            FromEditor::TextDocumentTypeDefinition(_) => Some(ServerCapability::TypeDefinition),
+            FromEditor::TextDocumentReferences { .. } => Some(ServerCapability::References),
+            FromEditor::TextDocumentPrepareRename(_) | FromEditor::TextDocumentRename { .. } => {
+                Some(ServerCapability::Rename)
+            }
+            FromEditor::TextDocumentCodeAction { .. } => Some(ServerCapability::CodeAction),
+            FromEditor::TextDocumentDocumentSymbol(_) => Some(ServerCapability::DocumentSymbol),
+            FromEditor::WorkspaceSymbol { .. } => Some(ServerCapability::WorkspaceSymbol),
+            FromEditor::TextDocumentFormatting(_) => Some(ServerCapability::Formatting),
+            FromEditor::TextDocumentRangeFormatting { .. } => {
+                Some(ServerCapability::RangeFormatting)
+            }
+            FromEditor::TextDocumentPrepareCallHierarchy(_)
+            | FromEditor::CallHierarchyIncomingCalls { .. }
+            | FromEditor::CallHierarchyOutgoingCalls { .. } => {
+                Some(ServerCapability::CallHierarchy)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns true if an older request of `method` is obsolete
+/// once a newer one is sent for the same document.
+fn is_superseded_by_newer_request(method: &str) -> bool {
+    matches!(
+        method,
+        "textDocument/completion"
+            | "completionItem/resolve"
+            | "textDocument/hover"
+            | "textDocument/definition"
+            | "textDocument/declaration"
+            | "textDocument/implementation"
+            | "textDocument/typeDefinition"
+            | "textDocument/references"
+            | "textDocument/signatureHelp"
+            | "textDocument/documentSymbol"
+            | "textDocument/semanticTokens/full"
+            | "textDocument/semanticTokens/full/delta"
+            | "textDocument/inlayHint"
+            | "textDocument/foldingRange"
+            | "textDocument/documentHighlight"
+            | "textDocument/prepareCallHierarchy"
+            | "callHierarchy/incomingCalls"
+            | "callHierarchy/outgoingCalls"
+            | "workspace/symbol"
+            | "textDocument/prepareRename"
+            | "textDocument/codeAction"
+    )
+}
+
+/// The kind of a request that is still waiting for the response of a language server.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct RequestKind {
+    method: String,
+}
+
+impl RequestKind {
+    /// For example, "finding references", which falls back to the method for uncommon requests.
+    pub(crate) fn description(&self) -> &str {
+        match self.method.as_str() {
+            "textDocument/completion" | "completionItem/resolve" => "fetching completions",
+            "textDocument/hover" => "fetching hover info",
+            "textDocument/definition" => "finding definitions",
+            "textDocument/declaration" => "finding declarations",
+            "textDocument/implementation" => "finding implementations",
+            "textDocument/typeDefinition" => "finding type definitions",
+            "textDocument/references" => "finding references",
+            "textDocument/prepareRename" | "textDocument/rename" => "renaming",
+            "textDocument/codeAction" | "codeAction/resolve" => "fetching code actions",
+            "textDocument/formatting" | "textDocument/rangeFormatting" => "formatting",
+            "textDocument/documentSymbol" => "fetching symbols",
+            "textDocument/signatureHelp" => "fetching signature help",
+            "textDocument/prepareCallHierarchy"
+            | "callHierarchy/incomingCalls"
+            | "callHierarchy/outgoingCalls" => "finding calls",
+            method => method,
+        }
+    }
 }
 
 pub(crate) struct LspServerProcessChannel {
     language: Language,
+    server_name: &'static str,
+    process_id: u32,
     join_handle: JoinHandle<JoinHandle<()>>,
     sender: Sender<LspServerProcessMessage>,
-    is_initialized: bool,
+    /// Only available after the server is initialized
+    server_capabilities: Option<ServerCapabilities>,
+    /// Documents that are notified as opened but not yet closed
+    opened_documents: HashSet<CanonicalizedPath>,
+    pending_response_requests: PendingResponseRequests,
 }
 
 impl LspServerProcessChannel {
     pub(crate) fn new(
         language: Language,
+        lsp_command: LspCommand,
         screen_message_sender: Sender<AppMessage>,
         current_working_directory: CanonicalizedPath,
+        configurations: Configurations,
     ) -> Result<Option<LspServerProcessChannel>, anyhow::Error> {
-        LspServerProcess::start(language, screen_message_sender, current_working_directory)
+        LspServerProcess::start(
+            language,
+            lsp_command,
+            screen_message_sender,
+            current_working_directory,
+            configurations,
+        )
+    }
+
+    pub(crate) fn server_name(&self) -> &'static str {
+        self.server_name
+    }
+
+    /// The kinds of the requests of `path` that are not responded yet.
+    pub(crate) fn pending_request_kinds(&self, path: &CanonicalizedPath) -> Vec<RequestKind> {
+        self.pending_response_requests
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|request| request.path.as_ref() == Some(path))
+            .map(|request| RequestKind {
+                method: request.method.clone(),
+            })
+            .collect()
+    }
+
+    pub(crate) fn language(&self) -> &Language {
+        &self.language
+    }
+
+    pub(crate) fn process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// True if the message processor loop has stopped,
+    /// after which every message sent to this channel fails.
+    pub(crate) fn has_exited(&self) -> bool {
+        self.join_handle.is_finished()
     }
 
     pub(crate) fn shutdown(self) -> anyhow::Result<()> {
@@ -190,6 +501,14 @@ impl LspServerProcessChannel {
             .map_err(|err| anyhow::anyhow!("Unable to send request: {}", err))
     }
 
+    pub(crate) fn set_request_timeout(&self, timeout: Duration) -> anyhow::Result<()> {
+        self.send(LspServerProcessMessage::SetRequestTimeout(timeout))
+    }
+
+    pub(crate) fn set_change_debounce(&self, debounce: Duration) -> anyhow::Result<()> {
+        self.send(LspServerProcessMessage::SetChangeDebounce(debounce))
+    }
+
     pub(crate) fn documents_did_open(
         &mut self,
         paths: Vec<CanonicalizedPath>,
@@ -203,11 +522,15 @@ impl LspServerProcessChannel {
         )
     }
 
-    pub(crate) fn document_did_open(&self, path: CanonicalizedPath) -> Result<(), anyhow::Error> {
+    pub(crate) fn document_did_open(
+        &mut self,
+        path: CanonicalizedPath,
+    ) -> Result<(), anyhow::Error> {
         let content = path.read()?;
         let Some(language_id) = self.language.id() else {
             return Ok(());
         };
+        self.opened_documents.insert(path.clone());
         self.send(LspServerProcessMessage::FromEditor(
             FromEditor::TextDocumentDidOpen {
                 file_path: path,
@@ -218,12 +541,39 @@ impl LspServerProcessChannel {
         ))
     }
 
+    /// Does nothing if `path` is not opened in this channel.
+    pub(crate) fn document_did_close(
+        &mut self,
+        path: &CanonicalizedPath,
+    ) -> Result<(), anyhow::Error> {
+        if !self.opened_documents.remove(path) {
+            return Ok(());
+        }
+        self.send(LspServerProcessMessage::FromEditor(
+            FromEditor::TextDocumentDidClose {
+                file_path: path.clone(),
+            },
+        ))
+    }
+
+    /// Used for identifying idle LSP servers, which have no opened documents.
+    pub(crate) fn opened_documents_count(&self) -> usize {
+        self.opened_documents.len()
+    }
+
     pub(crate) fn is_initialized(&self) -> bool {
-        self.is_initialized
+        self.server_capabilities.is_some()
     }
 
-    pub(crate) fn initialized(&mut self) {
-        self.is_initialized = true
+    pub(crate) fn initialized(&mut self, capabilities: ServerCapabilities) {
+        self.server_capabilities = Some(capabilities)
+    }
+
+    /// Always false if the server is not initialized yet.
+    pub(crate) fn supports(&self, capability: ServerCapability) -> bool {
+        self.server_capabilities
+            .as_ref()
+            .is_some_and(|capabilities| capability.is_supported_by(capabilities))
     }
 
     pub(crate) fn send_from_editor(&self, from_editor: FromEditor) -> Result<(), anyhow::Error> {
@@ -234,15 +584,12 @@ impl LspServerProcessChannel {
 impl LspServerProcess {
     fn start(
         language: Language,
+        lsp_command: LspCommand,
         app_message_sender: Sender<AppMessage>,
         current_working_directory: CanonicalizedPath,
+        configurations: Configurations,
     ) -> anyhow::Result<Option<LspServerProcessChannel>> {
-        let process_command = match language.lsp_process_command() {
-            Some(result) => result,
-            None => return Ok(None),
-        };
-
-        let mut process = match process_command.spawn() {
+        let mut process = match lsp_command.process_command().spawn() {
             SpawnCommandResult::Spawned(result) => result?,
             SpawnCommandResult::CommandNotFound { .. } => {
                 return Ok(None);
@@ -263,14 +610,27 @@ impl LspServerProcess {
             .take()
             .ok_or_else(|| anyhow::anyhow!("Unable to obtain stdout"))?;
         let (sender, receiver) = std::sync::mpsc::channel::<LspServerProcessMessage>();
+        let server_name = lsp_command.name();
+        let process_id = process.id();
+        let pending_response_requests = PendingResponseRequests::default();
         let mut lsp_server_process = LspServerProcess {
             language: language.clone(),
+            lsp_command,
+            process_id,
             stdin,
             stdout: Some(stdout),
             stderr: Some(stderr),
             current_working_directory,
             next_request_id: 0,
-            pending_response_requests: HashMap::new(),
+            pending_response_requests: pending_response_requests.clone(),
+            configurations,
+            published_diagnostics: HashMap::new(),
+            semantic_tokens: HashMap::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            change_debounce: DEFAULT_CHANGE_DEBOUNCE,
+            pending_changes: HashMap::new(),
+            document_versions: HashMap::new(),
+            documents: HashMap::new(),
             server_capabilities: None,
             app_message_sender: app_message_sender.clone(),
             sender: sender.clone(),
@@ -283,9 +643,13 @@ impl LspServerProcess {
 
         Ok(Some(LspServerProcessChannel {
             language,
+            server_name,
+            process_id,
             join_handle,
             sender,
-            is_initialized: false,
+            server_capabilities: None,
+            opened_documents: HashSet::new(),
+            pending_response_requests,
         }))
     }
 
@@ -295,10 +659,18 @@ impl LspServerProcess {
             None,
             InitializeParams {
                 process_id: None,
-                initialization_options: self.language.initialization_options(),
+                initialization_options: self.lsp_command.initialization_options(),
                 capabilities: ClientCapabilities {
+                    window: Some(WindowClientCapabilities {
+                        work_done_progress: Some(true),
+                        ..WindowClientCapabilities::default()
+                    }),
                     workspace: Some(WorkspaceClientCapabilities {
                         apply_edit: Some(true),
+                        did_change_watched_files: Some(DidChangeWatchedFilesClientCapabilities {
+                            dynamic_registration: Some(false),
+                            ..Default::default()
+                        }),
                         workspace_edit: Some(WorkspaceEditClientCapabilities {
                             document_changes: Some(true),
                             resource_operations: Some(
@@ -320,6 +692,7 @@ impl LspServerProcess {
                         execute_command: Some(DynamicRegistrationClientCapabilities {
                             dynamic_registration: None,
                         }),
+                        configuration: Some(true),
                         ..WorkspaceClientCapabilities::default()
                     }),
                     text_document: Some(TextDocumentClientCapabilities {
@@ -370,6 +743,10 @@ impl LspServerProcess {
                                     .collect(),
                                 },
                             }),
+                            data_support: Some(true),
+                            resolve_support: Some(CodeActionCapabilityResolveSupport {
+                                properties: vec!["edit".to_string()],
+                            }),
                             ..Default::default()
                         }),
                         rename: Some(RenameClientCapabilities {
@@ -390,6 +767,43 @@ impl LspServerProcess {
                             dynamic_registration: Some(true),
                             link_support: None,
                         }),
+                        semantic_tokens: Some(SemanticTokensClientCapabilities {
+                            requests: SemanticTokensClientCapabilitiesRequests {
+                                range: None,
+                                full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                            },
+                            token_types: vec![
+                                SemanticTokenType::NAMESPACE,
+                                SemanticTokenType::TYPE,
+                                SemanticTokenType::CLASS,
+                                SemanticTokenType::ENUM,
+                                SemanticTokenType::INTERFACE,
+                                SemanticTokenType::STRUCT,
+                                SemanticTokenType::TYPE_PARAMETER,
+                                SemanticTokenType::PARAMETER,
+                                SemanticTokenType::VARIABLE,
+                                SemanticTokenType::PROPERTY,
+                                SemanticTokenType::ENUM_MEMBER,
+                                SemanticTokenType::FUNCTION,
+                                SemanticTokenType::METHOD,
+                                SemanticTokenType::MACRO,
+                                SemanticTokenType::DECORATOR,
+                            ],
+                            token_modifiers: vec![SemanticTokenModifier::DEFAULT_LIBRARY],
+                            formats: vec![TokenFormat::RELATIVE],
+                            ..Default::default()
+                        }),
+                        inlay_hint: Some(InlayHintClientCapabilities {
+                            dynamic_registration: None,
+                            resolve_support: None,
+                        }),
+                        folding_range: Some(FoldingRangeClientCapabilities {
+                            line_folding_only: Some(true),
+                            ..Default::default()
+                        }),
+                        call_hierarchy: Some(CallHierarchyClientCapabilities {
+                            dynamic_registration: None,
+                        }),
                         ..TextDocumentClientCapabilities::default()
                     }),
                     ..ClientCapabilities::default()
@@ -450,6 +864,8 @@ impl LspServerProcess {
         app_message_sender: Sender<AppMessage>,
         lsp_command: String,
     ) -> JoinHandle<()> {
+        let language = self.language.clone();
+        let process_id = self.process_id;
         thread::spawn(move || {
             let mut error_tracker = ErrorTracker::new();
 
@@ -480,6 +896,17 @@ impl LspServerProcess {
                                     error
                                 );
                             });
+                            app_message_sender
+                                .send(AppMessage::LspNotification(LspNotification::ServerExited {
+                                    language,
+                                    process_id,
+                                }))
+                                .unwrap_or_else(|error| {
+                                    log::error!(
+                                        "[LspServerProcess] Error sending ServerExited to app: {:?}",
+                                        error
+                                    );
+                                });
                             break;
                         }
                     }
@@ -514,8 +941,42 @@ impl LspServerProcess {
             })
         };
 
+        let mut timeouts_checked_at = Instant::now();
+
         // The message processor loop
-        while let Ok(message) = receiver.recv() {
+        loop {
+            // Checked on every iteration, because a busy server may never leave the receiver idle
+            if timeouts_checked_at.elapsed() >= REQUEST_TIMEOUT_CHECK_INTERVAL {
+                timeouts_checked_at = Instant::now();
+                self.cancel_timed_out_requests().unwrap_or_else(|error| {
+                    log::info!(
+                        "LspServerProcess::listen | Error cancelling timed out requests, error={:?}",
+                        error
+                    );
+                });
+            }
+            self.send_pending_changes(true).unwrap_or_else(|error| {
+                log::info!(
+                    "LspServerProcess::listen | Error sending pending changes, error={:?}",
+                    error
+                );
+            });
+            // Wake up in time for the next pending change, or the next timeout check
+            let next_timeout_check =
+                REQUEST_TIMEOUT_CHECK_INTERVAL.saturating_sub(timeouts_checked_at.elapsed());
+            let timeout = self
+                .pending_changes
+                .values()
+                .map(|change| change.due_at.saturating_duration_since(Instant::now()))
+                .min()
+                .map_or(next_timeout_check, |duration| {
+                    duration.min(next_timeout_check)
+                });
+            let message = match receiver.recv_timeout(timeout) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
             match &message {
                 LspServerProcessMessage::FromLspServer(json_value) => {
                     self.handle_reply(json_value.clone())
@@ -535,11 +996,55 @@ impl LspServerProcess {
                         completion_item,
                         params,
                     })),
-                    _ => self.handle_from_editor(from_editor),
+                    FromEditor::TextDocumentDidChange {
+                        file_path,
+                        version,
+                        content,
+                        changes,
+                    } if !self.change_debounce.is_zero() => {
+                        let change = match self.pending_changes.remove(&file_path) {
+                            Some(pending) => PendingChange {
+                                base_version: pending.base_version,
+                                version,
+                                content,
+                                changes: pending.changes.zip(changes).map(
+                                    |(mut pending_changes, changes)| {
+                                        pending_changes.extend(changes);
+                                        pending_changes
+                                    },
+                                ),
+                                due_at: pending.due_at,
+                            },
+                            None => PendingChange {
+                                base_version: version - 1,
+                                version,
+                                content,
+                                changes,
+                                due_at: Instant::now() + self.change_debounce,
+                            },
+                        };
+                        self.pending_changes.insert(file_path, change);
+                    }
+                    _ => {
+                        // Requests (and saves) must be handled against the latest content
+                        self.send_pending_changes(false).unwrap_or_else(|error| {
+                            log::info!(
+                                "LspServerProcess::listen | Error sending pending changes, error={:?}",
+                                error
+                            );
+                        });
+                        self.handle_from_editor(from_editor)
+                    }
                 },
                 LspServerProcessMessage::Throttled(from_editor) => {
                     self.handle_from_editor(from_editor)
                 }
+                LspServerProcessMessage::SetRequestTimeout(timeout) => {
+                    self.request_timeout = *timeout
+                }
+                LspServerProcessMessage::SetChangeDebounce(debounce) => {
+                    self.change_debounce = *debounce
+                }
                 LspServerProcessMessage::Shutdown => break,
             }
         }
@@ -604,11 +1109,19 @@ impl LspServerProcess {
                 let request_id = reply.get("id").unwrap().as_u64().unwrap();
 
                 // Get the method of the request
-                let pending_response_request =
-                    self.pending_response_requests.remove(&request_id).unwrap();
+                let Some(pending_response_request) = self
+                    .pending_response_requests
+                    .lock()
+                    .unwrap()
+                    .remove(&request_id)
+                else {
+                    // The request was cancelled
+                    log::info!("Ignoring response of request {request_id}");
+                    return Ok(());
+                };
 
                 // Parse the reply as a Response
-                let response = serde_json::from_value::<
+                let mut response = serde_json::from_value::<
                     json_rpc_types::Response<
                         serde_json::Value,
                         (),
@@ -626,6 +1139,13 @@ impl LspServerProcess {
                             format!("LSP JSON-RPC Error: {:?}: {}", e.code, e.message),
                         )))
                         .unwrap();
+                    self.app_message_sender
+                        .send(AppMessage::LspNotification(
+                            LspNotification::RequestFailed {
+                                method: pending_response_request.method.clone(),
+                            },
+                        ))
+                        .unwrap();
                     anyhow::anyhow!(
                         "LSP JSON-RPC Error: Code={:?} Message={}",
                         e.code,
@@ -637,7 +1157,9 @@ impl LspServerProcess {
                     method,
                     context: response_context,
                     path,
+                    ..
                 } = pending_response_request;
+                self.convert_positions(&mut response, path.as_ref(), Conversion::Utf16ToChar);
 
                 match method.as_str() {
                     "initialize" => {
@@ -646,7 +1168,7 @@ impl LspServerProcess {
                             serde_json::from_value(response)?;
 
                         // Get the capabilities
-                        self.server_capabilities = Some(payload.capabilities);
+                        self.server_capabilities = Some(payload.capabilities.clone());
 
                         // Send the initialized notification
                         self.send_notification::<lsp_notification!("initialized")>(
@@ -654,31 +1176,35 @@ impl LspServerProcess {
                         )?;
 
                         self.app_message_sender.send(AppMessage::LspNotification(
-                            LspNotification::Initialized(self.language.clone()),
+                            LspNotification::Initialized {
+                                language: self.language.clone(),
+                                server_name: self.lsp_command.name(),
+                                capabilities: payload.capabilities,
+                            },
                         ))?;
                     }
                     "textDocument/completion" => {
                         let payload: <lsp_request!("textDocument/completion") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(LspNotification::Completion(
-                                    response_context,
-                                    Completion {
-                                        trigger_characters: self.trigger_characters(),
-                                        items: match payload {
-                                            CompletionResponse::Array(items) => items,
-                                            CompletionResponse::List(list) => list.items,
-                                        }
-                                        .into_iter()
-                                        .map(CompletionItem::from)
-                                        .map(|item| item.into())
-                                        .collect(),
-                                    },
-                                )))
-                                .unwrap();
-                        }
+                        // Always respond, so that the results of multiple servers can be merged
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(LspNotification::Completion(
+                                response_context,
+                                Completion {
+                                    trigger_characters: self.trigger_characters(),
+                                    items: match payload {
+                                        Some(CompletionResponse::Array(items)) => items,
+                                        Some(CompletionResponse::List(list)) => list.items,
+                                        None => Vec::new(),
+                                    }
+                                    .into_iter()
+                                    .map(CompletionItem::from)
+                                    .map(|item| item.into())
+                                    .collect(),
+                                },
+                            )))
+                            .unwrap();
                     }
                     "textDocument/hover" => {
                         let payload: <lsp_request!("textDocument/hover") as Request>::Result =
@@ -696,81 +1222,55 @@ impl LspServerProcess {
                         let payload: <lsp_request!("textDocument/definition") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(LspNotification::Definition(
-                                    response_context,
-                                    payload.try_into()?,
-                                )))
-                                .unwrap();
-                        }
+                        self.send_definition(response_context, payload)?;
                     }
                     "textDocument/references" => {
                         let payload: <lsp_request!("textDocument/references") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(LspNotification::References(
-                                    response_context,
-                                    payload
-                                        .into_iter()
-                                        .map(|r| r.try_into())
-                                        .collect::<Result<Vec<_>, _>>()?,
-                                )))
-                                .unwrap();
-                        }
+                        // Always respond, so that the results of multiple servers can be merged
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(LspNotification::References(
+                                response_context,
+                                payload
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|r| r.try_into())
+                                    .collect::<Result<Vec<_>, _>>()?,
+                            )))
+                            .unwrap();
                     }
                     "textDocument/declaration" => {
                         let payload: <lsp_request!("textDocument/declaration") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(LspNotification::Definition(
-                                    response_context,
-                                    payload.try_into()?,
-                                )))
-                                .unwrap();
-                        }
+                        self.send_definition(response_context, payload)?;
                     }
                     "textDocument/typeDefinition" => {
                         let payload: <lsp_request!("textDocument/typeDefinition") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(LspNotification::Definition(
-                                    response_context,
-                                    payload.try_into()?,
-                                )))
-                                .unwrap();
-                        }
+                        self.send_definition(response_context, payload)?;
                     }
                     "textDocument/implementation" => {
                         let payload: <lsp_request!("textDocument/implementation") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(LspNotification::Definition(
-                                    response_context,
-                                    payload.try_into()?,
-                                )))
-                                .unwrap();
-                        }
+                        self.send_definition(response_context, payload)?;
                     }
                     "textDocument/prepareRename" => {
                         let payload: <lsp_request!("textDocument/prepareRename") as Request>::Result =
                             serde_json::from_value(response)?;
 
-                        if let Some(payload) = payload {
-                            self.app_message_sender
-                                .send(AppMessage::LspNotification(
-                                    LspNotification::PrepareRenameResponse(payload.into()),
-                                ))
-                                .unwrap();
-                        }
+                        let notification = match payload {
+                            Some(payload) => LspNotification::PrepareRenameResponse(payload.into()),
+                            None => LspNotification::Error(
+                                "The symbol at the cursor cannot be renamed".to_string(),
+                            ),
+                        };
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(notification))
+                            .unwrap();
                     }
                     "textDocument/rename" => {
                         let payload: <lsp_request!("textDocument/rename") as Request>::Result =
@@ -794,7 +1294,9 @@ impl LspServerProcess {
                                     payload
                                         .into_iter()
                                         .map(|r| match r {
-                                            CodeActionOrCommand::Command(_) => todo!(),
+                                            CodeActionOrCommand::Command(command) => {
+                                                Ok(command.into())
+                                            }
                                             CodeActionOrCommand::CodeAction(code_action) => {
                                                 code_action.try_into()
                                             }
@@ -804,6 +1306,36 @@ impl LspServerProcess {
                                 .unwrap();
                         }
                     }
+                    "codeAction/resolve" => {
+                        let payload: <lsp_request!("codeAction/resolve") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(
+                                LspNotification::CodeActionResolved(payload.try_into()?),
+                            ))
+                            .unwrap();
+                    }
+                    "textDocument/formatting" | "textDocument/rangeFormatting" => {
+                        // Both requests share the same result type
+                        let payload: <lsp_request!("textDocument/formatting") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let (Some(payload), Some(path)) = (payload, path) {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(
+                                    LspNotification::DocumentFormatting(TextDocumentEdit {
+                                        path,
+                                        version: None,
+                                        edits: payload
+                                            .into_iter()
+                                            .map(|edit| edit.try_into())
+                                            .collect::<Result<Vec<_>, _>>()?,
+                                    }),
+                                ))
+                                .unwrap();
+                        }
+                    }
                     "textDocument/signatureHelp" => {
                         let payload: <lsp_request!("textDocument/signatureHelp") as Request>::Result =
                             serde_json::from_value(response)?;
@@ -828,13 +1360,169 @@ impl LspServerProcess {
                             }
                         }
                     }
-                    "completionItem/resolve" => {
-                        let payload: <lsp_request!("completionItem/resolve") as Request>::Result =
+                    "textDocument/semanticTokens/full" => {
+                        let payload: <lsp_request!("textDocument/semanticTokens/full") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let (Some(SemanticTokensResult::Tokens(tokens)), Some(path)) =
+                            (payload, path)
+                        {
+                            self.update_semantic_tokens(path, tokens)?;
+                        }
+                    }
+                    "textDocument/semanticTokens/full/delta" => {
+                        let payload: <lsp_request!("textDocument/semanticTokens/full/delta") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let (Some(payload), Some(path)) = (payload, path) {
+                            let tokens = match payload {
+                                SemanticTokensFullDeltaResult::Tokens(tokens) => Some(tokens),
+                                SemanticTokensFullDeltaResult::TokensDelta(delta) => {
+                                    self.semantic_tokens.remove(&path).map(|mut tokens| {
+                                        semantic_tokens::apply_edits(&mut tokens.data, delta.edits);
+                                        SemanticTokens {
+                                            result_id: delta.result_id,
+                                            data: tokens.data,
+                                        }
+                                    })
+                                }
+                                SemanticTokensFullDeltaResult::PartialTokensDelta { .. } => None,
+                            };
+                            if let Some(tokens) = tokens {
+                                self.update_semantic_tokens(path, tokens)?;
+                            }
+                        }
+                    }
+                    "textDocument/inlayHint" => {
+                        let payload: <lsp_request!("textDocument/inlayHint") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let Some(path) = path {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(LspNotification::InlayHints {
+                                    path,
+                                    hints: payload
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(InlayHint::from)
+                                        .collect(),
+                                }))
+                                .unwrap();
+                        }
+                    }
+                    "textDocument/foldingRange" => {
+                        let payload: <lsp_request!("textDocument/foldingRange") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let (Some(payload), Some(path)) = (payload, path) {
+                            let mut ranges = payload
+                                .into_iter()
+                                .map(|range| range.start_line as usize..range.end_line as usize + 1)
+                                .collect::<Vec<_>>();
+                            ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(
+                                    LspNotification::FoldingRanges { path, ranges },
+                                ))
+                                .unwrap();
+                        }
+                    }
+                    "textDocument/documentHighlight" => {
+                        let payload: <lsp_request!("textDocument/documentHighlight") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let Some(path) = path {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(
+                                    LspNotification::DocumentHighlights {
+                                        path,
+                                        highlights: payload
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .map(DocumentHighlight::from)
+                                            .collect(),
+                                    },
+                                ))
+                                .unwrap();
+                        }
+                    }
+                    "textDocument/prepareCallHierarchy" => {
+                        let payload: <lsp_request!("textDocument/prepareCallHierarchy") as Request>::Result =
                             serde_json::from_value(response)?;
 
                         self.app_message_sender
                             .send(AppMessage::LspNotification(
-                                LspNotification::CompletionItemResolve(payload),
+                                LspNotification::CallHierarchyItems(
+                                    response_context,
+                                    payload
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(CallHierarchyItem::try_from)
+                                        .collect::<Result<Vec<_>, _>>()?,
+                                ),
+                            ))
+                            .unwrap();
+                    }
+                    "callHierarchy/incomingCalls" => {
+                        let payload: <lsp_request!("callHierarchy/incomingCalls") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(
+                                LspNotification::CallHierarchyCalls(
+                                    response_context,
+                                    payload
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(CallHierarchyCall::try_from_incoming)
+                                        .collect::<Result<Vec<_>, _>>()?,
+                                ),
+                            ))
+                            .unwrap();
+                    }
+                    "callHierarchy/outgoingCalls" => {
+                        let payload: <lsp_request!("callHierarchy/outgoingCalls") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        if let Some(path) = path {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(
+                                    LspNotification::CallHierarchyCalls(
+                                        response_context,
+                                        payload
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .map(|call| {
+                                                CallHierarchyCall::try_from_outgoing(call, &path)
+                                            })
+                                            .collect::<Result<Vec<_>, _>>()?,
+                                    ),
+                                ))
+                                .unwrap();
+                        }
+                    }
+                    "workspace/symbol" => {
+                        let payload: <lsp_request!("workspace/symbol") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        // Always respond, so that the results of multiple servers can be merged
+                        let symbols = match payload {
+                            Some(payload) => Symbols::try_from_workspace_symbol_response(payload)?,
+                            None => Symbols::default(),
+                        };
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(
+                                LspNotification::WorkspaceSymbols(symbols),
+                            ))
+                            .unwrap();
+                    }
+                    "completionItem/resolve" => {
+                        let payload: <lsp_request!("completionItem/resolve") as Request>::Result =
+                            serde_json::from_value(response)?;
+
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(
+                                LspNotification::CompletionItemResolve(payload),
                             ))
                             .unwrap();
                     }
@@ -846,7 +1534,7 @@ impl LspServerProcess {
 
             // reply is Notification
             Some(_) => {
-                let request = serde_json::from_value::<
+                let mut request = serde_json::from_value::<
                     json_rpc_types::Request<
                         serde_json::Value,
                         // Need to specify String here
@@ -856,6 +1544,9 @@ impl LspServerProcess {
                     >,
                 >(reply)
                 .map_err(|e| anyhow::anyhow!("Serde error = {:?}", e))?;
+                if let Some(params) = request.params.as_mut() {
+                    self.convert_positions(params, None, Conversion::Utf16ToChar);
+                }
 
                 let method = request.method;
                 // Parse the reply as Notification
@@ -864,11 +1555,16 @@ impl LspServerProcess {
                         let params: <lsp_notification!("textDocument/publishDiagnostics") as Notification>::Params =
                             serde_json::from_value(request.params.ok_or_else(|| anyhow::anyhow!("Missing params"))?)?;
 
-                        self.app_message_sender
-                            .send(AppMessage::LspNotification(
-                                LspNotification::PublishDiagnostics(params),
-                            ))
-                            .unwrap();
+                        if self.should_publish_diagnostics(&params) {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(
+                                    LspNotification::PublishDiagnostics {
+                                        server_name: self.lsp_command.name(),
+                                        params,
+                                    },
+                                ))
+                                .unwrap();
+                        }
                     }
                     "workspace/applyEdit" => {
                         let params: <lsp_request!("workspace/applyEdit") as Request>::Params =
@@ -881,26 +1577,70 @@ impl LspServerProcess {
                             .unwrap();
                     }
                     "workspace/configuration" => {
-                        // Just return null for now, since I don't know how how to handle this properly
-                        // This reply is necessary for Graphql LSP to work
-
-                        self.send_reply(request.id, serde_json::Value::Null)?;
+                        let params: <lsp_request!("workspace/configuration") as Request>::Params =
+                            serde_json::from_value(
+                                request
+                                    .params
+                                    .ok_or_else(|| anyhow::anyhow!("Missing params"))?,
+                            )?;
+                        // This reply is necessary for Graphql LSP to work, even if nothing is configured
+                        let result = self.configuration_items(&params.items);
+                        self.send_reply(request.id, result)?;
                     }
                     "window/logMessage" => {
                         let command = self.lsp_command();
                         let params: <lsp_notification!("window/logMessage") as Notification>::Params =
                             serde_json::from_value(request.params.ok_or_else(|| anyhow::anyhow!("Missing params"))?)?;
-                        let typ = match params.typ {
-                            MessageType::LOG => "LOG".to_string(),
-                            MessageType::ERROR => "ERROR".to_string(),
-                            MessageType::WARNING => "WARNING".to_string(),
-                            MessageType::INFO => "INFO".to_string(),
-                            _ => format!("[Unknown message type {:?}]", params.typ),
-                        };
-                        log::info!(
-                            "LSP(window/logMessage)({command})[{typ}]: '{}'",
+                        let level = log_level(params.typ);
+                        log::log!(
+                            level,
+                            "LSP(window/logMessage)({command}): '{}'",
                             params.message
-                        )
+                        );
+                        // Other messages are too noisy to be shown
+                        if level == log::Level::Error {
+                            self.app_message_sender
+                                .send(AppMessage::LspNotification(LspNotification::Log {
+                                    server_name: self.lsp_command.name(),
+                                    level,
+                                    message: params.message,
+                                }))
+                                .unwrap();
+                        }
+                    }
+                    "window/showMessage" => {
+                        let params: <lsp_notification!("window/showMessage") as Notification>::Params =
+                            serde_json::from_value(request.params.ok_or_else(|| anyhow::anyhow!("Missing params"))?)?;
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(LspNotification::Log {
+                                server_name: self.lsp_command.name(),
+                                level: log_level(params.typ),
+                                message: params.message,
+                            }))
+                            .unwrap();
+                    }
+                    "window/workDoneProgress/create" => {
+                        // The progress is reported via `$/progress` notifications
+                        self.send_reply(request.id, serde_json::Value::Null)?;
+                    }
+                    "$/progress" => {
+                        let params: <lsp_notification!("$/progress") as Notification>::Params =
+                            serde_json::from_value(
+                                request
+                                    .params
+                                    .ok_or_else(|| anyhow::anyhow!("Missing params"))?,
+                            )?;
+                        let ProgressParamsValue::WorkDone(progress) = params.value;
+                        self.app_message_sender
+                            .send(AppMessage::LspNotification(LspNotification::Progress {
+                                server_name: self.lsp_command.name(),
+                                token: match params.token {
+                                    NumberOrString::Number(number) => number.to_string(),
+                                    NumberOrString::String(string) => string,
+                                },
+                                progress,
+                            }))
+                            .unwrap();
                     }
 
                     _ => log::info!("unhandled Incoming Notification: {}", method),
@@ -911,6 +1651,24 @@ impl LspServerProcess {
         Ok(())
     }
 
+    /// Always sent, even if there is no definition,
+    /// so that the results of multiple servers can be merged.
+    fn send_definition(
+        &self,
+        context: ResponseContext,
+        payload: Option<lsp_types::GotoDefinitionResponse>,
+    ) -> anyhow::Result<()> {
+        let response = match payload {
+            Some(payload) => payload.try_into()?,
+            None => GotoDefinitionResponse::Multiple(Vec::new()),
+        };
+        self.app_message_sender
+            .send(AppMessage::LspNotification(LspNotification::Definition(
+                context, response,
+            )))?;
+        Ok(())
+    }
+
     fn trigger_characters(&self) -> Vec<String> {
         self.server_capabilities
             .as_ref()
@@ -947,6 +1705,32 @@ impl LspServerProcess {
         Ok(())
     }
 
+    /// The settings of each of `items`, taken from the configuration of this language,
+    /// where the section `a.b` refers to `settings["a"]["b"]`.
+    ///
+    /// Unknown sections are `null`.
+    fn configuration_items(&self, items: &[ConfigurationItem]) -> serde_json::Value {
+        let configurations = self.configurations.lock().unwrap();
+        let settings = self
+            .language
+            .id()
+            .and_then(|language_id| configurations.get(&language_id));
+        items
+            .iter()
+            .map(|item| {
+                settings
+                    .and_then(|settings| match item.section.as_deref() {
+                        None | Some("") => Some(settings),
+                        Some(section) => section
+                            .split('.')
+                            .try_fold(settings, |settings, key| settings.get(key)),
+                    })
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Null)
+            })
+            .collect()
+    }
+
     /// Used for sending response to reponses of the LSP server
     fn send_reply(
         &mut self,
@@ -980,6 +1764,25 @@ impl LspServerProcess {
         Ok(())
     }
 
+    /// `path` is the document of the request, which is assumed for positions
+    /// that are not accompanied by the URI of their document.
+    ///
+    /// Documents that are not opened are read from the disk.
+    fn convert_positions(
+        &self,
+        value: &mut serde_json::Value,
+        path: Option<&CanonicalizedPath>,
+        conversion: Conversion,
+    ) {
+        let uri = path.and_then(|path| path_buf_to_url(path.clone()).ok());
+        position_encoding::convert_positions(value, uri.as_ref(), conversion, |uri| {
+            self.documents.get(uri).cloned().or_else(|| {
+                let content = std::fs::read_to_string(uri.to_file_path().ok()?).ok()?;
+                Some(Rope::from_str(&content))
+            })
+        })
+    }
+
     /// Returns the request ID
     fn send_request<R: Request>(
         &mut self,
@@ -990,11 +1793,16 @@ impl LspServerProcess {
     where
         R::Params: serde::Serialize,
     {
+        if is_superseded_by_newer_request(R::METHOD) {
+            self.cancel_pending_requests(R::METHOD, path.as_ref())?;
+        }
         let id = {
             let result = self.next_request_id;
             self.next_request_id += 1;
             result
         };
+        let mut params = serde_json::to_value(params)?;
+        self.convert_positions(&mut params, path.as_ref(), Conversion::CharToUtf16);
         // Convert the request to a JSON-RPC message
         let request = json_rpc_types::Request {
             jsonrpc: json_rpc_types::Version::V2,
@@ -1005,18 +1813,82 @@ impl LspServerProcess {
 
         self.send_json(&request)?;
 
-        self.pending_response_requests.insert(
+        let context = ResponseContext {
+            document_version: path
+                .as_ref()
+                .and_then(|path| self.document_versions.get(path).copied()),
+            path: path.clone(),
+            ..context
+        };
+        self.pending_response_requests.lock().unwrap().insert(
             id,
             PendingResponseRequest {
                 context,
                 method: R::METHOD.to_string(),
                 path,
+                sent_at: Instant::now(),
             },
         );
 
         Ok(())
     }
 
+    /// Cancel the pending requests of the given `method` and `path`,
+    /// so that their responses, if any, are ignored.
+    fn cancel_pending_requests(
+        &mut self,
+        method: &str,
+        path: Option<&CanonicalizedPath>,
+    ) -> anyhow::Result<()> {
+        let ids = self
+            .pending_response_requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, request)| request.method == method && request.path.as_ref() == path)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.cancel_request(id)?;
+        }
+        Ok(())
+    }
+
+    /// Cancel the pending requests that are not responded within the request timeout,
+    /// and notify the editor about them.
+    fn cancel_timed_out_requests(&mut self) -> anyhow::Result<()> {
+        let ids = self
+            .pending_response_requests
+            .lock()
+            .unwrap()
+            .iter()
+            // The server might take a while to start up
+            .filter(|(_, request)| {
+                request.method != "initialize" && request.sent_at.elapsed() >= self.request_timeout
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            if let Some(request) = self.cancel_request(id)? {
+                self.app_message_sender.send(AppMessage::LspNotification(
+                    LspNotification::RequestTimedOut {
+                        method: request.method,
+                        path: request.path,
+                    },
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel_request(&mut self, id: RequestId) -> anyhow::Result<Option<PendingResponseRequest>> {
+        let request = self.pending_response_requests.lock().unwrap().remove(&id);
+        self.send_notification::<lsp_notification!("$/cancelRequest")>(CancelParams {
+            id: NumberOrString::Number(id as i32),
+        })?;
+        Ok(request)
+    }
+
     fn text_document_did_open(
         &mut self,
         file_path: CanonicalizedPath,
@@ -1024,6 +1896,12 @@ impl LspServerProcess {
         version: usize,
         content: String,
     ) -> Result<(), anyhow::Error> {
+        self.document_versions
+            .insert(file_path.clone(), version as i32);
+        self.documents.insert(
+            path_buf_to_url(file_path.clone())?,
+            Rope::from_str(&content),
+        );
         self.send_notification::<lsp_notification!("textDocument/didOpen")>(
             DidOpenTextDocumentParams {
                 text_document: TextDocumentItem {
@@ -1036,23 +1914,87 @@ impl LspServerProcess {
         )
     }
 
+    /// Sends the pending changes, or only those that are due if `only_due` is true.
+    fn send_pending_changes(&mut self, only_due: bool) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let paths = self
+            .pending_changes
+            .iter()
+            .filter(|(_, change)| !only_due || change.due_at <= now)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        consolidate_errors(
+            "[send_pending_changes]",
+            paths
+                .into_iter()
+                .filter_map(|path| {
+                    let change = self.pending_changes.remove(&path)?;
+                    Some(self.text_document_did_change(
+                        path,
+                        change.base_version,
+                        change.version,
+                        change.content,
+                        change.changes,
+                    ))
+                })
+                .collect(),
+        )
+    }
+
+    fn supports_incremental_sync(&self) -> bool {
+        matches!(
+            self.server_capabilities
+                .as_ref()
+                .and_then(|capabilities| capabilities.text_document_sync.as_ref()),
+            Some(
+                TextDocumentSyncCapability::Kind(TextDocumentSyncKind::INCREMENTAL)
+                    | TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        ..
+                    })
+            )
+        )
+    }
+
+    /// `changes` are only sent if they are based on the version of the document
+    /// known by the server, otherwise the full `content` is sent.
     fn text_document_did_change(
         &mut self,
         file_path: CanonicalizedPath,
+        base_version: i32,
         version: i32,
         content: String,
+        changes: Option<Vec<TextDocumentContentChangeEvent>>,
     ) -> Result<(), anyhow::Error> {
+        let is_based_on_known_version =
+            self.document_versions.get(&file_path) == Some(&base_version);
+        self.documents.insert(
+            path_buf_to_url(file_path.clone())?,
+            Rope::from_str(&content),
+        );
+        let content_changes = match changes {
+            Some(changes)
+                if !changes.is_empty()
+                    && is_based_on_known_version
+                    && self.supports_incremental_sync() =>
+            {
+                changes
+            }
+            _ => vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: content,
+            }],
+        };
+        self.document_versions.insert(file_path.clone(), version);
+        // The ranges of `content_changes` are already measured in UTF-16 code units
         self.send_notification::<lsp_notification!("textDocument/didChange")>(
             DidChangeTextDocumentParams {
                 text_document: VersionedTextDocumentIdentifier {
                     uri: path_buf_to_url(file_path)?,
                     version,
                 },
-                content_changes: vec![TextDocumentContentChangeEvent {
-                    range: None,
-                    range_length: None,
-                    text: content,
-                }],
+                content_changes,
             },
         )
     }
@@ -1069,6 +2011,36 @@ impl LspServerProcess {
         )
     }
 
+    fn workspace_did_change_watched_files(
+        &mut self,
+        changes: Vec<(CanonicalizedPath, FileChangeType)>,
+    ) -> Result<(), anyhow::Error> {
+        self.send_notification::<lsp_notification!("workspace/didChangeWatchedFiles")>(
+            DidChangeWatchedFilesParams {
+                changes: changes
+                    .into_iter()
+                    .map(|(path, typ)| {
+                        Ok(FileEvent {
+                            uri: path_buf_to_url(path)?,
+                            typ,
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            },
+        )
+    }
+
+    fn text_document_did_close(
+        &mut self,
+        file_path: CanonicalizedPath,
+    ) -> Result<(), anyhow::Error> {
+        self.send_notification::<lsp_notification!("textDocument/didClose")>(
+            DidCloseTextDocumentParams {
+                text_document: path_buf_to_text_document_identifier(file_path)?,
+            },
+        )
+    }
+
     fn workspace_did_rename_files(
         &mut self,
         old: CanonicalizedPath,
@@ -1272,6 +2244,25 @@ impl LspServerProcess {
         if !self.has_capability(|c| c.rename_provider.is_some()) {
             return Ok(());
         }
+        if !self.has_capability(|c| {
+            matches!(
+                c.rename_provider,
+                Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    ..
+                }))
+            )
+        }) {
+            // The server cannot validate the rename location,
+            // so skip straight to asking for the new name
+            self.app_message_sender.send(AppMessage::LspNotification(
+                LspNotification::PrepareRenameResponse(PrepareRenameResponse {
+                    range: None,
+                    placeholder: None,
+                }),
+            ))?;
+            return Ok(());
+        }
         self.send_request::<lsp_request!("textDocument/prepareRename")>(
             params.context,
             Some(params.path.clone()),
@@ -1307,6 +2298,7 @@ impl LspServerProcess {
     fn text_document_code_action(
         &mut self,
         params: RequestParams,
+        range: std::ops::Range<crate::position::Position>,
         diagnostics: Vec<Diagnostic>,
     ) -> Result<(), anyhow::Error> {
         if !self.has_capability(|c| c.code_action_provider.is_some()) {
@@ -1323,8 +2315,8 @@ impl LspServerProcess {
                 },
                 partial_result_params: Default::default(),
                 range: Range {
-                    start: params.position.into(),
-                    end: params.position.into(),
+                    start: range.start.into(),
+                    end: range.end.into(),
                 },
                 text_document: path_buf_to_text_document_identifier(params.path)?,
                 work_done_progress_params: Default::default(),
@@ -1332,6 +2324,121 @@ impl LspServerProcess {
         )
     }
 
+    fn code_action_resolve(
+        &mut self,
+        params: RequestParams,
+        code_action: CodeAction,
+    ) -> Result<(), anyhow::Error> {
+        let can_resolve = self.has_capability(|c| {
+            matches!(
+                c.code_action_provider,
+                Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                    resolve_provider: Some(true),
+                    ..
+                }))
+            )
+        });
+        match code_action.unresolved.clone() {
+            Some(unresolved) if can_resolve => self
+                .send_request::<lsp_request!("codeAction/resolve")>(
+                    params.context,
+                    Some(params.path),
+                    unresolved.into_inner(),
+                ),
+            // Nothing to resolve, apply the code action as it is
+            _ => {
+                self.app_message_sender.send(AppMessage::LspNotification(
+                    LspNotification::CodeActionResolved(code_action),
+                ))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Only the latest set of diagnostics of each file is kept.
+    /// Returns false if `params` is outdated (its version is older than the
+    /// latest published version) or identical to the latest published set.
+    fn should_publish_diagnostics(&mut self, params: &PublishDiagnosticsParams) -> bool {
+        if let Some(latest) = self.published_diagnostics.get(&params.uri) {
+            let is_outdated = matches!(
+                (latest.version, params.version),
+                (Some(latest), Some(version)) if version < latest
+            );
+            if is_outdated || latest == params {
+                return false;
+            }
+        }
+        self.published_diagnostics
+            .insert(params.uri.clone(), params.clone());
+        true
+    }
+
+    fn workspace_symbol(&mut self, query: String) -> Result<(), anyhow::Error> {
+        self.send_request::<lsp_request!("workspace/symbol")>(
+            ResponseContext::default().set_description("Workspace Symbols"),
+            None,
+            WorkspaceSymbolParams {
+                query,
+                partial_result_params: Default::default(),
+                work_done_progress_params: Default::default(),
+            },
+        )
+    }
+
+    fn formatting_options() -> FormattingOptions {
+        FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+            ..Default::default()
+        }
+    }
+
+    fn text_document_formatting(&mut self, params: RequestParams) -> Result<(), anyhow::Error> {
+        if !self.has_capability(|c| c.document_formatting_provider.is_some()) {
+            self.app_message_sender
+                .send(AppMessage::LspNotification(LspNotification::Error(
+                    "The language server does not support formatting".to_string(),
+                )))?;
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/formatting")>(
+            params.context,
+            Some(params.path.clone()),
+            DocumentFormattingParams {
+                text_document: path_buf_to_text_document_identifier(params.path)?,
+                options: Self::formatting_options(),
+                work_done_progress_params: Default::default(),
+            },
+        )
+    }
+
+    fn text_document_range_formatting(
+        &mut self,
+        params: RequestParams,
+        range: std::ops::Range<crate::position::Position>,
+    ) -> Result<(), anyhow::Error> {
+        if !self.has_capability(|c| c.document_range_formatting_provider.is_some()) {
+            self.app_message_sender
+                .send(AppMessage::LspNotification(LspNotification::Error(
+                    "The language server does not support range formatting".to_string(),
+                )))?;
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/rangeFormatting")>(
+            params.context,
+            Some(params.path.clone()),
+            DocumentRangeFormattingParams {
+                text_document: path_buf_to_text_document_identifier(params.path)?,
+                range: Range {
+                    start: range.start.into(),
+                    end: range.end.into(),
+                },
+                options: Self::formatting_options(),
+                work_done_progress_params: Default::default(),
+            },
+        )
+    }
+
     pub(crate) fn text_document_signature_help(
         &mut self,
         params: RequestParams,
@@ -1371,6 +2478,201 @@ impl LspServerProcess {
         )
     }
 
+    /// Only the delta since the previous response is requested
+    /// if the server supports it.
+    fn text_document_semantic_tokens(&mut self, params: RequestParams) -> anyhow::Result<()> {
+        let Some(capabilities) = self
+            .server_capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.semantic_tokens_provider.as_ref())
+        else {
+            return Ok(());
+        };
+        let previous_result_id = self
+            .semantic_tokens
+            .get(&params.path)
+            .and_then(|tokens| tokens.result_id.clone())
+            .filter(|_| semantic_tokens::supports_delta(capabilities));
+        let text_document = path_buf_to_text_document_identifier(params.path.clone())?;
+        match previous_result_id {
+            Some(previous_result_id) => self
+                .send_request::<lsp_request!("textDocument/semanticTokens/full/delta")>(
+                    params.context,
+                    Some(params.path),
+                    SemanticTokensDeltaParams {
+                        text_document,
+                        previous_result_id,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                ),
+            None => self.send_request::<lsp_request!("textDocument/semanticTokens/full")>(
+                params.context,
+                Some(params.path),
+                SemanticTokensParams {
+                    text_document,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                },
+            ),
+        }
+    }
+
+    fn text_document_inlay_hint(
+        &mut self,
+        params: RequestParams,
+        range: std::ops::Range<crate::position::Position>,
+    ) -> anyhow::Result<()> {
+        if !self.has_capability(|c| {
+            matches!(
+                c.inlay_hint_provider,
+                Some(OneOf::Left(true) | OneOf::Right(_))
+            )
+        }) {
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/inlayHint")>(
+            params.context,
+            Some(params.path.clone()),
+            InlayHintParams {
+                text_document: path_buf_to_text_document_identifier(params.path)?,
+                range: Range {
+                    start: range.start.into(),
+                    end: range.end.into(),
+                },
+                work_done_progress_params: Default::default(),
+            },
+        )
+    }
+
+    fn text_document_folding_range(&mut self, params: RequestParams) -> anyhow::Result<()> {
+        if !self.has_capability(|c| {
+            !matches!(
+                c.folding_range_provider,
+                None | Some(FoldingRangeProviderCapability::Simple(false))
+            )
+        }) {
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/foldingRange")>(
+            params.context,
+            Some(params.path.clone()),
+            FoldingRangeParams {
+                text_document: path_buf_to_text_document_identifier(params.path)?,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        )
+    }
+
+    /// Like folding ranges, document highlights are requested automatically as the cursor moves,
+    /// so they are silently skipped if unsupported.
+    fn text_document_document_highlight(
+        &mut self,
+        RequestParams {
+            path,
+            position,
+            context,
+        }: RequestParams,
+    ) -> anyhow::Result<()> {
+        if !self.has_capability(|c| ServerCapability::DocumentHighlight.is_supported_by(c)) {
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/documentHighlight")>(
+            context,
+            Some(path.clone()),
+            DocumentHighlightParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    position: position.into(),
+                    text_document: path_buf_to_text_document_identifier(path)?,
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        )
+    }
+
+    fn text_document_prepare_call_hierarchy(
+        &mut self,
+        RequestParams {
+            path,
+            position,
+            context,
+        }: RequestParams,
+    ) -> anyhow::Result<()> {
+        if !self.has_capability(|c| ServerCapability::CallHierarchy.is_supported_by(c)) {
+            return Ok(());
+        }
+        self.send_request::<lsp_request!("textDocument/prepareCallHierarchy")>(
+            context,
+            Some(path.clone()),
+            CallHierarchyPrepareParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    position: position.into(),
+                    text_document: path_buf_to_text_document_identifier(path)?,
+                },
+                work_done_progress_params: Default::default(),
+            },
+        )
+    }
+
+    fn call_hierarchy_incoming_calls(
+        &mut self,
+        params: RequestParams,
+        item: lsp_types::CallHierarchyItem,
+    ) -> anyhow::Result<()> {
+        self.send_request::<lsp_request!("callHierarchy/incomingCalls")>(
+            params.context,
+            Some(params.path),
+            CallHierarchyIncomingCallsParams {
+                item,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        )
+    }
+
+    /// The path of `params` must be the file of `item`,
+    /// because the ranges of outgoing calls are relative to it.
+    fn call_hierarchy_outgoing_calls(
+        &mut self,
+        params: RequestParams,
+        item: lsp_types::CallHierarchyItem,
+    ) -> anyhow::Result<()> {
+        self.send_request::<lsp_request!("callHierarchy/outgoingCalls")>(
+            params.context,
+            Some(params.path),
+            CallHierarchyOutgoingCallsParams {
+                item,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        )
+    }
+
+    fn update_semantic_tokens(
+        &mut self,
+        path: CanonicalizedPath,
+        tokens: SemanticTokens,
+    ) -> anyhow::Result<()> {
+        let Some(capabilities) = self
+            .server_capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.semantic_tokens_provider.as_ref())
+        else {
+            return Ok(());
+        };
+        let decoded = semantic_tokens::decode(&tokens.data, semantic_tokens::legend(capabilities));
+        self.semantic_tokens.insert(path.clone(), tokens);
+        self.app_message_sender.send(AppMessage::LspNotification(
+            LspNotification::SemanticTokens {
+                path,
+                tokens: decoded,
+            },
+        ))?;
+        Ok(())
+    }
+
     fn workspace_execute_command(
         &mut self,
         params: RequestParams,
@@ -1440,11 +2742,42 @@ impl LspServerProcess {
             }
             FromEditor::TextDocumentCodeAction {
                 params,
+                range,
                 diagnostics,
-            } => self.text_document_code_action(params, diagnostics),
+            } => self.text_document_code_action(params, range, diagnostics),
+            FromEditor::CodeActionResolve {
+                params,
+                code_action,
+            } => self.code_action_resolve(params, code_action),
             FromEditor::TextDocumentDocumentSymbol(params) => {
                 self.text_document_document_symbol(params)
             }
+            FromEditor::TextDocumentSemanticTokens(params) => {
+                self.text_document_semantic_tokens(params)
+            }
+            FromEditor::TextDocumentInlayHint { params, range } => {
+                self.text_document_inlay_hint(params, range)
+            }
+            FromEditor::TextDocumentFoldingRange(params) => {
+                self.text_document_folding_range(params)
+            }
+            FromEditor::TextDocumentDocumentHighlight(params) => {
+                self.text_document_document_highlight(params)
+            }
+            FromEditor::TextDocumentPrepareCallHierarchy(params) => {
+                self.text_document_prepare_call_hierarchy(params)
+            }
+            FromEditor::CallHierarchyIncomingCalls { params, item } => {
+                self.call_hierarchy_incoming_calls(params, item)
+            }
+            FromEditor::CallHierarchyOutgoingCalls { params, item } => {
+                self.call_hierarchy_outgoing_calls(params, item)
+            }
+            FromEditor::WorkspaceSymbol { query } => self.workspace_symbol(query),
+            FromEditor::TextDocumentFormatting(params) => self.text_document_formatting(params),
+            FromEditor::TextDocumentRangeFormatting { params, range } => {
+                self.text_document_range_formatting(params, range)
+            }
 
             FromEditor::TextDocumentDidOpen {
                 file_path,
@@ -1457,8 +2790,24 @@ impl LspServerProcess {
                 file_path,
                 version,
                 content,
-            } => self.text_document_did_change(file_path, version, content),
+                changes,
+            } => self.text_document_did_change(file_path, version - 1, version, content, changes),
             FromEditor::TextDocumentDidSave { file_path } => self.text_document_did_save(file_path),
+            FromEditor::WorkspaceDidChangeWatchedFiles { changes } => {
+                self.workspace_did_change_watched_files(changes)
+            }
+            FromEditor::WorkspaceDidChangeConfiguration { settings } => self
+                .send_notification::<lsp_notification!("workspace/didChangeConfiguration")>(
+                    DidChangeConfigurationParams { settings },
+                ),
+            FromEditor::TextDocumentDidClose { file_path } => {
+                self.semantic_tokens.remove(&file_path);
+                self.document_versions.remove(&file_path);
+                if let Ok(url) = path_buf_to_url(file_path.clone()) {
+                    self.documents.remove(&url);
+                }
+                self.text_document_did_close(file_path)
+            }
             FromEditor::TextDocumentSignatureHelp(params) => {
                 self.text_document_signature_help(params)
             }
@@ -1482,10 +2831,7 @@ impl LspServerProcess {
     }
 
     fn lsp_command(&self) -> String {
-        self.language
-            .lsp_process_command()
-            .map(|command| command.to_string())
-            .unwrap_or_default()
+        self.lsp_command.process_command().to_string()
     }
 }
 
@@ -1501,6 +2847,15 @@ fn path_buf_to_text_document_identifier(
     })
 }
 
+fn log_level(message_type: MessageType) -> log::Level {
+    match message_type {
+        MessageType::ERROR => log::Level::Error,
+        MessageType::WARNING => log::Level::Warn,
+        MessageType::INFO => log::Level::Info,
+        _ => log::Level::Debug,
+    }
+}
+
 /// `ErrorTracker` is created for preventing infinite error loops in LSP communication.
 ///
 /// This exists because some LSP servers can enter states where they continuously emit
@@ -1581,35 +2936,54 @@ mod test_lsp_server_process {
     use std::process::Command;
     use std::sync::mpsc;
 
-    #[test]
-    fn lsp_should_shutdown_after_too_many_consecutive_errors() -> anyhow::Result<()> {
-        let (app_sender, app_receiver) = mpsc::channel();
-        let (sender, receiver) = mpsc::channel();
-
-        // Create a process that will output invalid LSP data quickly
-        let mut process = Command::new("sh")
-            .args(["-c", "for i in {1..10}; do echo 'invalid data'; done"])
+    /// Spawns `command` as the language server.
+    ///
+    /// The child process is returned too, so that tests can wait for it or kill it.
+    fn test_process(
+        mut command: Command,
+        app_sender: Sender<AppMessage>,
+        sender: Sender<LspServerProcessMessage>,
+    ) -> anyhow::Result<(LspServerProcess, std::process::Child)> {
+        let mut process = command
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .spawn()?;
-
-        let stdin = process.stdin.take().unwrap();
-        let stdout = process.stdout.take().unwrap();
-        let stderr = process.stderr.take().unwrap();
-
         let lsp_process = LspServerProcess {
             language: Language::default(),
-            stdin,
-            stdout: Some(stdout),
-            stderr: Some(stderr),
+            lsp_command: LspCommand::default(),
+            process_id: process.id(),
+            stdin: process.stdin.take().unwrap(),
+            stdout: process.stdout.take(),
+            stderr: process.stderr.take(),
             server_capabilities: None,
             current_working_directory: std::env::current_dir()?.try_into()?,
             next_request_id: 0,
-            pending_response_requests: HashMap::new(),
-            app_message_sender: app_sender.clone(),
+            pending_response_requests: Default::default(),
+            configurations: Default::default(),
+            published_diagnostics: HashMap::new(),
+            semantic_tokens: HashMap::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            change_debounce: DEFAULT_CHANGE_DEBOUNCE,
+            pending_changes: HashMap::new(),
+            document_versions: HashMap::new(),
+            documents: HashMap::new(),
+            app_message_sender: app_sender,
             sender,
         };
+        Ok((lsp_process, process))
+    }
+
+    #[test]
+    fn lsp_should_shutdown_after_too_many_consecutive_errors() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        // Create a process that will output invalid LSP data quickly
+        let mut command = Command::new("sh");
+        command.args(["-c", "for i in {1..10}; do echo 'invalid data'; done"]);
+        let (lsp_process, mut process) = test_process(command, app_sender.clone(), sender)?;
+        let lsp_process_id = process.id();
 
         // Start listening in a separate thread
         let handle = lsp_process.listen(receiver, app_sender);
@@ -1626,6 +3000,14 @@ mod test_lsp_server_process {
             other => panic!("Expected error notification, got: {:?}", other),
         }
 
+        // The app is notified so that the server can be restarted
+        match app_receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(AppMessage::LspNotification(LspNotification::ServerExited {
+                process_id, ..
+            })) => assert_eq!(process_id, lsp_process_id),
+            other => panic!("Expected server exited notification, got: {:?}", other),
+        }
+
         // Verify the thread has actually finished by waiting a short time
         // If join returns Ok, it means the thread completed (loop was escaped)
         // If it's still running, join_timeout would return Err
@@ -1636,4 +3018,448 @@ mod test_lsp_server_process {
         );
         Ok(())
     }
+
+    #[test]
+    fn only_the_latest_diagnostics_of_each_file_are_published() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+
+        let publish = |lsp_process: &mut LspServerProcess,
+                       version: i32,
+                       message: &str|
+         -> anyhow::Result<()> {
+            lsp_process.handle_reply(serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": {
+                    "uri": "file:///main.rs",
+                    "version": version,
+                    "diagnostics": [{
+                        "range": {
+                            "start": { "line": 0, "character": 0 },
+                            "end": { "line": 0, "character": 1 }
+                        },
+                        "message": message
+                    }]
+                }
+            }))
+        };
+        let published_messages = || {
+            app_receiver
+                .try_iter()
+                .map(|message| match message {
+                    AppMessage::LspNotification(LspNotification::PublishDiagnostics {
+                        params,
+                        ..
+                    }) => params.diagnostics[0].message.clone(),
+                    other => panic!("Expected diagnostics, got: {:?}", other),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        publish(&mut lsp_process, 2, "first")?;
+        // Identical diagnostics should not be published again
+        publish(&mut lsp_process, 2, "first")?;
+        // Outdated diagnostics should be ignored
+        publish(&mut lsp_process, 1, "outdated")?;
+        publish(&mut lsp_process, 3, "second")?;
+
+        assert_eq!(published_messages(), ["first", "second"]);
+
+        process.kill()?;
+        process.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn newer_request_should_cancel_pending_request_of_the_same_kind() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+
+        // `cat` echoes whatever is sent to the server
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+        let mut stdout = lsp_process.stdout.take().unwrap();
+
+        let path: CanonicalizedPath = std::env::current_dir()?.join("Cargo.toml").try_into()?;
+        let hover = |lsp_process: &mut LspServerProcess| {
+            lsp_process.send_request::<lsp_request!("textDocument/hover")>(
+                Default::default(),
+                Some(path.clone()),
+                HoverParams {
+                    text_document_position_params: TextDocumentPositionParams {
+                        position: Default::default(),
+                        text_document: path_buf_to_text_document_identifier(path.clone())?,
+                    },
+                    work_done_progress_params: Default::default(),
+                },
+            )
+        };
+        hover(&mut lsp_process)?;
+        hover(&mut lsp_process)?;
+        assert_eq!(
+            lsp_process.pending_response_requests.lock().unwrap().len(),
+            1
+        );
+
+        // The response of the cancelled request should be ignored
+        lsp_process.handle_reply(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": null
+        }))?;
+        assert!(app_receiver.try_recv().is_err());
+
+        // Close stdin so that `cat` exits
+        drop(lsp_process);
+        let mut sent = String::new();
+        stdout.read_to_string(&mut sent)?;
+        process.wait()?;
+
+        let cancel = sent.find("$/cancelRequest").unwrap();
+        let hovers = sent
+            .match_indices("textDocument/hover")
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(hovers.len(), 2);
+        assert!(hovers[0] < cancel && cancel < hovers[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn rapid_changes_are_coalesced_and_sent_before_save() -> anyhow::Result<()> {
+        let (app_sender, _app_receiver) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        // `cat` echoes whatever is sent to the server
+        let (mut lsp_process, mut process) =
+            test_process(Command::new("cat"), app_sender, sender.clone())?;
+        let mut stdout = lsp_process.stdout.take().unwrap();
+        lsp_process.change_debounce = Duration::from_secs(60);
+
+        let path: CanonicalizedPath = std::env::current_dir()?.join("Cargo.toml").try_into()?;
+        for (version, content) in [(2, "first change"), (3, "second change")] {
+            sender.send(LspServerProcessMessage::FromEditor(
+                FromEditor::TextDocumentDidChange {
+                    file_path: path.clone(),
+                    version,
+                    content: content.to_string(),
+                    changes: None,
+                },
+            ))?;
+        }
+        sender.send(LspServerProcessMessage::FromEditor(
+            FromEditor::TextDocumentDidSave {
+                file_path: path.clone(),
+            },
+        ))?;
+        sender.send(LspServerProcessMessage::Shutdown)?;
+        lsp_process.process_messages(receiver);
+
+        // Close stdin so that `cat` exits
+        drop(lsp_process);
+        let mut sent = String::new();
+        stdout.read_to_string(&mut sent)?;
+        process.wait()?;
+
+        assert!(!sent.contains("first change"));
+        let change = sent.find("second change").unwrap();
+        let save = sent.find("textDocument/didSave").unwrap();
+        assert!(change < save);
+        Ok(())
+    }
+
+    #[test]
+    fn incremental_changes_are_only_sent_if_based_on_the_known_version() -> anyhow::Result<()> {
+        let (app_sender, _app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+
+        // `cat` echoes whatever is sent to the server
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+        let mut stdout = lsp_process.stdout.take().unwrap();
+        lsp_process.change_debounce = Duration::ZERO;
+        lsp_process.server_capabilities = Some(ServerCapabilities {
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                TextDocumentSyncKind::INCREMENTAL,
+            )),
+            ..Default::default()
+        });
+
+        let path: CanonicalizedPath = std::env::current_dir()?.join("Cargo.toml").try_into()?;
+        let did_change = |version, content: &str| FromEditor::TextDocumentDidChange {
+            file_path: path.clone(),
+            version,
+            content: content.to_string(),
+            changes: Some(vec![TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(0, 0), Position::new(0, 0))),
+                range_length: None,
+                text: format!("incremental {version}"),
+            }]),
+        };
+        lsp_process.handle_from_editor(&FromEditor::TextDocumentDidOpen {
+            file_path: path.clone(),
+            language_id: "toml".to_string(),
+            version: 1,
+            content: String::new(),
+        });
+        lsp_process.handle_from_editor(&did_change(2, "full 2"));
+        // Version 3 was never notified, so the server cannot apply the changes of version 4
+        lsp_process.handle_from_editor(&did_change(4, "full 4"));
+
+        // Close stdin so that `cat` exits
+        drop(lsp_process);
+        let mut sent = String::new();
+        stdout.read_to_string(&mut sent)?;
+        process.wait()?;
+
+        assert!(sent.contains("incremental 2") && !sent.contains("full 2"));
+        assert!(sent.contains("full 4") && !sent.contains("incremental 4"));
+        Ok(())
+    }
+
+    #[test]
+    fn timed_out_requests_should_be_cancelled_and_reported() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+        lsp_process.request_timeout = Duration::from_millis(100);
+
+        lsp_process.send_request::<lsp_request!("workspace/symbol")>(
+            Default::default(),
+            None,
+            WorkspaceSymbolParams {
+                query: "foo".to_string(),
+                ..Default::default()
+            },
+        )?;
+
+        // Not timed out yet
+        lsp_process.cancel_timed_out_requests()?;
+        assert_eq!(
+            lsp_process.pending_response_requests.lock().unwrap().len(),
+            1
+        );
+
+        thread::sleep(Duration::from_millis(150));
+        lsp_process.cancel_timed_out_requests()?;
+        assert!(lsp_process
+            .pending_response_requests
+            .lock()
+            .unwrap()
+            .is_empty());
+        match app_receiver.try_recv() {
+            Ok(AppMessage::LspNotification(LspNotification::RequestTimedOut { method, path })) => {
+                assert_eq!(method, "workspace/symbol");
+                assert_eq!(path, None);
+            }
+            other => panic!("Expected request timed out notification, got: {:?}", other),
+        }
+
+        process.kill()?;
+        process.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn error_responses_are_reported_as_failed_requests() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+        lsp_process.send_request::<lsp_request!("workspace/symbol")>(
+            Default::default(),
+            None,
+            WorkspaceSymbolParams {
+                query: "foo".to_string(),
+                ..Default::default()
+            },
+        )?;
+        assert!(lsp_process
+            .handle_reply(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "error": { "code": -32603, "message": "Internal error" }
+            }))
+            .is_err());
+
+        // So that the symbols of the other servers are not waiting for this server forever
+        assert!(app_receiver.try_iter().any(|message| matches!(
+            message,
+            AppMessage::LspNotification(LspNotification::RequestFailed { method })
+                if method == "workspace/symbol"
+        )));
+
+        process.kill()?;
+        process.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn requests_time_out_even_if_messages_keep_arriving() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        let (mut lsp_process, mut process) =
+            test_process(Command::new("cat"), app_sender, sender.clone())?;
+        lsp_process.request_timeout = Duration::from_millis(100);
+        lsp_process.send_request::<lsp_request!("workspace/symbol")>(
+            Default::default(),
+            None,
+            WorkspaceSymbolParams {
+                query: "foo".to_string(),
+                ..Default::default()
+            },
+        )?;
+
+        // The receiver is never idle for long enough to time out
+        let feeder = thread::spawn(move || {
+            let started_at = Instant::now();
+            while started_at.elapsed() < REQUEST_TIMEOUT_CHECK_INTERVAL * 2 {
+                sender
+                    .send(LspServerProcessMessage::SetChangeDebounce(Duration::ZERO))
+                    .unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+            sender.send(LspServerProcessMessage::Shutdown).unwrap();
+        });
+        lsp_process.process_messages(receiver);
+        feeder.join().unwrap();
+
+        assert!(app_receiver.try_iter().any(|message| matches!(
+            message,
+            AppMessage::LspNotification(LspNotification::RequestTimedOut { .. })
+        )));
+
+        process.kill()?;
+        process.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn workspace_configuration_is_replied_from_the_settings_of_the_language() -> anyhow::Result<()>
+    {
+        let (app_sender, _app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+        let mut stdout = lsp_process.stdout.take().unwrap();
+        let language = shared::language::from_extension("rs").unwrap();
+        lsp_process.language = language.clone();
+        lsp_process.configurations.lock().unwrap().insert(
+            language.id().unwrap(),
+            serde_json::json!({ "rust-analyzer": { "checkOnSave": false } }),
+        );
+
+        lsp_process.handle_reply(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "workspace/configuration",
+            "params": {
+                "items": [
+                    { "section": "rust-analyzer" },
+                    { "section": "rust-analyzer.checkOnSave" },
+                    { "section": "unknown" }
+                ]
+            }
+        }))?;
+
+        // Close stdin so that `cat` exits
+        drop(lsp_process);
+        let mut sent = String::new();
+        stdout.read_to_string(&mut sent)?;
+        process.wait()?;
+
+        assert!(sent.contains(r#""result":[{"checkOnSave":false},false,null]"#));
+        Ok(())
+    }
+
+    #[test]
+    fn positions_are_converted_between_chars_and_utf16() -> anyhow::Result<()> {
+        let (app_sender, app_receiver) = mpsc::channel();
+        let (sender, _receiver) = mpsc::channel();
+        let (mut lsp_process, mut process) = test_process(Command::new("cat"), app_sender, sender)?;
+        let mut stdout = lsp_process.stdout.take().unwrap();
+        lsp_process.server_capabilities = Some(ServerCapabilities {
+            definition_provider: Some(OneOf::Left(true)),
+            ..Default::default()
+        });
+        let directory = tempfile::tempdir()?;
+        let file = directory.path().join("main.rs");
+        // The document is not opened, so it is read from the disk
+        std::fs::write(&file, "let 😀 = x;\n")?;
+        let path: CanonicalizedPath = file.try_into()?;
+
+        // `=` is at the char column 6, but at the UTF-16 column 7, due to the emoji
+        lsp_process.text_document_definition(RequestParams {
+            path: path.clone(),
+            position: crate::position::Position::new(0, 6),
+            context: ResponseContext::default(),
+        })?;
+        lsp_process.handle_reply(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": {
+                "uri": path_buf_to_url(path.clone())?,
+                "range": {
+                    "start": { "line": 0, "character": 7 },
+                    "end": { "line": 0, "character": 8 }
+                }
+            }
+        }))?;
+
+        // Close stdin so that `cat` exits
+        drop(lsp_process);
+        let mut sent = String::new();
+        stdout.read_to_string(&mut sent)?;
+        process.wait()?;
+
+        let (_, request) = sent
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| anyhow::anyhow!("Missing header"))?;
+        let request: serde_json::Value = serde_json::from_str(request)?;
+        assert_eq!(
+            request["params"]["position"],
+            serde_json::json!({ "line": 0, "character": 7 })
+        );
+
+        let AppMessage::LspNotification(LspNotification::Definition(
+            _,
+            super::GotoDefinitionResponse::Single(location),
+        )) = app_receiver.try_recv()?
+        else {
+            panic!("Expected a definition");
+        };
+        assert_eq!(
+            location.range,
+            crate::position::Position::new(0, 6)..crate::position::Position::new(0, 7)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn response_of_older_document_version_is_outdated() {
+        let context = |document_version| ResponseContext {
+            document_version,
+            ..ResponseContext::default()
+        };
+        assert!(context(Some(1)).is_outdated(2));
+        assert!(!context(Some(2)).is_outdated(2));
+        assert!(!context(None).is_outdated(2));
+    }
+
+    #[test]
+    fn request_kind_is_described_by_its_method() {
+        let kind = |method: &str| RequestKind {
+            method: method.to_string(),
+        };
+        assert_eq!(
+            kind("textDocument/references").description(),
+            "finding references"
+        );
+        assert_eq!(
+            kind("textDocument/linkedEditingRange").description(),
+            "textDocument/linkedEditingRange"
+        );
+    }
 }
@@ -0,0 +1,136 @@
+use itertools::Itertools;
+use lsp_types::WorkDoneProgress;
+
+/// An ongoing `$/progress` of a language server, such as indexing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Progress {
+    pub(crate) server_name: &'static str,
+    pub(crate) token: String,
+    pub(crate) title: String,
+    pub(crate) message: Option<String>,
+    pub(crate) percentage: Option<u32>,
+}
+
+impl Progress {
+    pub(crate) fn display(&self) -> String {
+        let details = self
+            .percentage
+            .map(|percentage| format!("{percentage}%"))
+            .into_iter()
+            .chain(self.message.clone())
+            .join(" ");
+        if details.is_empty() {
+            format!("{}: {}", self.server_name, self.title)
+        } else {
+            format!("{}: {} ({details})", self.server_name, self.title)
+        }
+    }
+}
+
+/// The ongoing progresses, in the order they began.
+#[derive(Debug, Default)]
+pub(crate) struct Progresses(Vec<Progress>);
+
+impl Progresses {
+    /// A report of an unknown progress is ignored,
+    /// because the title is only sent when the progress begins.
+    pub(crate) fn update(
+        &mut self,
+        server_name: &'static str,
+        token: String,
+        progress: WorkDoneProgress,
+    ) {
+        let position = self
+            .0
+            .iter()
+            .position(|progress| progress.server_name == server_name && progress.token == token);
+        match (progress, position) {
+            (WorkDoneProgress::Begin(begin), position) => {
+                let progress = Progress {
+                    server_name,
+                    token,
+                    title: begin.title,
+                    message: begin.message,
+                    percentage: begin.percentage,
+                };
+                match position {
+                    Some(position) => self.0[position] = progress,
+                    None => self.0.push(progress),
+                }
+            }
+            (WorkDoneProgress::Report(report), Some(position)) => {
+                let progress = &mut self.0[position];
+                if report.message.is_some() {
+                    progress.message = report.message
+                }
+                if report.percentage.is_some() {
+                    progress.percentage = report.percentage
+                }
+            }
+            (WorkDoneProgress::End(_), Some(position)) => {
+                self.0.remove(position);
+            }
+            (_, None) => {}
+        }
+    }
+
+    /// Used when the language server exited, as its progresses will never end.
+    pub(crate) fn remove_server(&mut self, server_name: &'static str) {
+        self.0
+            .retain(|progress| progress.server_name != server_name)
+    }
+
+    /// Returns `None` if there is no ongoing progress.
+    pub(crate) fn display(&self) -> Option<String> {
+        (!self.0.is_empty()).then(|| self.0.iter().map(Progress::display).join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test_progress {
+    use super::*;
+    use lsp_types::{WorkDoneProgressBegin, WorkDoneProgressEnd, WorkDoneProgressReport};
+
+    #[test]
+    fn report_updates_the_progress_that_began_with_the_same_token() {
+        let mut progresses = Progresses::default();
+        progresses.update(
+            "rust-analyzer",
+            "indexing".to_string(),
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Indexing".to_string(),
+                percentage: Some(0),
+                ..Default::default()
+            }),
+        );
+        progresses.update(
+            "rust-analyzer",
+            "indexing".to_string(),
+            WorkDoneProgress::Report(WorkDoneProgressReport {
+                message: Some("1/4 (core)".to_string()),
+                percentage: Some(25),
+                ..Default::default()
+            }),
+        );
+        // Reports of unknown progresses are ignored
+        progresses.update(
+            "rust-analyzer",
+            "fetching".to_string(),
+            WorkDoneProgress::Report(WorkDoneProgressReport {
+                percentage: Some(50),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(
+            progresses.display(),
+            Some("rust-analyzer: Indexing (25% 1/4 (core))".to_string())
+        );
+
+        progresses.update(
+            "rust-analyzer",
+            "indexing".to_string(),
+            WorkDoneProgress::End(WorkDoneProgressEnd { message: None }),
+        );
+        assert_eq!(progresses.display(), None);
+    }
+}
@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+use lsp_types::{
+    SemanticTokenModifier, SemanticTokenType, SemanticTokensEdit, SemanticTokensFullOptions,
+    SemanticTokensLegend, SemanticTokensServerCapabilities,
+};
+
+use crate::position::Position;
+
+/// A semantic token with its absolute position decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SemanticToken {
+    pub(crate) range: Range<Position>,
+    pub(crate) token_type: SemanticTokenType,
+    pub(crate) modifiers: Vec<SemanticTokenModifier>,
+}
+
+impl SemanticToken {
+    /// Returns `None` for token types that are better highlighted by Tree-sitter.
+    pub(crate) fn highlight_name(&self) -> Option<&'static str> {
+        let default_library = self
+            .modifiers
+            .contains(&SemanticTokenModifier::DEFAULT_LIBRARY);
+        Some(match self.token_type.as_str() {
+            "namespace" => "module",
+            "type" | "class" | "enum" | "interface" | "struct" | "typeParameter" | "typeAlias"
+                if default_library =>
+            {
+                "type.builtin"
+            }
+            "type" | "class" | "enum" | "interface" | "struct" | "typeParameter" | "typeAlias" => {
+                "type"
+            }
+            "parameter" => "variable.parameter",
+            "variable" if default_library => "variable.builtin",
+            "variable" => "variable",
+            "property" => "property",
+            "enumMember" => "constant",
+            "function" if default_library => "function.builtin",
+            "function" => "function",
+            "method" => "function.method",
+            "macro" => "function.macro",
+            "decorator" => "attribute",
+            _ => return None,
+        })
+    }
+}
+
+pub(crate) fn legend(capabilities: &SemanticTokensServerCapabilities) -> &SemanticTokensLegend {
+    match capabilities {
+        SemanticTokensServerCapabilities::SemanticTokensOptions(options) => &options.legend,
+        SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(options) => {
+            &options.semantic_tokens_options.legend
+        }
+    }
+}
+
+/// Returns true if the server supports `textDocument/semanticTokens/full/delta`.
+pub(crate) fn supports_delta(capabilities: &SemanticTokensServerCapabilities) -> bool {
+    let full = match capabilities {
+        SemanticTokensServerCapabilities::SemanticTokensOptions(options) => &options.full,
+        SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(options) => {
+            &options.semantic_tokens_options.full
+        }
+    };
+    matches!(
+        full,
+        Some(SemanticTokensFullOptions::Delta { delta: Some(true) })
+    )
+}
+
+/// The position of each token is encoded relative to the previous token,
+/// and its type and modifiers are indices into `legend`.
+pub(crate) fn decode(
+    data: &[lsp_types::SemanticToken],
+    legend: &SemanticTokensLegend,
+) -> Vec<SemanticToken> {
+    let mut line = 0;
+    let mut column = 0;
+    data.iter()
+        .filter_map(|token| {
+            if token.delta_line > 0 {
+                column = 0;
+            }
+            line += token.delta_line as usize;
+            column += token.delta_start as usize;
+            let start = Position { line, column };
+            let end = Position {
+                line,
+                column: column + token.length as usize,
+            };
+            Some(SemanticToken {
+                range: start..end,
+                token_type: legend.token_types.get(token.token_type as usize)?.clone(),
+                modifiers: legend
+                    .token_modifiers
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| token.token_modifiers_bitset & (1 << index) != 0)
+                    .map(|(_, modifier)| modifier.clone())
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// The `start` and `delete_count` of each edit are indices into the flattened
+/// integer array, in which every token occupies 5 integers.
+pub(crate) fn apply_edits(
+    data: &mut Vec<lsp_types::SemanticToken>,
+    edits: Vec<SemanticTokensEdit>,
+) {
+    // Edits refer to the original data, so they are applied from the back
+    for edit in edits
+        .into_iter()
+        .sorted_by_key(|edit| std::cmp::Reverse(edit.start))
+    {
+        let start = (edit.start as usize / 5).min(data.len());
+        let end = (start + edit.delete_count as usize / 5).min(data.len());
+        data.splice(start..end, edit.data.unwrap_or_default());
+    }
+}
+
+#[cfg(test)]
+mod test_semantic_tokens {
+    use super::*;
+
+    fn token(
+        delta_line: u32,
+        delta_start: u32,
+        length: u32,
+        token_type: u32,
+    ) -> lsp_types::SemanticToken {
+        lsp_types::SemanticToken {
+            delta_line,
+            delta_start,
+            length,
+            token_type,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn decode_relative_positions() {
+        let legend = SemanticTokensLegend {
+            token_types: vec![SemanticTokenType::VARIABLE, SemanticTokenType::FUNCTION],
+            token_modifiers: vec![
+                SemanticTokenModifier::DECLARATION,
+                SemanticTokenModifier::new("mutable"),
+            ],
+        };
+        let data = [
+            token(1, 4, 3, 0),
+            lsp_types::SemanticToken {
+                token_modifiers_bitset: 0b10,
+                ..token(0, 6, 2, 1)
+            },
+            token(2, 1, 5, 1),
+        ];
+        let position = |line, column| Position { line, column };
+        assert_eq!(
+            decode(&data, &legend),
+            [
+                SemanticToken {
+                    range: position(1, 4)..position(1, 7),
+                    token_type: SemanticTokenType::VARIABLE,
+                    modifiers: vec![],
+                },
+                SemanticToken {
+                    range: position(1, 10)..position(1, 12),
+                    token_type: SemanticTokenType::FUNCTION,
+                    modifiers: vec![SemanticTokenModifier::new("mutable")],
+                },
+                SemanticToken {
+                    range: position(3, 1)..position(3, 6),
+                    token_type: SemanticTokenType::FUNCTION,
+                    modifiers: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_delta_edits() {
+        let mut data = vec![token(0, 0, 1, 0), token(0, 2, 1, 0), token(0, 2, 1, 0)];
+        apply_edits(
+            &mut data,
+            vec![
+                SemanticTokensEdit {
+                    start: 10,
+                    delete_count: 5,
+                    data: None,
+                },
+                SemanticTokensEdit {
+                    start: 0,
+                    delete_count: 0,
+                    data: Some(vec![token(1, 0, 9, 1)]),
+                },
+            ],
+        );
+        assert_eq!(
+            data,
+            [token(1, 0, 9, 1), token(0, 0, 1, 0), token(0, 2, 1, 0)]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use lsp_types::{
+    CallHierarchyServerCapability, CodeActionProviderCapability, DeclarationCapability,
+    HoverProviderCapability, ImplementationProviderCapability, OneOf, ServerCapabilities,
+    TypeDefinitionProviderCapability,
+};
+
+/// LSP features that are only available if the language server advertises them
+/// in its `ServerCapabilities`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ServerCapability {
+    Hover,
+    Completion,
+    Definition,
+    Declaration,
+    Implementation,
+    TypeDefinition,
+    References,
+    Rename,
+    CodeAction,
+    DocumentSymbol,
+    DocumentHighlight,
+    CallHierarchy,
+    WorkspaceSymbol,
+    Formatting,
+    RangeFormatting,
+}
+
+impl ServerCapability {
+    pub(crate) fn is_supported_by(&self, capabilities: &ServerCapabilities) -> bool {
+        fn one_of<T>(capability: &Option<OneOf<bool, T>>) -> bool {
+            matches!(capability, Some(OneOf::Left(true) | OneOf::Right(_)))
+        }
+        match self {
+            ServerCapability::Completion => capabilities.completion_provider.is_some(),
+            ServerCapability::Hover => matches!(
+                capabilities.hover_provider,
+                Some(HoverProviderCapability::Simple(true) | HoverProviderCapability::Options(_))
+            ),
+            ServerCapability::Definition => one_of(&capabilities.definition_provider),
+            ServerCapability::Declaration => matches!(
+                capabilities.declaration_provider,
+                Some(
+                    DeclarationCapability::Simple(true)
+                        | DeclarationCapability::RegistrationOptions(_)
+                        | DeclarationCapability::Options(_)
+                )
+            ),
+            ServerCapability::Implementation => matches!(
+                capabilities.implementation_provider,
+                Some(
+                    ImplementationProviderCapability::Simple(true)
+                        | ImplementationProviderCapability::Options(_)
+                )
+            ),
+            ServerCapability::TypeDefinition => matches!(
+                capabilities.type_definition_provider,
+                Some(
+                    TypeDefinitionProviderCapability::Simple(true)
+                        | TypeDefinitionProviderCapability::Options(_)
+                )
+            ),
+            ServerCapability::References => one_of(&capabilities.references_provider),
+            ServerCapability::Rename => one_of(&capabilities.rename_provider),
+            ServerCapability::CodeAction => matches!(
+                capabilities.code_action_provider,
+                Some(
+                    CodeActionProviderCapability::Simple(true)
+                        | CodeActionProviderCapability::Options(_)
+                )
+            ),
+            ServerCapability::DocumentSymbol => one_of(&capabilities.document_symbol_provider),
+            ServerCapability::DocumentHighlight => {
+                one_of(&capabilities.document_highlight_provider)
+            }
+            ServerCapability::CallHierarchy => matches!(
+                capabilities.call_hierarchy_provider,
+                Some(
+                    CallHierarchyServerCapability::Simple(true)
+                        | CallHierarchyServerCapability::Options(_)
+                )
+            ),
+            ServerCapability::WorkspaceSymbol => one_of(&capabilities.workspace_symbol_provider),
+            ServerCapability::Formatting => one_of(&capabilities.document_formatting_provider),
+            ServerCapability::RangeFormatting => {
+                one_of(&capabilities.document_range_formatting_provider)
+            }
+        }
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            ServerCapability::Hover => "hover",
+            ServerCapability::Completion => "completion",
+            ServerCapability::Definition => "go to definition",
+            ServerCapability::Declaration => "go to declaration",
+            ServerCapability::Implementation => "go to implementation",
+            ServerCapability::TypeDefinition => "go to type definition",
+            ServerCapability::References => "find references",
+            ServerCapability::Rename => "rename",
+            ServerCapability::CodeAction => "code actions",
+            ServerCapability::DocumentSymbol => "document symbols",
+            ServerCapability::DocumentHighlight => "document highlight",
+            ServerCapability::CallHierarchy => "call hierarchy",
+            ServerCapability::WorkspaceSymbol => "workspace symbols",
+            ServerCapability::Formatting => "formatting",
+            ServerCapability::RangeFormatting => "range formatting",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_server_capability {
+    use super::*;
+
+    #[test]
+    fn explicitly_disabled_capability_is_unsupported() {
+        let capabilities = ServerCapabilities {
+            hover_provider: Some(HoverProviderCapability::Simple(true)),
+            rename_provider: Some(OneOf::Left(false)),
+            document_formatting_provider: Some(OneOf::Right(Default::default())),
+            ..Default::default()
+        };
+        assert!(ServerCapability::Hover.is_supported_by(&capabilities));
+        assert!(ServerCapability::Formatting.is_supported_by(&capabilities));
+        assert!(!ServerCapability::Rename.is_supported_by(&capabilities));
+        assert!(!ServerCapability::References.is_supported_by(&capabilities));
+    }
+
+    #[test]
+    fn type_definition_and_implementation_are_gated_separately_from_definition() {
+        let capabilities = ServerCapabilities {
+            definition_provider: Some(OneOf::Left(true)),
+            type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+            implementation_provider: Some(ImplementationProviderCapability::Simple(false)),
+            ..Default::default()
+        };
+        assert!(ServerCapability::Definition.is_supported_by(&capabilities));
+        assert!(ServerCapability::TypeDefinition.is_supported_by(&capabilities));
+        assert!(!ServerCapability::Implementation.is_supported_by(&capabilities));
+    }
+
+    #[test]
+    fn declaration_is_gated_separately_from_definition() {
+        let capabilities = |declaration_provider| ServerCapabilities {
+            definition_provider: Some(OneOf::Left(true)),
+            declaration_provider,
+            ..Default::default()
+        };
+        assert!(!ServerCapability::Declaration.is_supported_by(&capabilities(None)));
+        assert!(!ServerCapability::Declaration
+            .is_supported_by(&capabilities(Some(DeclarationCapability::Simple(false)))));
+        assert!(ServerCapability::Declaration
+            .is_supported_by(&capabilities(Some(DeclarationCapability::Simple(true)))));
+    }
+}
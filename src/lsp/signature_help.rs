@@ -11,6 +11,8 @@ use super::documentation::Documentation;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct SignatureHelp {
     pub(crate) signatures: Vec<SignatureInformation>,
+    /// Index of the signature that best matches the call being typed.
+    pub(crate) active_signature: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -26,17 +28,24 @@ impl From<lsp_types::SignatureHelp> for SignatureHelp {
             signatures: value
                 .signatures
                 .into_iter()
-                .map(SignatureInformation::from)
+                .map(|signature| SignatureInformation::new(signature, value.active_parameter))
                 .collect(),
+            active_signature: value
+                .active_signature
+                .map(|active_signature| active_signature as usize),
         }
     }
 }
 
 impl SignatureHelp {
+    /// The active signature is shown first.
     pub(crate) fn into_info(self) -> Option<Info> {
+        let active_signature = self.active_signature;
         self.signatures
             .into_iter()
-            .map(|signature| {
+            .enumerate()
+            .sorted_by_key(|(index, _)| Some(*index) != active_signature)
+            .map(|(_, signature)| {
                 let signature_label_len = signature.label.len();
                 let content = [signature.label]
                     .into_iter()
@@ -56,23 +65,122 @@ impl SignatureHelp {
     }
 }
 
-impl From<lsp_types::SignatureInformation> for SignatureInformation {
-    fn from(value: lsp_types::SignatureInformation) -> Self {
+impl SignatureInformation {
+    /// `default_active_parameter` is the `activeParameter` of the `SignatureHelp`,
+    /// which is only used if the signature does not specify its own active parameter.
+    fn new(value: lsp_types::SignatureInformation, default_active_parameter: Option<u32>) -> Self {
+        let active_parameter_byte_range = value.parameters.as_ref().and_then(|parameters| {
+            let active_parameter = value.active_parameter.or(default_active_parameter)? as usize;
+            if active_parameter >= parameters.len() {
+                return None;
+            }
+            // A simple label is searched after the previous parameter, starting from the
+            // parameter list, so that it does not match the function name or an earlier parameter
+            let mut offset = value.label.find('(').map_or(0, |index| index + 1);
+            let mut range = None;
+            for parameter in parameters.iter().take(active_parameter + 1) {
+                let (start, end) = match &parameter.label {
+                    lsp_types::ParameterLabel::LabelOffsets([start, end]) => {
+                        (*start as usize, *end as usize)
+                    }
+                    lsp_types::ParameterLabel::Simple(parameter_label) => {
+                        let start =
+                            offset + value.label.get(offset..)?.find(parameter_label.as_str())?;
+                        (start, start + parameter_label.len())
+                    }
+                };
+                offset = end;
+                range = Some(SelectionRange::Byte(start..end));
+            }
+            range
+        });
         Self {
             label: value.label,
             documentation: value.documentation.map(Documentation::from),
-            active_parameter_byte_range: value.parameters.and_then(|parameters| {
-                let label = parameters
-                    .get(value.active_parameter? as usize)?
-                    .label
-                    .to_owned();
-                match label {
-                    lsp_types::ParameterLabel::LabelOffsets([start, end]) => {
-                        Some(SelectionRange::Byte(start as usize..end as usize))
-                    }
-                    _ => None,
-                }
-            }),
+            active_parameter_byte_range,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_signature_help {
+    use super::*;
+    use lsp_types::{ParameterInformation, ParameterLabel};
+
+    fn signature(label: &str, parameters: &[&str]) -> lsp_types::SignatureInformation {
+        lsp_types::SignatureInformation {
+            label: label.to_string(),
+            documentation: None,
+            parameters: Some(
+                parameters
+                    .iter()
+                    .map(|parameter| ParameterInformation {
+                        label: ParameterLabel::Simple(parameter.to_string()),
+                        documentation: None,
+                    })
+                    .collect(),
+            ),
+            active_parameter: None,
+        }
+    }
+
+    #[test]
+    fn active_parameter_of_signature_help_is_used_as_fallback() {
+        let signature_help: SignatureHelp = lsp_types::SignatureHelp {
+            signatures: vec![
+                signature("fn foo(a: u8, b: u8)", &["a: u8", "b: u8"]),
+                lsp_types::SignatureInformation {
+                    active_parameter: Some(0),
+                    ..signature("fn bar(c: u8, d: u8)", &["c: u8", "d: u8"])
+                },
+            ],
+            active_signature: Some(1),
+            active_parameter: Some(1),
+        }
+        .into();
+        assert_eq!(signature_help.active_signature, Some(1));
+        assert_eq!(
+            signature_help
+                .signatures
+                .iter()
+                .map(|signature| signature.active_parameter_byte_range.clone())
+                .collect_vec(),
+            [
+                Some(SelectionRange::Byte(14..19)),
+                Some(SelectionRange::Byte(7..12))
+            ]
+        );
+    }
+
+    #[test]
+    fn simple_parameter_labels_are_searched_after_the_previous_parameter() {
+        let active_parameter_byte_range = |active_parameter| {
+            SignatureInformation::new(
+                signature("fn ab(a, b: a)", &["a", "b: a"]),
+                Some(active_parameter),
+            )
+            .active_parameter_byte_range
+        };
+        assert_eq!(
+            active_parameter_byte_range(0),
+            Some(SelectionRange::Byte(6..7))
+        );
+        assert_eq!(
+            active_parameter_byte_range(1),
+            Some(SelectionRange::Byte(9..13))
+        );
+        assert_eq!(active_parameter_byte_range(2), None);
+    }
+
+    #[test]
+    fn active_signature_is_shown_first() {
+        let signature_help: SignatureHelp = lsp_types::SignatureHelp {
+            signatures: vec![signature("fn foo()", &[]), signature("fn bar()", &[])],
+            active_signature: Some(1),
+            active_parameter: None,
         }
+        .into();
+        let info = signature_help.into_info().unwrap();
+        assert!(info.content().starts_with("fn bar()"));
     }
 }
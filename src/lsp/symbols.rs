@@ -1,12 +1,14 @@
 use crate::{
     app::{Dispatch, Dispatches},
+    buffer::Buffer,
     components::dropdown::DropdownItem,
     quickfix_list::Location,
 };
-use lsp_types::{DocumentSymbolResponse, SymbolKind};
+use itertools::Itertools;
+use lsp_types::{DocumentSymbolResponse, OneOf, SymbolKind, WorkspaceSymbolResponse};
 use shared::{canonicalized_path::CanonicalizedPath, icons::get_icon_config};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub(crate) struct Symbols {
     pub(crate) symbols: Vec<Symbol>,
 }
@@ -64,6 +66,155 @@ impl Symbols {
 
         Ok(Self { symbols })
     }
+
+    pub(crate) fn try_from_workspace_symbol_response(
+        value: WorkspaceSymbolResponse,
+    ) -> anyhow::Result<Self> {
+        let symbols = match value {
+            WorkspaceSymbolResponse::Flat(flat_symbols) => flat_symbols
+                .into_iter()
+                .map(|symbol| symbol.try_into())
+                .collect::<Result<Vec<_>, _>>()?,
+            WorkspaceSymbolResponse::Nested(nested_symbols) => nested_symbols
+                .into_iter()
+                .map(|symbol| symbol.try_into())
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        Ok(Self { symbols })
+    }
+
+    /// Symbols at the same location, which are usually reported
+    /// by different language servers, are only kept once.
+    pub(crate) fn merge(self, other: Symbols) -> Symbols {
+        Symbols {
+            symbols: self
+                .symbols
+                .into_iter()
+                .chain(other.symbols)
+                .unique_by(|symbol| {
+                    (
+                        symbol.name.clone(),
+                        symbol.location.path.clone(),
+                        symbol.location.range.start,
+                        symbol.location.range.end,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// A basic outline derived from the syntax tree of `buffer`,
+    /// used when no language server provides document symbols.
+    ///
+    /// The headings of a Markdown buffer are included as well, prefixed by their level.
+    pub(crate) fn try_from_syntax_tree(buffer: &Buffer) -> anyhow::Result<Self> {
+        let path = buffer
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("Unable to get the symbols of a buffer without path"))?;
+        let mut symbols = Vec::new();
+        if let Some(tree) = buffer.tree() {
+            Self::collect_syntax_tree_symbols(
+                buffer,
+                &buffer.content(),
+                tree.root_node(),
+                None,
+                &path,
+                &mut symbols,
+            )?;
+        }
+        for (level, text, start) in buffer.markdown_outline() {
+            let position = buffer.char_to_position(start)?;
+            symbols.push(Symbol {
+                name: format!("{} {text}", "#".repeat(level)),
+                kind: SymbolKind::STRING,
+                location: Location {
+                    path: path.clone(),
+                    range: position..position,
+                },
+                container_name: None,
+            })
+        }
+        Ok(Self { symbols })
+    }
+
+    fn collect_syntax_tree_symbols(
+        buffer: &Buffer,
+        content: &str,
+        node: tree_sitter::Node,
+        parent_name: Option<String>,
+        path: &CanonicalizedPath,
+        symbols: &mut Vec<Symbol>,
+    ) -> anyhow::Result<()> {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            let symbol = syntax_node_symbol_kind(child.kind())
+                .zip(child.child_by_field_name("name"))
+                .map(|(kind, name)| -> anyhow::Result<_> {
+                    Ok(Symbol {
+                        name: name.utf8_text(content.as_bytes())?.to_string(),
+                        kind,
+                        location: Location {
+                            path: path.clone(),
+                            range: buffer.byte_to_position(child.start_byte())?
+                                ..buffer.byte_to_position(child.end_byte())?,
+                        },
+                        container_name: parent_name.clone(),
+                    })
+                })
+                .transpose()?;
+            let child_parent_name = match &symbol {
+                Some(symbol) => Some(format!(
+                    "{}{}",
+                    parent_name
+                        .as_ref()
+                        .map(|name| format!("{name} ▶ "))
+                        .unwrap_or_default(),
+                    symbol.name
+                )),
+                None => parent_name.clone(),
+            };
+            symbols.extend(symbol);
+            Self::collect_syntax_tree_symbols(
+                buffer,
+                content,
+                child,
+                child_parent_name,
+                path,
+                symbols,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Guess the symbol kind of a syntax node based on the naming conventions
+/// of tree-sitter grammars, e.g. `function_item`, `class_definition`.
+fn syntax_node_symbol_kind(kind: &str) -> Option<SymbolKind> {
+    if !["_item", "_definition", "_declaration"]
+        .iter()
+        .any(|suffix| kind.ends_with(suffix))
+    {
+        return None;
+    }
+    Some(if kind.contains("method") {
+        SymbolKind::METHOD
+    } else if kind.contains("function") {
+        SymbolKind::FUNCTION
+    } else if kind.contains("class") {
+        SymbolKind::CLASS
+    } else if kind.contains("struct") {
+        SymbolKind::STRUCT
+    } else if kind.contains("enum") {
+        SymbolKind::ENUM
+    } else if kind.contains("trait") || kind.contains("interface") {
+        SymbolKind::INTERFACE
+    } else if kind.contains("mod") || kind.contains("namespace") {
+        SymbolKind::MODULE
+    } else if kind.contains("const") {
+        SymbolKind::CONSTANT
+    } else {
+        SymbolKind::VARIABLE
+    })
 }
 
 impl TryFrom<lsp_types::SymbolInformation> for Symbol {
@@ -79,6 +230,26 @@ impl TryFrom<lsp_types::SymbolInformation> for Symbol {
     }
 }
 
+impl TryFrom<lsp_types::WorkspaceSymbol> for Symbol {
+    type Error = anyhow::Error;
+
+    fn try_from(value: lsp_types::WorkspaceSymbol) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: value.name,
+            kind: value.kind,
+            location: match value.location {
+                OneOf::Left(location) => location.try_into()?,
+                // The server did not provide the range of the symbol
+                OneOf::Right(location) => Location {
+                    path: location.uri.try_into()?,
+                    range: Default::default(),
+                },
+            },
+            container_name: value.container_name,
+        })
+    }
+}
+
 impl Symbol {
     fn try_from_document_symbol(
         value: lsp_types::DocumentSymbol,
@@ -131,3 +302,102 @@ impl From<Symbol> for DropdownItem {
             .set_dispatches(dispatches)
     }
 }
+
+#[cfg(test)]
+mod test_symbols {
+    use super::*;
+
+    #[test]
+    fn symbols_from_syntax_tree() -> anyhow::Result<()> {
+        let mut buffer = Buffer::new(
+            Some(tree_sitter_rust::LANGUAGE.into()),
+            "struct Foo;
+mod bar {
+    fn spam() {}
+}",
+        );
+        buffer.set_path(std::env::current_dir()?.join("Cargo.toml").try_into()?);
+        let symbols = Symbols::try_from_syntax_tree(&buffer)?
+            .symbols
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.container_name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            [
+                ("Foo".to_string(), SymbolKind::STRUCT, None),
+                ("bar".to_string(), SymbolKind::MODULE, None),
+                (
+                    "spam".to_string(),
+                    SymbolKind::FUNCTION,
+                    Some("bar".to_string())
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_headings_are_symbols() -> anyhow::Result<()> {
+        let mut buffer = Buffer::new(
+            Some(tree_sitter_md::LANGUAGE.into()),
+            "# One\n\ntext\n\n## Two\n",
+        );
+        buffer.set_path(std::env::current_dir()?.join("Cargo.toml").try_into()?);
+        let symbols = Symbols::try_from_syntax_tree(&buffer)?
+            .symbols
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.location.range.start.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            [("# One".to_string(), 0), ("## Two".to_string(), 4)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn workspace_symbols_at_the_same_location_are_merged() -> anyhow::Result<()> {
+        let uri = lsp_types::Url::from_file_path(std::env::current_dir()?.join("Cargo.toml"))
+            .map_err(|_| anyhow::anyhow!("Invalid path"))?;
+        let workspace_symbol = |name: &str, line: u32| lsp_types::WorkspaceSymbol {
+            name: name.to_string(),
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            container_name: None,
+            location: OneOf::Left(lsp_types::Location {
+                uri: uri.clone(),
+                range: lsp_types::Range::new(
+                    lsp_types::Position::new(line, 0),
+                    lsp_types::Position::new(line, 3),
+                ),
+            }),
+            data: None,
+        };
+        let left =
+            Symbols::try_from_workspace_symbol_response(WorkspaceSymbolResponse::Nested(vec![
+                workspace_symbol("foo", 0),
+                workspace_symbol("bar", 1),
+            ]))?;
+        let right =
+            Symbols::try_from_workspace_symbol_response(WorkspaceSymbolResponse::Nested(vec![
+                workspace_symbol("bar", 1),
+                workspace_symbol("bar", 2),
+            ]))?;
+        let symbols = left
+            .merge(right)
+            .symbols
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.location.range.start.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            [
+                ("foo".to_string(), 0),
+                ("bar".to_string(), 1),
+                ("bar".to_string(), 2)
+            ]
+        );
+        Ok(())
+    }
+}
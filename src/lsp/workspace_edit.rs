@@ -1,8 +1,14 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use itertools::Itertools;
 use shared::canonicalized_path::CanonicalizedPath;
 
+use crate::{
+    buffer::Buffer,
+    edit::{Action, ActionGroup, Edit, EditTransaction},
+    selection::SelectionSet,
+};
+
 use super::completion::PositionalEdit;
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -53,6 +59,7 @@ impl TryFrom<lsp_types::WorkspaceEdit> for WorkspaceEdit {
                     .map(|(url, edits)| {
                         Ok(TextDocumentEdit {
                             path: url.try_into()?,
+                            version: None,
                             edits: edits
                                 .into_iter()
                                 .map(|edit| edit.try_into())
@@ -113,6 +120,7 @@ impl TryFrom<lsp_types::TextDocumentEdit> for TextDocumentEdit {
         let path = value.text_document.uri.try_into()?;
         Ok(TextDocumentEdit {
             path,
+            version: value.text_document.version,
             edits: value
                 .edits
                 .into_iter()
@@ -132,5 +140,170 @@ impl TryFrom<lsp_types::TextDocumentEdit> for TextDocumentEdit {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct TextDocumentEdit {
     pub(crate) path: CanonicalizedPath,
+    /// The version of the document that the edits are computed against,
+    /// `None` if the edit comes from the legacy `changes` map, which is unversioned.
+    pub(crate) version: Option<i32>,
     pub(crate) edits: Vec<PositionalEdit>,
 }
+
+impl TextDocumentEdit {
+    /// Unlike `Editor::apply_positional_edits`, invalid positions are not silently dropped.
+    fn to_edit_transaction(&self, buffer: &Buffer) -> anyhow::Result<EditTransaction> {
+        Ok(EditTransaction::from_action_groups(
+            self.edits
+                .iter()
+                .map(|edit| -> anyhow::Result<_> {
+                    let range = edit.range.start.to_char_index(buffer)?
+                        ..edit.range.end.to_char_index(buffer)?;
+                    Ok(ActionGroup::new(vec![Action::Edit(Edit::new(
+                        buffer.rope(),
+                        range.into(),
+                        edit.new_text.clone().into(),
+                    ))]))
+                })
+                .try_collect()?,
+        ))
+    }
+}
+
+/// Applies the text edits of `workspace_edit` to `buffers`,
+/// such that either every buffer is edited, or none of them is.
+///
+/// Edits of the same document are applied in order,
+/// thus each of them is computed against the content left by the previous one.
+///
+/// Resource operations are not applied, because they are not tied to any buffer.
+pub(crate) fn apply_workspace_edit(
+    buffers: &mut HashMap<CanonicalizedPath, Buffer>,
+    workspace_edit: WorkspaceEdit,
+) -> anyhow::Result<()> {
+    let snapshots = workspace_edit
+        .edits
+        .iter()
+        .map(|edit| {
+            let buffer = buffers.get(&edit.path).ok_or_else(|| {
+                anyhow::anyhow!("No buffer is opened for {}", edit.path.display_absolute())
+            })?;
+            match edit.version {
+                Some(version) if version != buffer.version() => Err(anyhow::anyhow!(
+                    "{} is modified since version {version} that the edit is computed against",
+                    edit.path.display_absolute(),
+                )),
+                _ => Ok((edit.path.clone(), buffer.clone())),
+            }
+        })
+        .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+    let result = workspace_edit.edits.iter().try_for_each(|edit| {
+        let buffer = buffers.get_mut(&edit.path).ok_or_else(|| {
+            anyhow::anyhow!("No buffer is opened for {}", edit.path.display_absolute())
+        })?;
+        let edit_transaction = edit.to_edit_transaction(buffer)?;
+        buffer
+            .apply_edit_transaction(&edit_transaction, SelectionSet::default(), true, true, 0)
+            .map(|_| ())
+    });
+    if result.is_err() {
+        buffers.extend(snapshots);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test_workspace_edit {
+    use super::*;
+    use crate::position::Position;
+
+    fn text_document_edit(
+        path: &CanonicalizedPath,
+        version: Option<i32>,
+        edit: (Position, Position, &str),
+    ) -> TextDocumentEdit {
+        let (start, end, new_text) = edit;
+        TextDocumentEdit {
+            path: path.clone(),
+            version,
+            edits: vec![PositionalEdit {
+                range: start..end,
+                new_text: new_text.to_string(),
+            }],
+        }
+    }
+
+    fn setup() -> anyhow::Result<(
+        CanonicalizedPath,
+        CanonicalizedPath,
+        HashMap<CanonicalizedPath, Buffer>,
+    )> {
+        let foo: CanonicalizedPath = "Cargo.toml".try_into()?;
+        let bar: CanonicalizedPath = "readme.md".try_into()?;
+        let buffers = [
+            (foo.clone(), Buffer::new(None, "fn foo() {}")),
+            (bar.clone(), Buffer::new(None, "foo();")),
+        ]
+        .into_iter()
+        .collect();
+        Ok((foo, bar, buffers))
+    }
+
+    #[test]
+    fn edits_of_every_buffer_are_applied_in_order() -> anyhow::Result<()> {
+        let (foo, bar, mut buffers) = setup()?;
+        apply_workspace_edit(
+            &mut buffers,
+            WorkspaceEdit {
+                edits: vec![
+                    text_document_edit(
+                        &foo,
+                        Some(1),
+                        (Position::new(0, 3), Position::new(0, 6), "bar"),
+                    ),
+                    text_document_edit(
+                        &bar,
+                        None,
+                        (Position::new(0, 0), Position::new(0, 3), "bar"),
+                    ),
+                    text_document_edit(
+                        &bar,
+                        None,
+                        (Position::new(0, 3), Position::new(0, 3), "::<()>"),
+                    ),
+                ],
+                resource_operations: Vec::new(),
+            },
+        )?;
+        assert_eq!(buffers[&foo].content(), "fn bar() {}");
+        assert_eq!(buffers[&bar].content(), "bar::<()>();");
+        Ok(())
+    }
+
+    #[test]
+    fn no_buffer_is_edited_if_any_edit_fails() -> anyhow::Result<()> {
+        let (foo, bar, mut buffers) = setup()?;
+        buffers.get_mut(&bar).unwrap().set_read_only(true);
+        let workspace_edit = |version| WorkspaceEdit {
+            edits: vec![
+                text_document_edit(
+                    &foo,
+                    version,
+                    (Position::new(0, 3), Position::new(0, 6), "bar"),
+                ),
+                text_document_edit(
+                    &bar,
+                    None,
+                    (Position::new(0, 0), Position::new(0, 3), "bar"),
+                ),
+            ],
+            resource_operations: Vec::new(),
+        };
+        assert!(apply_workspace_edit(&mut buffers, workspace_edit(None)).is_err());
+        assert_eq!(buffers[&foo].content(), "fn foo() {}");
+        assert_eq!(buffers[&bar].content(), "foo();");
+
+        buffers.get_mut(&bar).unwrap().set_read_only(false);
+        assert!(apply_workspace_edit(&mut buffers, workspace_edit(Some(0))).is_err());
+        assert_eq!(buffers[&foo].content(), "fn foo() {}");
+        assert_eq!(buffers[&bar].content(), "foo();");
+        Ok(())
+    }
+}
@@ -88,6 +88,13 @@ pub(crate) fn run(config: RunConfig) -> anyhow::Result<()> {
             StatusLineComponent::SelectionMode,
             StatusLineComponent::LocalSearchConfig,
             StatusLineComponent::LastDispatch,
+            StatusLineComponent::LspProgress,
+            StatusLineComponent::LspPendingRequests,
+            StatusLineComponent::LspServerStatus,
+            StatusLineComponent::StaleSyntaxTree,
+            StatusLineComponent::Breadcrumbs,
+            #[cfg(feature = "metrics")]
+            StatusLineComponent::ParseDuration,
         ]
         .to_vec(),
     )?;
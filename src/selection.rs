@@ -442,6 +442,9 @@ pub(crate) enum SelectionMode {
     // Mark
     Mark,
     LineFull,
+
+    // Folding ranges of LSP or syntax tree
+    Fold,
 }
 impl SelectionMode {
     pub(crate) fn is_node(&self) -> bool {
@@ -468,6 +471,7 @@ impl SelectionMode {
                 format!("GIT HUNK {}", diff_mode.display()).to_string()
             }
             SelectionMode::Mark => "MARK".to_string(),
+            SelectionMode::Fold => "FOLD".to_string(),
             SelectionMode::LocalQuickfix { title } => title.to_string(),
             SelectionMode::Word { skip_symbols } => {
                 format!("{}WORD", if *skip_symbols { "" } else { "FINE " })
@@ -523,6 +527,7 @@ impl SelectionMode {
                 diff_mode, buffer, context,
             )?)),
             SelectionMode::Mark => Box::new(IterBased(selection_mode::Mark)),
+            SelectionMode::Fold => Box::new(IterBased(selection_mode::Fold)),
             SelectionMode::LocalQuickfix { .. } => {
                 Box::new(IterBased(selection_mode::LocalQuickfix::new(params)))
             }
@@ -657,11 +662,13 @@ impl Selection {
     }
 
     fn clamp(&self, max_char_index: CharIndex) -> Self {
-        let range =
-            (self.range.start.min(max_char_index)..self.range.end.min(max_char_index)).into();
+        let clamp = |range: CharIndexRange| -> CharIndexRange {
+            (range.start.min(max_char_index)..range.end.min(max_char_index)).into()
+        };
         Selection {
-            range,
-            initial_range: self.initial_range,
+            range: clamp(self.range),
+            // The anchor of an extended selection can be out of bound too
+            initial_range: self.initial_range.map(clamp),
             info: self.info.clone(),
         }
     }
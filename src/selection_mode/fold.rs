@@ -0,0 +1,20 @@
+use super::IterBasedSelectionMode;
+
+/// Selects the foldable line ranges of the buffer, see `Buffer::folding_ranges`.
+pub(crate) struct Fold;
+
+impl IterBasedSelectionMode for Fold {
+    fn iter<'a>(
+        &'a self,
+        params: &super::SelectionModeParams<'a>,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = super::ByteRange> + 'a>> {
+        let buffer = params.buffer;
+        Ok(Box::new(buffer.folding_ranges().into_iter().filter_map(
+            |line_range| {
+                Some(super::ByteRange::new(
+                    buffer.line_range_to_byte_range(&line_range).ok()?,
+                ))
+            },
+        )))
+    }
+}
@@ -2,6 +2,7 @@ pub(crate) mod ast_grep;
 pub(crate) mod character;
 pub(crate) mod custom;
 pub(crate) mod diagnostic;
+pub(crate) mod fold;
 pub(crate) mod git_hunk;
 pub(crate) mod mark;
 pub(crate) mod naming_convention_agnostic;
@@ -21,6 +22,7 @@ pub(crate) use ast_grep::AstGrep;
 pub(crate) use character::Character;
 pub(crate) use custom::Custom;
 pub(crate) use diagnostic::Diagnostic;
+pub(crate) use fold::Fold;
 pub(crate) use git_hunk::GitHunk;
 use itertools::Itertools;
 pub(crate) use line_full::LineFull;
@@ -754,7 +756,7 @@ pub trait PositionBasedSelectionMode {
                 .current_selection
                 .range()
                 .end
-                .min(CharIndex(params.buffer.len_chars().saturating_sub(1))),
+                .min(params.buffer.last_char_index()),
             IfCurrentNotFound::LookForward,
         )?
         .map(|range| {
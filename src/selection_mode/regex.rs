@@ -9,15 +9,24 @@ pub(crate) struct Regex {
 
 /// BOTTLENECK 3
 pub(crate) fn get_regex(pattern: &str, config: RegexConfig) -> anyhow::Result<fancy_regex::Regex> {
-    let pattern = if config.escaped {
-        regex::escape(pattern)
-    } else {
-        pattern.to_string()
-    };
-    let pattern = if config.match_whole_word {
+    let is_word_char =
+        |char: Option<char>| char.is_some_and(|char| char.is_alphanumeric() || char == '_');
+    let escaped = regex::escape(pattern);
+    let pattern = if config.match_whole_word && config.escaped {
+        // A word boundary next to a non-word character would require the
+        // surrounding character to be a word character, so it is omitted there
+        let boundary = |char| if is_word_char(char) { "\\b" } else { "" };
+        format!(
+            "{}{escaped}{}",
+            boundary(pattern.chars().next()),
+            boundary(pattern.chars().last())
+        )
+    } else if config.match_whole_word {
         format!("\\b{}\\b", pattern)
+    } else if config.escaped {
+        escaped
     } else {
-        pattern
+        pattern.to_string()
     };
     let pattern = if config.case_sensitive {
         pattern
@@ -133,6 +142,26 @@ mod test_regex {
         .assert_all_selections(&buffer, Selection::default(), &[(3..7, "Main")]);
     }
 
+    #[test]
+    fn match_whole_word_of_literal_ending_with_non_word_character() {
+        let buffer = Buffer::new(None, "fn main() { let x = domain(); main(); }");
+        crate::selection_mode::Regex::from_config(
+            &buffer,
+            "main(",
+            RegexConfig {
+                escaped: true,
+                case_sensitive: true,
+                match_whole_word: true,
+            },
+        )
+        .unwrap()
+        .assert_all_selections(
+            &buffer,
+            Selection::default(),
+            &[(3..8, "main("), (30..35, "main(")],
+        );
+    }
+
     #[test]
     fn multiline_mode_enabled_by_default() {
         let buffer = Buffer::new(
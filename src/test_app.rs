@@ -44,11 +44,14 @@ use crate::{
     integration_test::TestRunner,
     list::grep::RegexConfig,
     lsp::{
+        call_hierarchy::CallDirection,
         code_action::CodeAction,
         completion::{Completion, CompletionItem, CompletionItemEdit, PositionalEdit},
+        document_highlight::{DocumentHighlight, DocumentHighlightKind},
         documentation::Documentation,
         goto_definition_response::GotoDefinitionResponse,
-        process::FromEditor,
+        inlay_hint::InlayHint,
+        process::{FromEditor, ResponseContext},
         signature_help::SignatureInformation,
         workspace_edit::{TextDocumentEdit, WorkspaceEdit},
     },
@@ -935,6 +938,56 @@ fn multi_paste() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn named_registers_are_independent_of_the_unnamed_register() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("foo bar".to_string())),
+            Editor(MatchLiteral("foo".to_string())),
+            App(OpenCopyToRegisterPrompt),
+            App(HandleKeyEvents(keys!("a enter").to_vec())),
+            Editor(MatchLiteral("bar".to_string())),
+            Editor(Copy {
+                use_system_clipboard: false,
+            }),
+            App(OpenPasteFromRegisterPrompt),
+            App(HandleKeyEvents(keys!("a enter").to_vec())),
+            Expect(CurrentComponentContent("foo barfoo")),
+            Expect(CurrentSelectedTexts(&["foo"])),
+            // The unnamed register still holds the text copied last
+            Editor(Paste {
+                direction: Direction::End,
+                use_system_clipboard: false,
+            }),
+            Expect(CurrentComponentContent("foo barfoobar")),
+        ])
+    })
+}
+
+#[test]
+fn reload_file_replaces_the_content_with_the_file_on_disk() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let path = s.main_rs();
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            AppLater(Box::new(move || {
+                path.write("fn reloaded() {}").unwrap();
+                Dispatch::ToEditor(ReloadFile)
+            })),
+            Expect(CurrentComponentContent("fn reloaded() {}")),
+        ])
+    })
+}
+
 #[test]
 fn signature_help() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -948,6 +1001,7 @@ fn signature_help() -> anyhow::Result<()> {
                     active_parameter_byte_range: None,
                 }]
                 .to_vec(),
+                active_signature: None,
             }))
         }
         Box::new([
@@ -1336,6 +1390,8 @@ fn local_lsp_references() -> anyhow::Result<()> {
                 crate::lsp::process::ResponseContext {
                     scope: Some(Scope::Local),
                     description: None,
+                    path: None,
+                    document_version: None,
                 },
                 [
                     Location {
@@ -1358,8 +1414,9 @@ fn local_lsp_references() -> anyhow::Result<()> {
 #[test]
 fn global_diagnostics() -> Result<(), anyhow::Error> {
     execute_test(|s| {
-        let publish_diagnostics = |path: CanonicalizedPath| {
-            LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
+        let publish_diagnostics = |path: CanonicalizedPath| LspNotification::PublishDiagnostics {
+            server_name: "rust-analyzer",
+            params: lsp_types::PublishDiagnosticsParams {
                 uri: path.to_url().unwrap(),
                 diagnostics: [lsp_types::Diagnostic {
                     range: lsp_types::Range::new(
@@ -1377,7 +1434,7 @@ fn global_diagnostics() -> Result<(), anyhow::Error> {
                 }]
                 .to_vec(),
                 version: None,
-            })
+            },
         };
         Box::new([
             App(OpenFile {
@@ -1676,18 +1733,21 @@ fn diagnostic_info() -> Result<(), anyhow::Error> {
                 focus: true,
             }),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: [lsp_types::Diagnostic::new_simple(
-                        lsp_types::Range::new(
-                            lsp_types::Position::new(0, 1),
-                            lsp_types::Position::new(0, 2),
-                        ),
-                        "Hello world".to_string(),
-                    )]
-                    .to_vec(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics {
+                    server_name: "rust-analyzer",
+                    params: lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: [lsp_types::Diagnostic::new_simple(
+                            lsp_types::Range::new(
+                                lsp_types::Position::new(0, 1),
+                                lsp_types::Position::new(0, 2),
+                            ),
+                            "Hello world".to_string(),
+                        )]
+                        .to_vec(),
+                        version: None,
+                    },
+                },
             )),
             Editor(SetSelectionMode(
                 IfCurrentNotFound::LookForward,
@@ -1698,11 +1758,14 @@ fn diagnostic_info() -> Result<(), anyhow::Error> {
             App(HandleKeyEvent(key!("esc"))),
             Expect(EditorInfoOpen(false)),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: Default::default(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics {
+                    server_name: "rust-analyzer",
+                    params: lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: Default::default(),
+                        version: None,
+                    },
+                },
             )),
             Editor(MoveSelection(Right)),
             Expect(EditorInfoOpen(false)),
@@ -1759,14 +1822,17 @@ fn diagnostic_severity_decoration_precedence() -> Result<(), anyhow::Error> {
                 width: 80,
             })),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: diagnostics
-                        .into_iter()
-                        .map(|(start, end, severity)| diagnostic(start, end, severity))
-                        .collect_vec(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics {
+                    server_name: "rust-analyzer",
+                    params: lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: diagnostics
+                            .into_iter()
+                            .map(|(start, end, severity)| diagnostic(start, end, severity))
+                            .collect_vec(),
+                        version: None,
+                    },
+                },
             )),
             ExpectMulti(
                 (0..1)
@@ -1807,12 +1873,15 @@ fn same_range_diagnostics_should_be_merged() -> Result<(), anyhow::Error> {
                 focus: true,
             }),
             App(Dispatch::HandleLspNotification(
-                LspNotification::PublishDiagnostics(lsp_types::PublishDiagnosticsParams {
-                    uri: Url::from_file_path(s.foo_rs()).unwrap(),
-                    diagnostics: [diagnostic("foo"), diagnostic("bar"), diagnostic("spam")]
-                        .to_vec(),
-                    version: None,
-                }),
+                LspNotification::PublishDiagnostics {
+                    server_name: "rust-analyzer",
+                    params: lsp_types::PublishDiagnosticsParams {
+                        uri: Url::from_file_path(s.foo_rs()).unwrap(),
+                        diagnostics: [diagnostic("foo"), diagnostic("bar"), diagnostic("spam")]
+                            .to_vec(),
+                        version: None,
+                    },
+                },
             )),
             Editor(SetSelectionMode(
                 IfCurrentNotFound::LookForward,
@@ -1838,6 +1907,7 @@ fn code_action() -> anyhow::Result<()> {
             edit: Some(WorkspaceEdit {
                 edits: [TextDocumentEdit {
                     path: s.main_rs(),
+                    version: None,
                     edits: [PositionalEdit {
                         range: Position::new(0, 2)..Position::new(0, 6),
                         new_text: new_text.to_string(),
@@ -1848,6 +1918,7 @@ fn code_action() -> anyhow::Result<()> {
                 resource_operations: Vec::new(),
             }),
             command: None,
+            unresolved: None,
         };
         Box::new([
             App(OpenFile {
@@ -1865,6 +1936,57 @@ fn code_action() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn choosing_unresolved_code_action_should_resolve_it() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let unresolved = CodeAction::try_from(lsp_types::CodeAction {
+            title: "Import foo".to_string(),
+            data: Some(serde_json::json!({ "id": 1 })),
+            ..Default::default()
+        })
+        .unwrap();
+        let resolved = CodeAction {
+            edit: Some(WorkspaceEdit {
+                edits: [TextDocumentEdit {
+                    path: s.main_rs(),
+                    version: None,
+                    edits: [PositionalEdit {
+                        range: Position::new(0, 0)..Position::new(0, 0),
+                        new_text: "use foo;".to_string(),
+                    }]
+                    .to_vec(),
+                }]
+                .to_vec(),
+                resource_operations: Vec::new(),
+            }),
+            unresolved: None,
+            ..unresolved.clone()
+        };
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("".to_string())),
+            App(ReceiveCodeActions([unresolved.clone()].to_vec())),
+            App(HandleKeyEvent(key!("enter"))),
+            Expect(ExpectKind::LspRequestSent(FromEditor::CodeActionResolve {
+                params: RequestParams {
+                    path: s.main_rs(),
+                    position: Position::new(0, 0),
+                    context: Default::default(),
+                },
+                code_action: unresolved,
+            })),
+            App(HandleLspNotification(LspNotification::CodeActionResolved(
+                resolved,
+            ))),
+            Expect(FileContent(s.main_rs(), "use foo;".to_string())),
+        ])
+    })
+}
+
 #[test]
 fn opening_new_file_should_replace_current_window() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -2160,6 +2282,46 @@ fn dropdown_can_only_be_rendered_on_suggestive_editor_or_prompt() -> anyhow::Res
     })
 }
 
+#[test]
+fn completion_of_an_outdated_version_of_its_document_is_discarded() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let received_completion = |document_version| {
+            App(HandleLspNotification(LspNotification::Completion(
+                crate::lsp::process::ResponseContext {
+                    path: Some(s.main_rs()),
+                    document_version: Some(document_version),
+                    ..Default::default()
+                },
+                Completion {
+                    trigger_characters: vec![".".to_string()],
+                    items: Some(CompletionItem::from_label(
+                        "Spongebob squarepants".to_string(),
+                    ))
+                    .into_iter()
+                    .map(|item| item.into())
+                    .collect(),
+                },
+            )))
+        };
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(EnterInsertMode(Direction::Start)),
+            Editor(Insert("S".to_owned())),
+            SuggestiveEditor(DispatchSuggestiveEditor::CompletionFilter(
+                SuggestiveEditorFilter::CurrentWord,
+            )),
+            received_completion(0),
+            Expect(ComponentCount(1)),
+            received_completion(i32::MAX),
+            Expect(ComponentCount(2)),
+        ])
+    })
+}
+
 #[test]
 fn only_children_of_root_can_remove_all_other_components() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -2279,6 +2441,7 @@ fn workspace_edit() -> anyhow::Result<()> {
             App(Dispatch::ApplyWorkspaceEdit(WorkspaceEdit {
                 edits: [TextDocumentEdit {
                     path: s.main_rs(),
+                    version: None,
                     edits: [PositionalEdit {
                         range: Position::new(0, 0)..Position::new(0, 0),
                         new_text: "hello ".to_string(),
@@ -2326,6 +2489,160 @@ fn request_signature_help() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn requesting_calls_prepares_the_call_hierarchy_item_at_the_cursor() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let item = lsp_types::CallHierarchyItem {
+            name: "main".to_string(),
+            kind: lsp_types::SymbolKind::FUNCTION,
+            tags: None,
+            detail: None,
+            uri: Url::from_file_path(s.main_rs().display_absolute()).unwrap(),
+            range: lsp_types::Range::new(
+                lsp_types::Position::new(0, 0),
+                lsp_types::Position::new(0, 12),
+            ),
+            selection_range: lsp_types::Range::new(
+                lsp_types::Position::new(0, 3),
+                lsp_types::Position::new(0, 7),
+            ),
+            data: None,
+        };
+        let params = |description: &str| RequestParams {
+            path: s.main_rs(),
+            position: Position::new(0, 3),
+            context: ResponseContext {
+                scope: Some(Scope::Global),
+                description: Some(description.to_string()),
+                ..Default::default()
+            },
+        };
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("fn main() {}".to_string())),
+            Editor(MatchLiteral("main".to_string())),
+            App(RequestCalls {
+                direction: CallDirection::Incoming,
+                scope: Scope::Global,
+            }),
+            Expect(ExpectKind::LspRequestSent(
+                FromEditor::TextDocumentPrepareCallHierarchy(params("Call hierarchy")),
+            )),
+            App(HandleLspNotification(LspNotification::CallHierarchyItems(
+                params("Call hierarchy").context,
+                [item.clone().try_into().unwrap()].to_vec(),
+            ))),
+            Expect(ExpectKind::LspRequestSent(
+                FromEditor::CallHierarchyIncomingCalls {
+                    params: params("Incoming calls"),
+                    item: item.clone(),
+                },
+            )),
+        ])
+    })
+}
+
+#[test]
+fn rename_prompt_prefers_placeholder_of_prepare_rename_response() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("fn foo() {}".to_string())),
+            App(HandleLspNotification(
+                LspNotification::PrepareRenameResponse(
+                    crate::lsp::prepare_rename_response::PrepareRenameResponse {
+                        range: Some(Position::new(0, 3)..Position::new(0, 6)),
+                        placeholder: Some("bar".to_string()),
+                    },
+                ),
+            )),
+            Expect(CurrentComponentContent("bar")),
+        ])
+    })
+}
+
+#[test]
+fn closing_file_should_notify_lsp() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            App(CloseCurrentWindow),
+            Expect(ExpectKind::LspRequestSent(
+                FromEditor::TextDocumentDidClose {
+                    file_path: s.main_rs(),
+                },
+            )),
+        ])
+    })
+}
+
+#[test]
+fn formatting_edits_are_applied_without_saving() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("fn  main( ) {}".to_string())),
+            Editor(Save),
+            App(HandleLspNotification(LspNotification::DocumentFormatting(
+                crate::lsp::workspace_edit::TextDocumentEdit {
+                    path: s.main_rs(),
+                    version: None,
+                    edits: [
+                        PositionalEdit {
+                            range: Position::new(0, 2)..Position::new(0, 4),
+                            new_text: " ".to_string(),
+                        },
+                        PositionalEdit {
+                            range: Position::new(0, 9)..Position::new(0, 10),
+                            new_text: "".to_string(),
+                        },
+                    ]
+                    .to_vec(),
+                },
+            ))),
+            Expect(CurrentComponentContent("fn main() {}")),
+            Expect(EditorIsDirty()),
+        ])
+    })
+}
+
+#[test]
+fn request_formatting() -> anyhow::Result<()> {
+    execute_test(|s| {
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            App(RequestFormatting),
+            Expect(ExpectKind::LspRequestSent(
+                FromEditor::TextDocumentFormatting(RequestParams {
+                    path: s.main_rs(),
+                    position: Position::new(0, 0),
+                    context: Default::default(),
+                }),
+            )),
+        ])
+    })
+}
+
 #[test]
 /// These JSON files will be used in docs
 fn export_keymaps_json() {
@@ -2591,6 +2908,76 @@ fn test_navigate_back_from_quickfix_list() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn inlay_hints_are_rendered_at_the_end_of_their_line() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let hint = |line, column, label: &str, padding_left| InlayHint {
+            position: Position::new(line, column),
+            label: label.to_string(),
+            padding_left,
+            padding_right: false,
+        };
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("let x = 1;\nfoo(2);".to_string())),
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 30,
+                height: 3,
+            })),
+            App(HandleLspNotification(LspNotification::InlayHints {
+                path: s.main_rs(),
+                hints: vec![hint(0, 5, ": i32", false), hint(1, 4, "x:", true)],
+            })),
+            Expect(EditorGrid("🦀  main.rs\n1│█et x = 1; : i32\n2│foo(2);  x:")),
+        ])
+    })
+}
+
+#[test]
+fn document_highlights_are_rendered_by_kind() -> anyhow::Result<()> {
+    execute_test(|s| {
+        let highlight = |column, kind| DocumentHighlight {
+            range: Position::new(0, column)..Position::new(0, column + 1),
+            kind,
+        };
+        Box::new([
+            App(OpenFile {
+                path: s.main_rs(),
+                owner: BufferOwner::User,
+                focus: true,
+            }),
+            Editor(SetContent("let x = x;".to_string())),
+            Editor(SetRectangle(Rectangle {
+                origin: Position::default(),
+                width: 20,
+                height: 2,
+            })),
+            App(HandleLspNotification(LspNotification::DocumentHighlights {
+                path: s.main_rs(),
+                highlights: vec![
+                    highlight(4, DocumentHighlightKind::Write),
+                    highlight(8, DocumentHighlightKind::Read),
+                ],
+            })),
+            Expect(EditorGrid("🦀  main.rs\n1│█et x = x;")),
+            // The first row is the title, and each line starts with its line number
+            Expect(GridCellStyleKey(
+                Position::new(1, 6),
+                Some(StyleKey::UiDocumentHighlightWrite),
+            )),
+            Expect(GridCellStyleKey(
+                Position::new(1, 10),
+                Some(StyleKey::UiDocumentHighlightRead),
+            )),
+        ])
+    })
+}
+
 #[test]
 fn mark_files_tabline_wrapping_no_word_break() -> anyhow::Result<()> {
     execute_test(|s| {
@@ -130,6 +130,43 @@ pub(crate) fn from_url(name: &'static str, url: &'static str) -> anyhow::Result<
         .ok_or_else(|| anyhow::anyhow!("could not find theme '{}'", name))
 }
 
+/// Loads the first theme of a Zed theme family file, see https://zed.dev/docs/themes.
+///
+/// Unlike the downloaded themes, invalid colors are not ignored,
+/// so that mistakes in the file are reported.
+pub(crate) fn from_file(path: &std::path::Path) -> anyhow::Result<Theme> {
+    let json_str = std::fs::read_to_string(path)
+        .map_err(|error| anyhow::anyhow!("Cannot read theme file {path:?} due to: {error}"))?;
+    let manifest: ZedThemeManiftest = serde_json5::from_str(&json_str)
+        .map_err(|error| anyhow::anyhow!("Cannot parse theme file {path:?} due to:\n{error:#?}"))?;
+    let theme = manifest
+        .themes
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("The theme file {path:?} does not contain any theme"))?;
+    validate_colors("style", &serde_json::to_value(&theme.style)?)
+        .map_err(|error| anyhow::anyhow!("Invalid theme file {path:?}: {error}"))?;
+    Ok(from_theme_content(theme))
+}
+
+/// Every string of a theme style is a color, except for font styles.
+fn validate_colors(field: &str, value: &serde_json::Value) -> anyhow::Result<()> {
+    match value {
+        serde_json::Value::String(hex) => Color::from_hex(hex)
+            .map(|_| ())
+            .map_err(|_| anyhow::anyhow!("`{field}` should be a hex color, but got {hex:?}")),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, value)| validate_colors(&format!("{field}[{index}]"), value)),
+        serde_json::Value::Object(map) => map
+            .iter()
+            .filter(|(key, _)| key.as_str() != "font_style")
+            .try_for_each(|(key, value)| validate_colors(&format!("{field}.{key}"), value)),
+        _ => Ok(()),
+    }
+}
+
 fn from_theme_content(theme: ThemeContent) -> Theme {
     let background = theme
         .style
@@ -266,6 +303,14 @@ fn from_theme_content(theme: ThemeContent) -> Theme {
                 .set_some_background_color(from_some_hex(theme.style.conflict_background)),
             possible_selection_background: from_some_hex(theme.style.search_match_background)
                 .unwrap_or_default(),
+            document_highlight_read_background: from_some_hex(
+                theme.style.editor_document_highlight_read_background,
+            )
+            .unwrap_or_default(),
+            document_highlight_write_background: from_some_hex(
+                theme.style.editor_document_highlight_write_background,
+            )
+            .unwrap_or_default(),
             keymap_hint: Style::new().underline(text_accent),
             keymap_key: Style::new().bold().foreground_color(text_accent),
             keymap_arrow: Style::new().set_some_foreground_color(
@@ -295,12 +340,60 @@ fn from_theme_content(theme: ThemeContent) -> Theme {
         } else {
             super::HunkStyles::dark()
         },
+        color_depth: super::ColorDepth::default(),
     }
 }
 
 #[cfg(test)]
 mod test_from_zed_theme {
-    use crate::themes::Theme;
+    use crate::themes::{HighlightName, Theme};
+    use my_proc_macros::hex;
+    use std::str::FromStr;
+
+    fn theme_file(syntax_color: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            format!(
+                r#"{{
+                  "name": "Mine",
+                  "author": "Me",
+                  "themes": [{{
+                    "name": "Mine Dark",
+                    "appearance": "dark",
+                    "style": {{
+                      "editor.background": "#000000",
+                      "syntax": {{ "keyword": {{ "color": "{syntax_color}", "font_style": "italic" }} }}
+                    }}
+                  }}]
+                }}"#
+            ),
+        )
+        .unwrap();
+        file
+    }
+
+    #[test]
+    fn from_file() -> anyhow::Result<()> {
+        let theme = super::from_file(theme_file("#ff0000").path())?;
+        assert_eq!(theme.name, "Mine Dark");
+        assert_eq!(
+            theme
+                .syntax
+                .get_style(&HighlightName::from_str("keyword.return")?)
+                .and_then(|style| style.foreground_color),
+            Some(hex!("#ff0000"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn from_file_reports_invalid_color() {
+        let error = super::from_file(theme_file("red").path()).unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with("`style.syntax.keyword.color` should be a hex color, but got \"red\""));
+    }
 
     #[test]
     fn ensure_all_zed_themes_parse() -> anyhow::Result<()> {
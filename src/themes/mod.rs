@@ -11,7 +11,11 @@ use strum::IntoEnumIterator as _;
 pub(crate) use vscode_dark::vscode_dark;
 pub(crate) use vscode_light::vscode_light;
 
-use crate::{env::parse_env, grid::StyleKey, style::Style};
+use crate::{
+    env::parse_env,
+    grid::{CellLine, StyleKey},
+    style::Style,
+};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) struct Theme {
@@ -20,6 +24,48 @@ pub(crate) struct Theme {
     pub(crate) ui: UiStyles,
     pub(crate) diagnostic: DiagnosticStyles,
     pub(crate) hunk: HunkStyles,
+    /// Configured by the `KI_EDITOR_COLOR_DEPTH` environment variable, see `Theme::resolve`.
+    pub(crate) color_depth: ColorDepth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ThemeVariant {
+    Light,
+    Dark,
+}
+
+/// The number of colors supported by the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    #[default]
+    TrueColor,
+}
+
+impl ColorDepth {
+    const ALL: [ColorDepth; 3] = [
+        ColorDepth::Ansi16,
+        ColorDepth::Ansi256,
+        ColorDepth::TrueColor,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ColorDepth::Ansi16 => "ansi16",
+            ColorDepth::Ansi256 => "ansi256",
+            ColorDepth::TrueColor => "truecolor",
+        }
+    }
+}
+
+/// A `Style` whose colors are opaque and down-sampled to a `ColorDepth`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ResolvedStyle {
+    pub(crate) foreground_color: Option<crossterm::style::Color>,
+    pub(crate) background_color: Option<crossterm::style::Color>,
+    pub(crate) line: Option<CellLine>,
+    pub(crate) is_bold: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -51,6 +97,52 @@ impl HunkStyles {
 }
 
 impl Theme {
+    /// Loads a theme from a Zed theme file.
+    pub(crate) fn from_file(path: &std::path::Path) -> anyhow::Result<Theme> {
+        from_zed_theme::from_file(path)
+    }
+
+    /// The default theme for dark backgrounds.
+    pub(crate) fn dark() -> Theme {
+        vscode_dark()
+    }
+
+    /// The default theme for light backgrounds.
+    pub(crate) fn light() -> Theme {
+        vscode_light()
+    }
+
+    /// Decided by the brightness of the background color.
+    pub(crate) fn variant(&self) -> ThemeVariant {
+        if self.ui.background_color.luminance() > 0.5 {
+            ThemeVariant::Light
+        } else {
+            ThemeVariant::Dark
+        }
+    }
+
+    /// Translucent colors are blended with the background of the theme before being
+    /// down-sampled, because terminals have no notion of alpha.
+    pub(crate) fn resolve(&self, style: Style, depth: ColorDepth) -> ResolvedStyle {
+        let resolve = |color: Color| color.apply_alpha(self.ui.background_color).quantize(depth);
+        ResolvedStyle {
+            foreground_color: style.foreground_color.map(resolve),
+            background_color: style.background_color.map(resolve),
+            line: style.line,
+            is_bold: style.is_bold,
+        }
+    }
+
+    /// The style of a Tree-sitter capture name such as `@function.method`,
+    /// which falls back to its parents, such as `@function`,
+    /// if neither the capture name nor its style is known.
+    #[allow(dead_code)]
+    pub(crate) fn style_for_capture(&self, capture: &str) -> Style {
+        HighlightName::from_capture(capture)
+            .and_then(|name| self.syntax.get_style(&name))
+            .unwrap_or_default()
+    }
+
     pub(crate) fn get_style(&self, source: &StyleKey) -> Style {
         match source {
             StyleKey::UiMark => self.ui.mark,
@@ -72,6 +164,12 @@ impl Theme {
             StyleKey::UiPossibleSelection => {
                 Style::new().background_color(self.ui.possible_selection_background)
             }
+            StyleKey::UiDocumentHighlightRead => {
+                Style::new().background_color(self.ui.document_highlight_read_background)
+            }
+            StyleKey::UiDocumentHighlightWrite => {
+                Style::new().background_color(self.ui.document_highlight_write_background)
+            }
             StyleKey::DiagnosticsHint => self.diagnostic.hint,
             StyleKey::DiagnosticsError => self.diagnostic.error,
             StyleKey::DiagnosticsWarning => self.diagnostic.warning,
@@ -117,6 +215,11 @@ impl Theme {
 
 impl Default for Theme {
     fn default() -> Self {
+        if let Ok(path) = std::env::var("KI_EDITOR_THEME_FILE") {
+            return Theme::from_file(std::path::Path::new(&path)).unwrap_or_else(|error| {
+                panic!("Please update your KI_EDITOR_THEME_FILE environment variable: {error}")
+            });
+        }
         let default_theme_descriptor = parse_env(
             "KI_EDITOR_THEME",
             &theme_descriptor::all(),
@@ -124,7 +227,15 @@ impl Default for Theme {
             theme_descriptor::ThemeDescriptor::default(),
         );
 
-        default_theme_descriptor.to_theme()
+        Theme {
+            color_depth: parse_env(
+                "KI_EDITOR_COLOR_DEPTH",
+                &ColorDepth::ALL,
+                |depth| depth.name(),
+                ColorDepth::default(),
+            ),
+            ..default_theme_descriptor.to_theme()
+        }
     }
 }
 
@@ -167,6 +278,10 @@ pub(crate) struct UiStyles {
     pub(crate) secondary_selection_background: Color,
     pub(crate) secondary_selection_anchor_background: Color,
     pub(crate) possible_selection_background: Color,
+    /// For the textual and read occurrences of the symbol under the cursor
+    pub(crate) document_highlight_read_background: Color,
+    /// For the write occurrences of the symbol under the cursor
+    pub(crate) document_highlight_write_background: Color,
     pub(crate) secondary_selection_primary_cursor: Style,
     pub(crate) secondary_selection_secondary_cursor: Style,
     pub(crate) line_number: Style,
@@ -260,6 +375,23 @@ mod test_syntax_styles {
             None
         );
     }
+
+    #[test]
+    fn style_for_capture_falls_back_to_known_prefix() {
+        let theme = Theme {
+            syntax: syntax_style(),
+            ..vscode_light()
+        };
+        assert_eq!(
+            theme.style_for_capture("@string.special.symbol"),
+            fg(hex!("#e50000"))
+        );
+        assert_eq!(
+            theme.style_for_capture("string.unknown.rust"),
+            fg(hex!("#267f99"))
+        );
+        assert_eq!(theme.style_for_capture("@unknown"), Style::default());
+    }
 }
 
 /// Refer https://github.com/nvim-treesitter/nvim-treesitter/blob/23ba63028c6acca29be6462c0a291fc4a1b9eae8/CONTRIBUTING.md#highlights
@@ -467,6 +599,18 @@ pub enum HighlightName {
     TagDelimiter,
 }
 impl HighlightName {
+    /// Returns the longest known prefix of `capture`, of which the leading `@` is optional,
+    /// for example `@function.method.call.rust` becomes `function.method.call`.
+    pub(crate) fn from_capture(capture: &str) -> Option<HighlightName> {
+        let mut capture = capture.strip_prefix('@').unwrap_or(capture);
+        loop {
+            if let Ok(name) = capture.parse() {
+                return Some(name);
+            }
+            capture = &capture[..capture.rfind('.')?];
+        }
+    }
+
     fn parent(&self) -> Option<HighlightName> {
         // We hardcode the branch instead of deriving it from the string
         // via separating the highlight name by period symbol
@@ -682,14 +826,17 @@ impl Color {
         Ok(Color { r, g, b, a })
     }
 
+    /// Between 0 (black) and 1 (white).
+    fn luminance(&self) -> f64 {
+        (0.299 * (self.r as f64) + 0.587 * (self.g as f64) + 0.114 * (self.b as f64)) / 255.0
+    }
+
     /// Refer https://docs.rs/colorsys/latest/src/colorsys/rgb/transform.rs.html#61
     /// Refer https://sl.bing.net/b69EKNHqrLw
     pub(crate) fn get_contrasting_color(&self) -> Color {
-        let Color { r, g, b, a } = self;
-        // Calculate the luminance of the color
-        let luminance = (0.299 * (*r as f64) + 0.587 * (*g as f64) + 0.114 * (*b as f64)) / 255.0;
+        let a = &self.a;
         // Return black for bright colors, white for dark colors
-        if luminance > 0.5 {
+        if self.luminance() > 0.5 {
             Color {
                 r: 0,
                 g: 0,
@@ -707,6 +854,88 @@ impl Color {
     }
 }
 
+/// The default colors of xterm, which are the first 16 colors of the 256-color palette.
+const ANSI_16_COLORS: [Color; 16] = [
+    Color::new(0, 0, 0),
+    Color::new(205, 0, 0),
+    Color::new(0, 205, 0),
+    Color::new(205, 205, 0),
+    Color::new(0, 0, 238),
+    Color::new(205, 0, 205),
+    Color::new(0, 205, 205),
+    Color::new(229, 229, 229),
+    Color::new(127, 127, 127),
+    Color::new(255, 0, 0),
+    Color::new(0, 255, 0),
+    Color::new(255, 255, 0),
+    Color::new(92, 92, 255),
+    Color::new(255, 0, 255),
+    Color::new(0, 255, 255),
+    Color::new(255, 255, 255),
+];
+
+/// The levels of each channel of the 6x6x6 color cube of the 256-color palette.
+const ANSI_256_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl Color {
+    fn distance(&self, other: &Color) -> u32 {
+        [(self.r, other.r), (self.g, other.g), (self.b, other.b)]
+            .into_iter()
+            .map(|(a, b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    }
+
+    /// Returns the nearest color supported by `depth`, the alpha channel is ignored.
+    pub(crate) fn quantize(&self, depth: ColorDepth) -> crossterm::style::Color {
+        match depth {
+            ColorDepth::TrueColor => (*self).into(),
+            ColorDepth::Ansi256 => crossterm::style::Color::AnsiValue(self.nearest_ansi_256()),
+            ColorDepth::Ansi16 => crossterm::style::Color::AnsiValue(self.nearest_ansi_16()),
+        }
+    }
+
+    fn nearest_ansi_16(&self) -> u8 {
+        (0..ANSI_16_COLORS.len())
+            .min_by_key(|index| self.distance(&ANSI_16_COLORS[*index]))
+            .unwrap_or_default() as u8
+    }
+
+    /// The basic 16 colors are skipped, because terminals often customize them.
+    fn nearest_ansi_256(&self) -> u8 {
+        let nearest_level = |channel: u8| {
+            (0..ANSI_256_CUBE_LEVELS.len())
+                .min_by_key(|index| ANSI_256_CUBE_LEVELS[*index].abs_diff(channel))
+                .unwrap_or_default()
+        };
+        let (r, g, b) = (
+            nearest_level(self.r),
+            nearest_level(self.g),
+            nearest_level(self.b),
+        );
+        let cube = (
+            16 + 36 * r + 6 * g + b,
+            Color::new(
+                ANSI_256_CUBE_LEVELS[r],
+                ANSI_256_CUBE_LEVELS[g],
+                ANSI_256_CUBE_LEVELS[b],
+            ),
+        );
+        // The grayscale ramp ranges from 8 to 238, in steps of 10
+        let average = (self.r as usize + self.g as usize + self.b as usize) / 3;
+        let gray_step = (average.saturating_sub(3) / 10).min(23);
+        let gray_level = 8 + 10 * gray_step as u8;
+        let gray = (
+            232 + gray_step,
+            Color::new(gray_level, gray_level, gray_level),
+        );
+        [cube, gray]
+            .into_iter()
+            .min_by_key(|(_, color)| self.distance(color))
+            .map(|(index, _)| index as u8)
+            .unwrap_or_default()
+    }
+}
+
 impl From<Color> for crossterm::style::Color {
     fn from(val: Color) -> Self {
         crossterm::style::Color::Rgb {
@@ -716,3 +945,56 @@ impl From<Color> for crossterm::style::Color {
         }
     }
 }
+
+#[cfg(test)]
+mod test_color {
+    use super::*;
+    use crossterm::style::Color::AnsiValue;
+
+    #[test]
+    fn quantize_to_the_nearest_palette_color() {
+        let quantize = |color: Color| {
+            (
+                color.quantize(ColorDepth::Ansi256),
+                color.quantize(ColorDepth::Ansi16),
+            )
+        };
+        assert_eq!(quantize(Color::new(0, 0, 0)), (AnsiValue(16), AnsiValue(0)));
+        assert_eq!(
+            quantize(Color::new(255, 0, 0)),
+            (AnsiValue(196), AnsiValue(9))
+        );
+        assert_eq!(
+            quantize(Color::new(128, 128, 128)),
+            (AnsiValue(244), AnsiValue(8))
+        );
+        assert_eq!(
+            quantize(Color::new(0x55, 0xa8, 0xf8)),
+            (AnsiValue(75), AnsiValue(12))
+        );
+        assert_eq!(
+            Color::new(1, 2, 3).quantize(ColorDepth::TrueColor),
+            crossterm::style::Color::Rgb { r: 1, g: 2, b: 3 }
+        );
+    }
+
+    #[test]
+    fn translucent_colors_are_blended_with_the_theme_background() {
+        let theme = Theme {
+            ui: UiStyles {
+                background_color: Color::new(10, 20, 30),
+                ..vscode_dark().ui
+            },
+            ..vscode_dark()
+        };
+        let style = Style::new().background_color(Color::from_hex("#ffffff00").unwrap());
+        assert_eq!(
+            theme.resolve(style, ColorDepth::TrueColor).background_color,
+            Some(crossterm::style::Color::Rgb {
+                r: 10,
+                g: 20,
+                b: 30
+            })
+        );
+    }
+}
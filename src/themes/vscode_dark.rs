@@ -62,6 +62,8 @@ pub fn vscode_dark() -> Theme {
                 .foreground_color(hex!("#858585")),
             mark: Style::new().background_color(hex!("#ffcc00")),
             possible_selection_background: hex!("#5C3521"),
+            document_highlight_read_background: hex!("#3A3D41"),
+            document_highlight_write_background: hex!("#0E3A5C"),
             keymap_hint: Style::new().underline(hex!("#af00db")),
             keymap_key: Style::new().bold().foreground_color(hex!("#af00db")),
             keymap_arrow: Style::new().foreground_color(hex!("#808080")),
@@ -69,5 +71,6 @@ pub fn vscode_dark() -> Theme {
         },
         diagnostic: DiagnosticStyles::default(),
         hunk: super::HunkStyles::dark(),
+        color_depth: super::ColorDepth::default(),
     }
 }
@@ -62,6 +62,8 @@ pub fn vscode_light() -> Theme {
                 .background_color(hex!("#ffffff")),
             mark: Style::new().background_color(hex!("#ffcc00")),
             possible_selection_background: hex!("#f6f7b2"),
+            document_highlight_read_background: hex!("#e2e2e2"),
+            document_highlight_write_background: hex!("#c3d8e6"),
             keymap_hint: Style::new().underline(hex!("#af00db")),
             keymap_key: Style::new().bold().foreground_color(hex!("#af00db")),
             keymap_arrow: Style::new().foreground_color(hex!("#808080")),
@@ -69,5 +71,6 @@ pub fn vscode_light() -> Theme {
         },
         diagnostic: DiagnosticStyles::default(),
         hunk: super::HunkStyles::light(),
+        color_depth: super::ColorDepth::default(),
     }
 }
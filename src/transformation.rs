@@ -1,6 +1,7 @@
 use convert_case::Casing;
 use itertools::Itertools;
 use shared::process_command::ProcessCommand;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     clipboard::CopiedTexts, selection_mode::NamingConventionAgnostic, soft_wrap::soft_wrap,
@@ -49,6 +50,56 @@ impl std::fmt::Display for Transformation {
     }
 }
 
+/// Case conversions that, unlike `Transformation::Case`, preserve the separators of the text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(dead_code)]
+pub(crate) enum CaseTransform {
+    Upper,
+    Lower,
+    /// Uppercase the first letter of every word, and lowercase the rest
+    Title,
+    /// Converts `foo_bar` to `fooBar`, and `fooBar` to `foo_bar`
+    ToggleSnakeCamel,
+}
+
+impl CaseTransform {
+    /// The length of the result might differ from `string`,
+    /// for example the uppercase of `ß` is `SS`.
+    pub(crate) fn apply(&self, string: &str) -> String {
+        match self {
+            CaseTransform::Upper => string.to_uppercase(),
+            CaseTransform::Lower => string.to_lowercase(),
+            CaseTransform::Title => string
+                .split_word_bounds()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| {
+                            first
+                                .to_uppercase()
+                                .chain(chars.flat_map(char::to_lowercase))
+                                .collect::<String>()
+                        })
+                        .unwrap_or_default()
+                })
+                .collect(),
+            CaseTransform::ToggleSnakeCamel => string
+                .split_word_bounds()
+                .map(|word| {
+                    if word.contains('_') {
+                        word.to_case(convert_case::Case::Camel)
+                    } else if word.chars().any(char::is_uppercase) {
+                        word.to_case(convert_case::Case::Snake)
+                    } else {
+                        word.to_string()
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct MyRegex(pub(crate) fancy_regex::Regex);
 
@@ -102,7 +153,21 @@ impl Transformation {
 
 #[cfg(test)]
 mod test_transformation {
-    use super::Transformation;
+    use super::{CaseTransform, Transformation};
+
+    #[test]
+    fn case_transform() {
+        assert_eq!(CaseTransform::Upper.apply("straße"), "STRASSE");
+        assert_eq!(CaseTransform::Lower.apply("ÉCOLE"), "école");
+        assert_eq!(
+            CaseTransform::Title.apply("hello wORLD, foo_bar"),
+            "Hello World, Foo_bar"
+        );
+        assert_eq!(
+            CaseTransform::ToggleSnakeCamel.apply("let foo_bar = fooBar;"),
+            "let fooBar = foo_bar;"
+        );
+    }
 
     #[test]
     fn join() {